@@ -0,0 +1,1383 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{error, fmt, fs};
+
+use anyhow::{Context, Result, anyhow, bail};
+use itertools::Itertools;
+
+use crate::database::{EpicFilter, JiraDatabase, StoryFilter};
+use crate::models::{
+    Date,
+    Epic,
+    Priority,
+    Project,
+    Rank,
+    Schedule,
+    Status,
+    Story,
+    User,
+    normalize_label,
+};
+
+mod completions;
+mod output;
+mod plan;
+pub use completions::{Shell, complete_board};
+pub use output::{EpicRecord, OutputFormat, ProjectRecord, StoryRecord, UserRecord};
+use output::{Lookup, Record, write_list, write_record};
+
+pub const USAGE: &str = "\
+Usage: jiraffe [--db <path>] [<command>]
+
+Without a command, jiraffe opens the interactive board.
+
+Commands:
+  project list [--output <format>]
+  project create --name <name> --key <KEY> [--description <text>]
+  user list [--output <format>]
+  user create --username <username> --name <name>
+  label list
+  label rename <label> <new label>
+  epic list [--project <KEY>] [--status <status>] [--priority <priority>]
+            [--owner <username>] [--label <label>] [--overdue] [--output <format>]
+  epic show <key> [--output <format>]
+  epic create [--project <KEY>] --name <name> [--description <text>]
+              [--priority <priority>]
+  epic status <key> <status>
+  epic assign <key> <username>
+  epic unassign <key>
+  epic label <key> <label>
+  epic unlabel <key> <label>
+  epic schedule <key> [--start <date>] [--due <date>]
+  epic delete <key> --yes
+  story list [--project <KEY>] [--epic <key>] [--status <status>]
+             [--priority <priority>] [--assignee <username> | --mine]
+             [--label <label>] [--overdue] [--output <format>]
+  story show <key> [--output <format>]
+  story create --epic <key> --name <name> [--description <text>]
+               [--priority <priority>]
+  story status <key> <status>
+  story move <key> --epic <key>
+  story rank <key> <up|down|top>
+  story assign <key> <username>
+  story unassign <key>
+  story label <key> <label>
+  story unlabel <key> <label>
+  story schedule <key> [--start <date>] [--due <date>]
+  story delete <key> --yes
+  apply [<plan file>]
+  check [--repair]
+  completions <bash|zsh|fish>
+
+Epics and stories are given by issue key (PAY-12) or id (12), statuses as open,
+in-progress, resolved or closed, and priorities as highest, high, medium (the
+default), low or lowest. --project can be left out while there is only one
+project. List and show commands print a table by default, or records
+for scripts with --output json or --output csv.
+
+Epics have an owner and stories an assignee and a reporter, picked from the
+users of `user create`. New stories are reported by the current user, taken
+from JIRAFFE_USER, `user` in the config file or $USER, and --mine lists the
+stories assigned to them.
+
+Labels are stored in lower case with dashes for spaces, so `Tech Debt` and
+tech-debt are the same label. label rename renames a label on every epic and
+story, including those in the trash.
+
+Dates are given as YYYY-MM-DD, today, or a number of days or weeks from today
+such as +3d or -2w, and none clears them. An epic or story is overdue when it is
+past its due date without being resolved or closed, and --overdue lists those.
+
+apply runs the create, status, move, rank, assign, label, unlabel, schedule,
+delete and label rename commands in a plan file (or stdin), one per line, all in
+one transaction. `name = epic create ...` keeps the new key for
+later lines to use as $name.
+
+completions prints a script that completes commands, options and the keys on
+the board, e.g. eval \"$(jiraffe completions bash)\" in ~/.bashrc.
+
+Exit status is 0 on success, 1 if the command failed and 2 on a usage error.";
+
+/// The exit status for a command line that couldn't be parsed.
+pub const USAGE_ERROR: u8 = 2;
+
+/// Options that don't take a value.
+const SWITCHES: [&str; 5] = ["help", "mine", "overdue", "repair", "yes"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    Check {
+        repair: bool,
+    },
+    ListProjects {
+        output: OutputFormat,
+    },
+    CreateProject {
+        name:        String,
+        key:         String,
+        description: String,
+    },
+    ListUsers {
+        output: OutputFormat,
+    },
+    CreateUser {
+        username: String,
+        name:     String,
+    },
+    ListLabels,
+    RenameLabel {
+        from: String,
+        to:   String,
+    },
+    ListEpics {
+        project:  Option<String>,
+        status:   Option<Status>,
+        priority: Option<Priority>,
+        owner:    Option<String>,
+        label:    Option<String>,
+        overdue:  bool,
+        output:   OutputFormat,
+    },
+    ShowEpic {
+        key:    String,
+        output: OutputFormat,
+    },
+    CreateEpic {
+        project:     Option<String>,
+        name:        String,
+        description: String,
+        priority:    Priority,
+    },
+    UpdateEpicStatus {
+        key:    String,
+        status: Status,
+    },
+    /// Leaves the epic without an owner for `None`.
+    AssignEpic {
+        key:   String,
+        owner: Option<String>,
+    },
+    AddEpicLabel {
+        key:   String,
+        label: String,
+    },
+    RemoveEpicLabel {
+        key:   String,
+        label: String,
+    },
+    ScheduleEpic {
+        key:    String,
+        change: ScheduleChange,
+    },
+    DeleteEpic {
+        key: String,
+    },
+    ListStories {
+        project:  Option<String>,
+        epic:     Option<String>,
+        status:   Option<Status>,
+        priority: Option<Priority>,
+        assignee: Option<String>,
+        /// Only the stories assigned to the current user.
+        mine:     bool,
+        label:    Option<String>,
+        overdue:  bool,
+        output:   OutputFormat,
+    },
+    ShowStory {
+        key:    String,
+        output: OutputFormat,
+    },
+    CreateStory {
+        epic:        String,
+        name:        String,
+        description: String,
+        priority:    Priority,
+    },
+    UpdateStoryStatus {
+        key:    String,
+        status: Status,
+    },
+    MoveStory {
+        key:  String,
+        epic: String,
+    },
+    RankStory {
+        key:  String,
+        rank: Rank,
+    },
+    /// Leaves the story unassigned for `None`.
+    AssignStory {
+        key:      String,
+        assignee: Option<String>,
+    },
+    AddStoryLabel {
+        key:   String,
+        label: String,
+    },
+    RemoveStoryLabel {
+        key:   String,
+        label: String,
+    },
+    ScheduleStory {
+        key:    String,
+        change: ScheduleChange,
+    },
+    DeleteStory {
+        key: String,
+    },
+    /// Reads the plan from stdin when no file is given.
+    Apply {
+        plan: Option<PathBuf>,
+    },
+    Completions {
+        shell: Shell,
+    },
+    /// Called by the completion scripts with the words typed so far, the
+    /// last being the one to complete.
+    Complete {
+        words: Vec<String>,
+    },
+}
+
+impl Command {
+    /// Whether the command changes the board, which is all a plan may hold.
+    pub(crate) fn is_change(&self) -> bool {
+        matches!(
+            self,
+            Command::UpdateEpicStatus { .. }
+                | Command::AssignEpic { .. }
+                | Command::AddEpicLabel { .. }
+                | Command::RemoveEpicLabel { .. }
+                | Command::ScheduleEpic { .. }
+                | Command::DeleteEpic { .. }
+                | Command::UpdateStoryStatus { .. }
+                | Command::MoveStory { .. }
+                | Command::RankStory { .. }
+                | Command::AssignStory { .. }
+                | Command::AddStoryLabel { .. }
+                | Command::RemoveStoryLabel { .. }
+                | Command::ScheduleStory { .. }
+                | Command::DeleteStory { .. }
+                | Command::RenameLabel { .. }
+        ) || self.is_create()
+    }
+
+    /// Whether the command creates an item and prints its key.
+    pub(crate) fn is_create(&self) -> bool {
+        matches!(
+            self,
+            Command::CreateProject { .. }
+                | Command::CreateUser { .. }
+                | Command::CreateEpic { .. }
+                | Command::CreateStory { .. }
+        )
+    }
+}
+
+/// The dates given to a schedule command. `None` keeps a date as it is and
+/// `Some(None)` clears it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleChange {
+    pub start: Option<Option<Date>>,
+    pub due:   Option<Option<Date>>,
+}
+
+impl ScheduleChange {
+    fn apply(self, current: Schedule) -> Schedule {
+        Schedule {
+            start: self.start.unwrap_or(current.start),
+            due:   self.due.unwrap_or(current.due),
+        }
+    }
+}
+
+/// A mistake on the command line, reported along with the usage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0) }
+}
+
+impl error::Error for UsageError {}
+
+/// Parses the arguments after `--db`, returning `None` when there is no
+/// command and the interactive board should open instead.
+pub fn parse(args: &[String]) -> Result<Option<Command>, UsageError> {
+    // The words to complete are a partial command line, so they are taken
+    // as they are rather than parsed.
+    if let [command, words @ ..] = args
+        && command == "complete"
+    {
+        return Ok(Some(Command::Complete { words: words.to_vec() }));
+    }
+
+    let (words, mut options) = Options::parse(args)?;
+    if options.switch("help") {
+        return Ok(Some(Command::Help));
+    }
+
+    let words = words.iter().map(String::as_str).collect::<Vec<_>>();
+    let command = match words.as_slice() {
+        [] => return options.finish().map(|()| None),
+        ["help"] => Command::Help,
+        ["check"] => Command::Check { repair: options.switch("repair") },
+        ["apply"] => Command::Apply { plan: None },
+        ["apply", plan] => Command::Apply { plan: Some(PathBuf::from(plan)) },
+        ["completions", shell] => Command::Completions {
+            shell: shell.parse().map_err(|_| {
+                UsageError(format!("Unsupported shell {shell:?}: use bash, zsh or fish"))
+            })?,
+        },
+        ["project", "list"] => Command::ListProjects { output: options.output()? },
+        ["project", "create"] => Command::CreateProject {
+            name:        options.required("name")?,
+            key:         options.required("key")?,
+            description: options.value("description").unwrap_or_default(),
+        },
+        ["user", "list"] => Command::ListUsers { output: options.output()? },
+        ["user", "create"] => Command::CreateUser {
+            username: options.required("username")?,
+            name:     options.required("name")?,
+        },
+        ["label", "list"] => Command::ListLabels,
+        ["label", "rename", from, to] => {
+            Command::RenameLabel { from: parse_label(from)?, to: parse_label(to)? }
+        },
+        ["epic", "list"] => Command::ListEpics {
+            project:  options.value("project"),
+            status:   options.status()?,
+            priority: options.priority()?,
+            owner:    options.value("owner"),
+            label:    options.label()?,
+            overdue:  options.switch("overdue"),
+            output:   options.output()?,
+        },
+        ["epic", "show", key] => {
+            Command::ShowEpic { key: key.to_string(), output: options.output()? }
+        },
+        ["epic", "create"] => Command::CreateEpic {
+            project:     options.value("project"),
+            name:        options.required("name")?,
+            description: options.value("description").unwrap_or_default(),
+            priority:    options.priority()?.unwrap_or_default(),
+        },
+        ["epic", "status", key, status] => {
+            Command::UpdateEpicStatus { key: key.to_string(), status: parse_status(status)? }
+        },
+        ["epic", "assign", key, owner] => {
+            Command::AssignEpic { key: key.to_string(), owner: Some(owner.to_string()) }
+        },
+        ["epic", "unassign", key] => {
+            Command::AssignEpic { key: key.to_string(), owner: None }
+        },
+        ["epic", "label", key, label] => {
+            Command::AddEpicLabel { key: key.to_string(), label: parse_label(label)? }
+        },
+        ["epic", "unlabel", key, label] => {
+            Command::RemoveEpicLabel { key: key.to_string(), label: parse_label(label)? }
+        },
+        ["epic", "schedule", key] => {
+            Command::ScheduleEpic { key: key.to_string(), change: options.schedule()? }
+        },
+        ["epic", "delete", key] => {
+            options.confirm(key)?;
+            Command::DeleteEpic { key: key.to_string() }
+        },
+        ["story", "list"] => {
+            let assignee = options.value("assignee");
+            let mine = options.switch("mine");
+            if mine && assignee.is_some() {
+                return Err(UsageError("--assignee and --mine can't be used together".into()));
+            }
+
+            Command::ListStories {
+                project: options.value("project"),
+                epic: options.value("epic"),
+                status: options.status()?,
+                priority: options.priority()?,
+                assignee,
+                mine,
+                label: options.label()?,
+                overdue: options.switch("overdue"),
+                output: options.output()?,
+            }
+        },
+        ["story", "show", key] => {
+            Command::ShowStory { key: key.to_string(), output: options.output()? }
+        },
+        ["story", "create"] => Command::CreateStory {
+            epic:        options.required("epic")?,
+            name:        options.required("name")?,
+            description: options.value("description").unwrap_or_default(),
+            priority:    options.priority()?.unwrap_or_default(),
+        },
+        ["story", "status", key, status] => Command::UpdateStoryStatus {
+            key:    key.to_string(),
+            status: parse_status(status)?,
+        },
+        ["story", "move", key] => {
+            Command::MoveStory { key: key.to_string(), epic: options.required("epic")? }
+        },
+        ["story", "rank", key, rank] => Command::RankStory {
+            key:  key.to_string(),
+            rank: rank.parse().map_err(|_| {
+                UsageError(format!("Invalid rank {rank:?}: use up, down or top"))
+            })?,
+        },
+        ["story", "assign", key, assignee] => Command::AssignStory {
+            key:      key.to_string(),
+            assignee: Some(assignee.to_string()),
+        },
+        ["story", "unassign", key] => {
+            Command::AssignStory { key: key.to_string(), assignee: None }
+        },
+        ["story", "label", key, label] => {
+            Command::AddStoryLabel { key: key.to_string(), label: parse_label(label)? }
+        },
+        ["story", "unlabel", key, label] => {
+            Command::RemoveStoryLabel { key: key.to_string(), label: parse_label(label)? }
+        },
+        ["story", "schedule", key] => {
+            Command::ScheduleStory { key: key.to_string(), change: options.schedule()? }
+        },
+        ["story", "delete", key] => {
+            options.confirm(key)?;
+            Command::DeleteStory { key: key.to_string() }
+        },
+        words => return Err(UsageError(format!("Unknown command: {}", words.join(" ")))),
+    };
+
+    options.finish()?;
+    Ok(Some(command))
+}
+
+/// Runs a command against the board as `user`, the current user if one is
+/// known, writing its output to `out`.
+pub fn execute(
+    db: &JiraDatabase,
+    user: Option<&str>,
+    command: Command,
+    out: &mut impl Write,
+) -> Result<ExitCode> {
+    match command {
+        Command::Help => writeln!(out, "{USAGE}")?,
+        Command::Check { repair } => return check(db, repair, out),
+        Command::Apply { plan } => {
+            let plan = match plan {
+                Some(path) => fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read file: {}", path.display()))?,
+                None => io::read_to_string(io::stdin()).context("Failed to read stdin")?,
+            };
+            plan::apply(db, user, &plan, out)?;
+        },
+        Command::Completions { shell } => write!(out, "{}", shell.script())?,
+        Command::Complete { words } => completions::complete(db, &words, out)?,
+        Command::ListProjects { output } => {
+            let lookup = Lookup::load(db)?;
+            let projects = db.list_projects()?;
+            let records = projects
+                .iter()
+                .sorted_by_key(|(_, project)| &project.key)
+                .map(|(project_id, project)| lookup.project(*project_id, project))
+                .collect_vec();
+            write_list(out, output, &records)?;
+        },
+        Command::CreateProject { name, key, description } => {
+            let key = key.trim().to_uppercase();
+            db.create_project(Project::new(name, key.clone(), description))?;
+            writeln!(out, "{key}")?;
+        },
+        Command::ListUsers { output } => {
+            let records = db
+                .list_users()?
+                .into_iter()
+                .map(|(username, user)| UserRecord { username, name: user.name })
+                .collect_vec();
+            write_list(out, output, &records)?;
+        },
+        Command::CreateUser { username, name } => {
+            db.create_user(&username, User { name })?;
+            writeln!(out, "{username}")?;
+        },
+        Command::ListLabels => {
+            for label in db.list_labels()? {
+                writeln!(out, "{label}")?;
+            }
+        },
+        Command::RenameLabel { from, to } => {
+            let renamed = db.rename_label(&from, &to)?;
+            writeln!(out, "Renamed {from} to {to} on {renamed} item(s)")?;
+        },
+        Command::ListEpics { project, status, priority, owner, label, overdue, output } => {
+            let project_id = match project {
+                Some(key) => Some(find_project(db, &key)?.0),
+                None => None,
+            };
+            let overdue_on = overdue.then(Date::today);
+            let filter = EpicFilter { project_id, status, priority, owner, label, overdue_on };
+            let epics = db.list_epics(&filter)?;
+            let lookup = Lookup::load(db)?;
+            let records = epics.iter().map(|(id, epic)| lookup.epic(*id, epic)).collect_vec();
+            write_list(out, output, &records)?;
+        },
+        Command::ShowEpic { key, output } => {
+            let epic_id = resolve_epic(db, &key)?;
+            let epic = db.get_epic(epic_id)?.ok_or_else(|| anyhow!("{key} not found!"))?;
+            let lookup = Lookup::load(db)?;
+            let record = lookup.epic(epic_id, &epic);
+            if output != OutputFormat::Table {
+                write_record(out, output, &record)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            writeln!(out, "{}: {}", record.key, record.name)?;
+            writeln!(out, "Project:     {}", record.project)?;
+            writeln!(out, "Status:      {}", epic.status)?;
+            writeln!(out, "Priority:    {}", epic.priority)?;
+            writeln!(out, "Owner:       {}", or_dash(&epic.owner))?;
+            writeln!(out, "Labels:      {}", labels_or_dash(&epic.labels))?;
+            write_dates(out, Schedule::from(&epic), epic.is_overdue(Date::today()))?;
+            writeln!(out, "Description: {}", indent_lines(&record.description))?;
+            writeln!(out, "Stories:")?;
+            let filter = StoryFilter { epic_id: Some(epic_id), ..StoryFilter::default() };
+            for (story_id, story) in db.list_stories(&filter)? {
+                write!(out, "  ")?;
+                lookup.story(story_id, &story).write_table_row(out)?;
+            }
+        },
+        Command::CreateEpic { project, name, description, priority } => {
+            let (project_id, project) = match project {
+                Some(key) => find_project(db, &key)?,
+                None => only_project(db)?,
+            };
+            let epic = Epic { priority, ..Epic::new(name, description) };
+            let epic_id = db.create_epic(epic, project_id)?;
+            writeln!(out, "{}", project.issue_key(epic_id))?;
+        },
+        Command::UpdateEpicStatus { key, status } => {
+            let epic_id = resolve_epic(db, &key)?;
+            let previous = db.update_epic_status(epic_id, status)?;
+            writeln!(out, "{key}: {previous} -> {status}")?;
+        },
+        Command::AssignEpic { key, owner } => {
+            let epic_id = resolve_epic(db, &key)?;
+            let previous = db.assign_epic(epic_id, owner.clone())?;
+            writeln!(out, "{key}: {} -> {}", or_dash(&previous), or_dash(&owner))?;
+        },
+        Command::AddEpicLabel { key, label } => {
+            add_label(db, resolve_epic(db, &key)?, &key, &label, out)?;
+        },
+        Command::RemoveEpicLabel { key, label } => {
+            remove_label(db, resolve_epic(db, &key)?, &key, &label, out)?;
+        },
+        Command::ScheduleEpic { key, change } => {
+            let epic_id = resolve_epic(db, &key)?;
+            let epic = db.get_epic(epic_id)?.ok_or_else(|| anyhow!("{key} not found!"))?;
+            schedule(db, epic_id, &key, change.apply(Schedule::from(&epic)), out)?;
+        },
+        Command::DeleteEpic { key } => {
+            let epic_id = resolve_epic(db, &key)?;
+            db.delete_epic(epic_project(db, epic_id)?, epic_id)?;
+            writeln!(out, "Moved {key} to the trash")?;
+        },
+        Command::ListStories {
+            project,
+            epic,
+            status,
+            priority,
+            assignee,
+            mine,
+            label,
+            overdue,
+            output,
+        } => {
+            let project_id = match project {
+                Some(key) => Some(find_project(db, &key)?.0),
+                None => None,
+            };
+            let epic_id = match epic {
+                Some(key) => Some(resolve_epic(db, &key)?),
+                None => None,
+            };
+            let assignee = match (assignee, mine) {
+                (_, true) => Some(current_user(user)?.to_string()),
+                (assignee, false) => assignee,
+            };
+            let filter = StoryFilter {
+                project_id,
+                epic_id,
+                status,
+                priority,
+                assignee,
+                label,
+                overdue_on: overdue.then(Date::today),
+            };
+            let stories = db.list_stories(&filter)?;
+            let lookup = Lookup::load(db)?;
+            let records =
+                stories.iter().map(|(id, story)| lookup.story(*id, story)).collect_vec();
+            write_list(out, output, &records)?;
+        },
+        Command::ShowStory { key, output } => {
+            let story_id = resolve_story(db, &key)?;
+            let story = db.get_story(story_id)?.ok_or_else(|| anyhow!("{key} not found!"))?;
+            let record = Lookup::load(db)?.story(story_id, &story);
+            if output != OutputFormat::Table {
+                write_record(out, output, &record)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            writeln!(out, "{}: {}", record.key, record.name)?;
+            writeln!(out, "Epic:        {}", record.epic)?;
+            writeln!(out, "Status:      {}", story.status)?;
+            writeln!(out, "Priority:    {}", story.priority)?;
+            writeln!(out, "Assignee:    {}", or_dash(&story.assignee))?;
+            writeln!(out, "Reporter:    {}", or_dash(&story.reporter))?;
+            writeln!(out, "Labels:      {}", labels_or_dash(&story.labels))?;
+            write_dates(out, Schedule::from(&story), story.is_overdue(Date::today()))?;
+            writeln!(out, "Description: {}", indent_lines(&record.description))?;
+        },
+        Command::CreateStory { epic, name, description, priority } => {
+            let epic_id = resolve_epic(db, &epic)?;
+            let reporter = match user {
+                Some(username) if db.get_user(username)?.is_some() => {
+                    Some(username.to_string())
+                },
+                _ => None,
+            };
+            let story = Story { priority, reporter, ..Story::new(name, description) };
+            let story_id = db.create_story(story, epic_id)?;
+            writeln!(out, "{}", Lookup::load(db)?.key(story_id))?;
+        },
+        Command::UpdateStoryStatus { key, status } => {
+            let story_id = resolve_story(db, &key)?;
+            let previous = db.update_story_status(story_id, status)?;
+            writeln!(out, "{key}: {previous} -> {status}")?;
+        },
+        Command::MoveStory { key, epic } => {
+            let story_id = resolve_story(db, &key)?;
+            let to_epic = resolve_epic(db, &epic)?;
+            db.move_story(story_id, story_epic(db, story_id)?, to_epic)?;
+            writeln!(out, "Moved {key} to {epic}")?;
+        },
+        Command::RankStory { key, rank } => {
+            let story_id = resolve_story(db, &key)?;
+            let epic_id = story_epic(db, story_id)?;
+            let stories = db.get_epic(epic_id)?.map(|epic| epic.stories).unwrap_or_default();
+            let position = stories.iter().position(|&id| id == story_id).unwrap_or_default();
+
+            let rank = rank.position(position, stories.len());
+            db.rank_story(epic_id, story_id, rank)?;
+            writeln!(out, "{key}: #{} -> #{}", position + 1, rank + 1)?;
+        },
+        Command::AssignStory { key, assignee } => {
+            let story_id = resolve_story(db, &key)?;
+            let previous = db.assign_story(story_id, assignee.clone())?;
+            writeln!(out, "{key}: {} -> {}", or_dash(&previous), or_dash(&assignee))?;
+        },
+        Command::AddStoryLabel { key, label } => {
+            add_label(db, resolve_story(db, &key)?, &key, &label, out)?;
+        },
+        Command::RemoveStoryLabel { key, label } => {
+            remove_label(db, resolve_story(db, &key)?, &key, &label, out)?;
+        },
+        Command::ScheduleStory { key, change } => {
+            let story_id = resolve_story(db, &key)?;
+            let story = db.get_story(story_id)?.ok_or_else(|| anyhow!("{key} not found!"))?;
+            schedule(db, story_id, &key, change.apply(Schedule::from(&story)), out)?;
+        },
+        Command::DeleteStory { key } => {
+            let story_id = resolve_story(db, &key)?;
+            db.delete_story(story_epic(db, story_id)?, story_id)?;
+            writeln!(out, "Moved {key} to the trash")?;
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Reports integrity issues and exits with a failure code if any remain.
+fn check(db: &JiraDatabase, repair: bool, out: &mut impl Write) -> Result<ExitCode> {
+    let issues = if repair { db.repair_integrity()? } else { db.check_integrity()? };
+    for issue in &issues {
+        writeln!(out, "{issue}")?;
+    }
+
+    if issues.is_empty() {
+        writeln!(out, "No integrity issues found")?;
+        Ok(ExitCode::SUCCESS)
+    } else if repair {
+        writeln!(out, "Repaired {} integrity issue(s)", issues.len())?;
+        Ok(ExitCode::SUCCESS)
+    } else {
+        writeln!(
+            out,
+            "Found {} integrity issue(s); run `jiraffe check --repair` to fix them",
+            issues.len()
+        )?;
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Lines up the continuation lines of a multi-line value with its first line
+/// after a `Label:       ` prefix.
+fn indent_lines(value: &str) -> String { value.replace('\n', "\n             ") }
+
+/// A username for tables and messages, or `-` for nobody.
+fn or_dash(username: &Option<String>) -> &str { username.as_deref().unwrap_or("-") }
+
+fn labels_or_dash(labels: &[String]) -> String {
+    if labels.is_empty() { "-".to_string() } else { labels.join(", ") }
+}
+
+/// Labelling an item twice is not an error, so that plans can be rerun.
+fn add_label(
+    db: &JiraDatabase,
+    item_id: u32,
+    key: &str,
+    label: &str,
+    out: &mut impl Write,
+) -> Result<()> {
+    match db.add_label(item_id, label)? {
+        Some(label) => writeln!(out, "{key}: +{label}")?,
+        None => writeln!(out, "{key} already has label {label}")?,
+    }
+    Ok(())
+}
+
+fn remove_label(
+    db: &JiraDatabase,
+    item_id: u32,
+    key: &str,
+    label: &str,
+    out: &mut impl Write,
+) -> Result<()> {
+    if db.remove_label(item_id, label)? {
+        writeln!(out, "{key}: -{label}")?;
+    } else {
+        writeln!(out, "{key} has no label {label}")?;
+    }
+    Ok(())
+}
+
+/// A date for tables and messages, or `-` for none.
+fn date_or_dash(date: Option<Date>) -> String {
+    date.map_or_else(|| "-".to_string(), |date| date.to_string())
+}
+
+fn write_dates(out: &mut impl Write, schedule: Schedule, overdue: bool) -> Result<()> {
+    writeln!(out, "Start:       {}", date_or_dash(schedule.start))?;
+    let overdue = if overdue { " (overdue)" } else { "" };
+    writeln!(out, "Due:         {}{overdue}", date_or_dash(schedule.due))?;
+    Ok(())
+}
+
+fn schedule(
+    db: &JiraDatabase,
+    item_id: u32,
+    key: &str,
+    schedule: Schedule,
+    out: &mut impl Write,
+) -> Result<()> {
+    db.schedule(item_id, schedule)?;
+    writeln!(
+        out,
+        "{key}: start {}, due {}",
+        date_or_dash(schedule.start),
+        date_or_dash(schedule.due)
+    )?;
+    Ok(())
+}
+
+fn current_user(user: Option<&str>) -> Result<&str> {
+    user.ok_or_else(|| {
+        anyhow!("No current user, set `user` in the config file or the JIRAFFE_USER variable")
+    })
+}
+
+fn find_project(db: &JiraDatabase, key: &str) -> Result<(u32, Project)> {
+    db.find_project(key)?.ok_or_else(|| anyhow!("Project {key} not found!"))
+}
+
+/// Picks the project to use when `--project` is left out.
+fn only_project(db: &JiraDatabase) -> Result<(u32, Project)> {
+    let mut projects = db.list_projects()?;
+    match projects.len() {
+        1 => Ok(projects.remove(0)),
+        0 => bail!("There are no projects yet, create one with `jiraffe project create`"),
+        _ => bail!("There is more than one project, pick one with --project"),
+    }
+}
+
+fn resolve_epic(db: &JiraDatabase, key: &str) -> Result<u32> {
+    let epic_id = db.resolve_issue_key(key)?;
+    if db.get_epic(epic_id)?.is_none() {
+        bail!("{key} is not an epic!");
+    }
+    Ok(epic_id)
+}
+
+fn resolve_story(db: &JiraDatabase, key: &str) -> Result<u32> {
+    let story_id = db.resolve_issue_key(key)?;
+    if db.get_story(story_id)?.is_none() {
+        bail!("{key} is not a story!");
+    }
+    Ok(story_id)
+}
+
+fn epic_project(db: &JiraDatabase, epic_id: u32) -> Result<u32> {
+    db.list_projects()?
+        .into_iter()
+        .find(|(_, project)| project.epics.contains(&epic_id))
+        .map(|(project_id, _)| project_id)
+        .ok_or_else(|| anyhow!("Epic with id {epic_id} is not in any project!"))
+}
+
+fn story_epic(db: &JiraDatabase, story_id: u32) -> Result<u32> {
+    db.find_story_epic(story_id)?
+        .ok_or_else(|| anyhow!("Story with id {story_id} is not in any epic!"))
+}
+
+fn parse_status(status: &str) -> Result<Status, UsageError> {
+    status.parse().map_err(|_| {
+        UsageError(format!(
+            "Invalid status {status:?}: use open, in-progress, resolved or closed"
+        ))
+    })
+}
+
+fn parse_priority(priority: &str) -> Result<Priority, UsageError> {
+    priority.parse().map_err(|_| {
+        UsageError(format!(
+            "Invalid priority {priority:?}: use highest, high, medium, low or lowest"
+        ))
+    })
+}
+
+/// Reads a date as typed, or `None` for `none`, which clears it.
+fn parse_date(date: &str) -> Result<Option<Date>, UsageError> {
+    if date.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+
+    Date::parse(date, Date::today()).map(Some).ok_or_else(|| {
+        UsageError(format!("Invalid date {date:?}: use YYYY-MM-DD, today, +3d, -2w or none"))
+    })
+}
+
+fn parse_label(label: &str) -> Result<String, UsageError> {
+    normalize_label(label).ok_or_else(|| {
+        UsageError(format!("Invalid label {label:?}: use up to 32 letters, digits or dashes"))
+    })
+}
+
+/// The `--name value` options of a command line, taken out one by one as
+/// the command is parsed so that leftovers can be reported.
+struct Options {
+    values:   HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<(Vec<String>, Self), UsageError> {
+        let mut words = Vec::new();
+        let mut options = Self { values: HashMap::new(), switches: HashSet::new() };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                words.push(arg.clone());
+                continue;
+            };
+
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None if SWITCHES.contains(&option) => {
+                    options.switches.insert(option.to_string());
+                    continue;
+                },
+                None => (option, args.next().cloned()),
+            };
+
+            let value = value.ok_or_else(|| UsageError(format!("--{name} needs a value")))?;
+            if options.values.insert(name.to_string(), value).is_some() {
+                return Err(UsageError(format!("--{name} was given more than once")));
+            }
+        }
+
+        Ok((words, options))
+    }
+
+    fn value(&mut self, name: &str) -> Option<String> { self.values.remove(name) }
+
+    fn required(&mut self, name: &str) -> Result<String, UsageError> {
+        self.value(name).ok_or_else(|| UsageError(format!("--{name} is required")))
+    }
+
+    fn switch(&mut self, name: &str) -> bool { self.switches.remove(name) }
+
+    fn status(&mut self) -> Result<Option<Status>, UsageError> {
+        self.value("status").map(|status| parse_status(&status)).transpose()
+    }
+
+    fn priority(&mut self) -> Result<Option<Priority>, UsageError> {
+        self.value("priority").map(|priority| parse_priority(&priority)).transpose()
+    }
+
+    fn label(&mut self) -> Result<Option<String>, UsageError> {
+        self.value("label").map(|label| parse_label(&label)).transpose()
+    }
+
+    /// Reads `--start` and `--due`, at least one of which must be given.
+    fn schedule(&mut self) -> Result<ScheduleChange, UsageError> {
+        let start = self.value("start").map(|start| parse_date(&start)).transpose()?;
+        let due = self.value("due").map(|due| parse_date(&due)).transpose()?;
+        if start.is_none() && due.is_none() {
+            return Err(UsageError("Give a --start or --due date".into()));
+        }
+        Ok(ScheduleChange { start, due })
+    }
+
+    fn output(&mut self) -> Result<OutputFormat, UsageError> {
+        let Some(output) = self.value("output") else { return Ok(OutputFormat::default()) };
+        output.parse().map_err(|_| {
+            UsageError(format!("Invalid output format {output:?}: use table, json or csv"))
+        })
+    }
+
+    /// Deletes only go ahead with `--yes`, as scripts can't answer a prompt.
+    fn confirm(&mut self, key: &str) -> Result<(), UsageError> {
+        if !self.switch("yes") {
+            return Err(UsageError(format!("Refusing to delete {key} without --yes")));
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), UsageError> {
+        let unknown = self.values.keys().chain(&self.switches).sorted().next();
+        match unknown {
+            Some(name) => Err(UsageError(format!("Unknown option --{name}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DocumentStore;
+    use crate::database::test_utils::{MockDB, test_project};
+    use crate::models::DBState;
+
+    fn args(line: &str) -> Vec<String> { line.split_whitespace().map(String::from).collect() }
+
+    fn run(db: &JiraDatabase, line: &str) -> Result<String> {
+        let command = parse(&args(line))?.expect("a command");
+        let mut out = Vec::new();
+        assert_eq!(execute(db, Some("ana"), command, &mut out)?, ExitCode::SUCCESS);
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn parse_should_read_commands_and_options() {
+        assert_eq!(parse(&[]), Ok(None));
+        assert_eq!(parse(&args("--help")), Ok(Some(Command::Help)));
+        assert_eq!(parse(&args("check --repair")), Ok(Some(Command::Check { repair: true })));
+        assert_eq!(
+            parse(&args("epic create --name=Checkout --project pay")),
+            Ok(Some(Command::CreateEpic {
+                project:     Some("pay".to_string()),
+                name:        "Checkout".to_string(),
+                description: "".to_string(),
+                priority:    Priority::Medium,
+            }))
+        );
+        assert_eq!(
+            parse(&args(
+                "story list --epic 3 --status in-progress --priority 2 --mine --label \
+                 Tech_Debt"
+            )),
+            Ok(Some(Command::ListStories {
+                project:  None,
+                epic:     Some("3".to_string()),
+                status:   Some(Status::InProgress),
+                priority: Some(Priority::High),
+                assignee: None,
+                mine:     true,
+                label:    Some("tech-debt".to_string()),
+                overdue:  false,
+                output:   OutputFormat::Table,
+            }))
+        );
+        assert_eq!(
+            parse(&args("epic schedule PAY-2 --start 2026-10-01 --due none")),
+            Ok(Some(Command::ScheduleEpic {
+                key:    "PAY-2".to_string(),
+                change: ScheduleChange {
+                    start: Some("2026-10-01".parse().ok()),
+                    due:   Some(None),
+                },
+            }))
+        );
+        assert_eq!(
+            parse(&args("story status PAY-12 closed")),
+            Ok(Some(Command::UpdateStoryStatus {
+                key:    "PAY-12".to_string(),
+                status: Status::Closed,
+            }))
+        );
+        assert_eq!(
+            parse(&args("epic delete 3 --yes")),
+            Ok(Some(Command::DeleteEpic { key: "3".to_string() }))
+        );
+    }
+
+    #[test]
+    fn parse_should_reject_bad_usage() {
+        assert!(parse(&args("epic")).is_err());
+        assert!(parse(&args("epic create")).is_err());
+        assert!(parse(&args("epic create --name")).is_err());
+        assert!(parse(&args("epic create --name a --name b")).is_err());
+        assert!(parse(&args("epic list --colour red")).is_err());
+        assert!(parse(&args("epic list --yes")).is_err());
+        assert!(parse(&args("epic delete 3")).is_err());
+        assert!(parse(&args("story status 12 done")).is_err());
+        assert!(parse(&args("story list --priority urgent")).is_err());
+        assert!(parse(&args("story list --assignee ana --mine")).is_err());
+        assert!(parse(&args("story assign 12")).is_err());
+        assert!(parse(&args("story list --output xml")).is_err());
+        assert!(parse(&args("epic create --name a --output json")).is_err());
+        assert!(parse(&args("--yes")).is_err());
+    }
+
+    #[test]
+    fn execute_should_drive_the_board() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+
+        assert_eq!(run(&db, "epic create --name Checkout").unwrap(), "TEST-2\n");
+        assert_eq!(run(&db, "story create --epic TEST-2 --name Cart").unwrap(), "TEST-3\n");
+        assert_eq!(
+            run(&db, "story create --epic 2 --name Pay --priority high").unwrap(),
+            "TEST-4\n"
+        );
+
+        assert_eq!(
+            run(&db, "story status test-3 in-progress").unwrap(),
+            "test-3: OPEN -> IN PROGRESS\n"
+        );
+        assert_eq!(
+            run(&db, "story list --epic 2 --status in-progress").unwrap(),
+            format!("TEST-3      {:<12} {:<8} {:<10} Cart\n", "IN PROGRESS", "MEDIUM", "-")
+        );
+        assert_eq!(
+            run(&db, "story list --priority high").unwrap(),
+            format!("TEST-4      {:<12} {:<8} {:<10} Pay\n", "OPEN", "HIGH", "-")
+        );
+        assert_eq!(
+            run(&db, "epic list").unwrap(),
+            format!("TEST-2      {:<12} {:<8} Checkout\n", "OPEN", "MEDIUM")
+        );
+
+        assert_eq!(
+            run(&db, "story delete TEST-4 --yes").unwrap(),
+            "Moved TEST-4 to the trash\n"
+        );
+        assert_eq!(db.get_epic(2).unwrap().unwrap().stories, vec![3]);
+
+        run(&db, "epic delete TEST-2 --yes").unwrap();
+        assert!(db.get_project(project_id).unwrap().unwrap().epics.is_empty());
+        assert_eq!(db.list_trash().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn execute_should_move_stories_between_epics() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        run(&db, "story create --epic TEST-2 --name Cart").unwrap();
+        run(&db, "epic create --name Later").unwrap();
+
+        assert_eq!(
+            run(&db, "story move TEST-3 --epic TEST-4").unwrap(),
+            "Moved TEST-3 to TEST-4\n"
+        );
+        assert!(db.get_epic(2).unwrap().unwrap().stories.is_empty());
+        assert_eq!(db.get_epic(4).unwrap().unwrap().stories, vec![3]);
+
+        assert!(run(&db, "story move TEST-3 --epic TEST-3").is_err());
+
+        run(&db, "story create --epic TEST-4 --name Pay").unwrap();
+        assert_eq!(run(&db, "story rank TEST-5 top").unwrap(), "TEST-5: #2 -> #1\n");
+        assert_eq!(run(&db, "story rank TEST-5 up").unwrap(), "TEST-5: #1 -> #1\n");
+        assert_eq!(run(&db, "story rank 5 down").unwrap(), "5: #1 -> #2\n");
+        assert_eq!(db.get_epic(4).unwrap().unwrap().stories, vec![3, 5]);
+        assert!(parse(&args("story rank TEST-5 bottom")).is_err());
+        assert!(parse(&args("story move TEST-3")).is_err());
+
+        // Moving to another project's epic would change the story's key.
+        run(&db, "project create --name Ops --key OPS").unwrap();
+        run(&db, "epic create --project OPS --name Infra").unwrap();
+        assert!(run(&db, "story move TEST-3 --epic OPS-7").is_err());
+        assert_eq!(db.get_epic(4).unwrap().unwrap().stories, vec![3, 5]);
+        assert!(db.get_epic(7).unwrap().unwrap().stories.is_empty());
+    }
+
+    #[test]
+    fn execute_should_assign_work_to_users() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        assert_eq!(run(&db, "user create --username ana --name Ana").unwrap(), "ana\n");
+        run(&db, "epic create --name Checkout").unwrap();
+        run(&db, "story create --epic TEST-2 --name Cart").unwrap();
+        assert_eq!(db.get_story(3).unwrap().unwrap().reporter.as_deref(), Some("ana"));
+
+        assert!(run(&db, "story assign TEST-3 bo").is_err());
+        run(&db, "user create --username bo --name Bo").unwrap();
+        assert_eq!(run(&db, "story assign TEST-3 bo").unwrap(), "TEST-3: - -> bo\n");
+        assert_eq!(run(&db, "story list --mine").unwrap(), "");
+        assert_eq!(
+            run(&db, "story list --assignee bo").unwrap(),
+            format!("TEST-3      {:<12} {:<8} {:<10} Cart\n", "OPEN", "MEDIUM", "bo")
+        );
+        assert_eq!(run(&db, "story unassign TEST-3").unwrap(), "TEST-3: bo -> -\n");
+
+        assert_eq!(run(&db, "epic assign TEST-2 ana").unwrap(), "TEST-2: - -> ana\n");
+        assert!(run(&db, "epic list --owner ana").unwrap().contains("Checkout"));
+        assert_eq!(run(&db, "epic list --owner bo").unwrap(), "");
+        assert_eq!(run(&db, "user list").unwrap(), "ana         Ana\nbo          Bo\n");
+
+        let show = run(&db, "story show TEST-3").unwrap();
+        assert!(show.contains("Assignee:    -\nReporter:    ana\n"));
+
+        let command = parse(&args("story list --mine")).unwrap().unwrap();
+        assert!(execute(&db, None, command, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn execute_should_label_work_and_filter_by_label() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        run(&db, "story create --epic TEST-2 --name Cart").unwrap();
+        run(&db, "story create --epic TEST-2 --name Pay").unwrap();
+
+        assert_eq!(run(&db, "story label TEST-3 Tech_Debt").unwrap(), "TEST-3: +tech-debt\n");
+        assert_eq!(
+            run(&db, "story label 3 tech-debt").unwrap(),
+            "3 already has label tech-debt\n"
+        );
+        assert!(run(&db, "story label TEST-3 a/b").is_err());
+        run(&db, "epic label TEST-2 backend").unwrap();
+        assert_eq!(run(&db, "label list").unwrap(), "backend\ntech-debt\n");
+
+        assert_eq!(
+            run(&db, "story list --label tech-debt").unwrap(),
+            format!(
+                "TEST-3      {:<12} {:<8} {:<10} Cart [tech-debt]\n",
+                "OPEN", "MEDIUM", "-"
+            )
+        );
+        assert!(run(&db, "epic list --label backend").unwrap().contains("Checkout [backend]"));
+        assert_eq!(run(&db, "epic list --label tech-debt").unwrap(), "");
+
+        assert_eq!(
+            run(&db, "label rename tech-debt cleanup").unwrap(),
+            "Renamed tech-debt to cleanup on 1 item(s)\n"
+        );
+        assert!(run(&db, "story show TEST-3").unwrap().contains("Labels:      cleanup\n"));
+        assert_eq!(run(&db, "story unlabel TEST-3 cleanup").unwrap(), "TEST-3: -cleanup\n");
+        assert_eq!(
+            run(&db, "story unlabel TEST-3 cleanup").unwrap(),
+            "TEST-3 has no label cleanup\n"
+        );
+        assert!(run(&db, "epic show TEST-2").unwrap().contains("Labels:      backend\n"));
+    }
+
+    #[test]
+    fn execute_should_schedule_work_and_list_overdue_items() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        run(&db, "story create --epic TEST-2 --name Cart").unwrap();
+        run(&db, "story create --epic TEST-2 --name Pay").unwrap();
+
+        assert_eq!(
+            run(&db, "story schedule TEST-3 --start 2020-01-01 --due 2020-01-31").unwrap(),
+            "TEST-3: start 2020-01-01, due 2020-01-31\n"
+        );
+        assert_eq!(
+            run(&db, "story schedule TEST-3 --start none").unwrap(),
+            "TEST-3: start -, due 2020-01-31\n"
+        );
+        assert!(run(&db, "story schedule TEST-3 --start 2020-02-01").is_err());
+        assert!(run(&db, "story schedule TEST-3").is_err());
+        assert!(run(&db, "story schedule TEST-3 --due tomorrow").is_err());
+        run(&db, "story schedule TEST-4 --due +1w").unwrap();
+        run(&db, "epic schedule TEST-2 --due 2020-02-01").unwrap();
+
+        assert!(
+            run(&db, "story show TEST-3")
+                .unwrap()
+                .contains("Due:         2020-01-31 (overdue)\n")
+        );
+        let due = Date::today().add_days(7);
+        assert!(
+            run(&db, "story show TEST-4")
+                .unwrap()
+                .contains(&format!("Start:       -\nDue:         {due}\n"))
+        );
+        let overdue = run(&db, "story list --overdue").unwrap();
+        assert!(overdue.starts_with("TEST-3 ") && overdue.lines().count() == 1);
+        assert!(run(&db, "epic list --overdue").unwrap().contains("Checkout"));
+
+        run(&db, "story status TEST-3 resolved").unwrap();
+        assert_eq!(run(&db, "story list --overdue").unwrap(), "");
+    }
+
+    #[test]
+    fn execute_should_print_records_as_json() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        run(&db, "story create --epic TEST-2 --name Cart").unwrap();
+        run(&db, "story status TEST-3 in-progress").unwrap();
+        run(&db, "story schedule TEST-3 --due 2026-10-20").unwrap();
+
+        let stories: serde_json::Value =
+            serde_json::from_str(&run(&db, "story list --output json").unwrap()).unwrap();
+        assert_eq!(
+            stories,
+            serde_json::json!([{
+                "id": 3,
+                "key": "TEST-3",
+                "epic": "TEST-2",
+                "name": "Cart",
+                "description": "",
+                "status": "in-progress",
+                "priority": "medium",
+                "assignee": null,
+                "reporter": null,
+                "labels": [],
+                "start_date": null,
+                "due_date": "2026-10-20",
+            }])
+        );
+
+        let epic: serde_json::Value =
+            serde_json::from_str(&run(&db, "epic show 2 --output=json").unwrap()).unwrap();
+        assert_eq!(epic["project"], "TEST");
+        assert_eq!(epic["status"], "open");
+        assert_eq!(epic["stories"], serde_json::json!(["TEST-3"]));
+
+        let projects: serde_json::Value =
+            serde_json::from_str(&run(&db, "project list --output json").unwrap()).unwrap();
+        assert_eq!(projects[0]["epics"], serde_json::json!(["TEST-2"]));
+    }
+
+    #[test]
+    fn show_should_indent_multi_line_descriptions() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        db.create_story(Story::new("Cart".to_string(), "Given\n- a\n- b".to_string()), 2)
+            .unwrap();
+
+        assert!(
+            run(&db, "story show TEST-3")
+                .unwrap()
+                .contains("Description: Given\n             - a\n             - b\n")
+        );
+    }
+
+    #[test]
+    fn execute_should_print_records_as_csv() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        test_project(&db);
+        run(&db, "epic create --name Checkout").unwrap();
+        db.create_story(Story::new("Cart, \"mini\"".to_string(), "".to_string()), 2).unwrap();
+        db.create_story(Story::new("Pay".to_string(), "line\nbreak".to_string()), 2).unwrap();
+        run(&db, "story label TEST-4 ui").unwrap();
+        run(&db, "story label TEST-4 backend").unwrap();
+        run(&db, "story schedule TEST-4 --start 2026-10-01 --due 2026-10-20").unwrap();
+
+        let rows = [
+            "id,key,epic,name,description,status,priority,assignee,reporter,labels,start_date,\
+             due_date",
+            "3,TEST-3,TEST-2,\"Cart, \"\"mini\"\"\",,open,medium,,,,,",
+            "4,TEST-4,TEST-2,Pay,\"line\nbreak\",open,medium,,,backend \
+             ui,2026-10-01,2026-10-20",
+        ];
+        assert_eq!(run(&db, "story list --output csv").unwrap(), rows.join("\n") + "\n");
+        assert_eq!(
+            run(&db, "epic show TEST-2 --output csv").unwrap(),
+            "id,key,project,name,description,status,stories,priority,owner,labels,start_date,\
+             due_date\n2,TEST-2,TEST,Checkout,,open,TEST-3 TEST-4,medium,,,,\n"
+        );
+        assert_eq!(
+            run(&db, "epic list --status closed --output csv").unwrap().lines().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn execute_should_fail_on_missing_or_mismatched_items() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        assert!(run(&db, "epic create --name Checkout").is_err());
+
+        test_project(&db);
+        run(&db, "project create --name Ops --key ops").unwrap();
+        assert!(run(&db, "epic create --name Checkout").is_err());
+
+        assert_eq!(run(&db, "epic create --name Checkout --project test").unwrap(), "TEST-3\n");
+        run(&db, "story create --epic TEST-3 --name Cart").unwrap();
+
+        assert!(run(&db, "story status TEST-3 closed").is_err());
+        assert!(run(&db, "epic status TEST-4 closed").is_err());
+        assert!(run(&db, "epic show OPS-3").is_err());
+        assert!(run(&db, "story list --project NOPE").is_err());
+        assert!(run(&db, "story create --epic 999 --name Cart").is_err());
+    }
+
+    #[test]
+    fn check_should_fail_if_issues_remain() {
+        let store = MockDB::new();
+        let mut db_state = DBState { last_item_id: 1, ..DBState::new() };
+        db_state.stories.insert(1, Story::new("".to_string(), "".to_string()));
+        store.write(&db_state).unwrap();
+        let db = JiraDatabase::from_store(store);
+
+        let check = |repair| {
+            let mut out = Vec::new();
+            let code = execute(&db, None, Command::Check { repair }, &mut out).unwrap();
+            (code, String::from_utf8(out).unwrap())
+        };
+
+        assert_eq!(
+            check(false),
+            (
+                ExitCode::FAILURE,
+                "Story 1 doesn't belong to any epic\nFound 1 integrity issue(s); run `jiraffe \
+                 check --repair` to fix them\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(true),
+            (
+                ExitCode::SUCCESS,
+                "Story 1 doesn't belong to any epic\nRepaired 1 integrity issue(s)\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(false),
+            (ExitCode::SUCCESS, "No integrity issues found\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use super::SWITCHES;
+use crate::database::{Backend, EpicFilter, JiraDatabase, StoryFilter};
+use crate::models::{Priority, Status};
+
+/// A shell that `jiraffe completions` can print a script for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The script to source from the shell's startup file. It asks
+    /// `jiraffe complete` for candidates, so keys stay in sync with the board,
+    /// and falls back to file names when there are none.
+    pub fn script(self) -> &'static str {
+        match self {
+            Shell::Bash => BASH_SCRIPT,
+            Shell::Zsh => ZSH_SCRIPT,
+            Shell::Fish => FISH_SCRIPT,
+        }
+    }
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A value the shell can complete to, and a description to show next to it.
+type Candidate = (String, String);
+
+/// Writes the candidates for the last of `words`, one `value<TAB>description`
+/// per line. `words` is the command line after `jiraffe`, up to and including
+/// the word being completed, which may be empty.
+pub fn complete(db: &JiraDatabase, words: &[String], out: &mut impl Write) -> Result<()> {
+    let Some((current, before)) = words.split_last() else { return Ok(()) };
+
+    let prefix = current.to_lowercase();
+    for (value, description) in candidates(db, before, current)? {
+        if value.to_lowercase().starts_with(&prefix) {
+            writeln!(out, "{value}\t{description}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Completes against the board at `db_path`, if there is one. The shell runs
+/// this on every TAB, wherever it is, so it never creates or writes a board.
+pub fn complete_board(
+    backend: Backend,
+    db_path: &Path,
+    words: &[String],
+    out: &mut impl Write,
+) -> Result<()> {
+    match JiraDatabase::open_existing(backend, db_path)? {
+        Some(db) => complete(&db, words, out),
+        None => Ok(()),
+    }
+}
+
+fn candidates(db: &JiraDatabase, before: &[String], current: &str) -> Result<Vec<Candidate>> {
+    let mut positional = Vec::new();
+    let mut given = Vec::new();
+    let mut option_needing_value = None;
+
+    for word in before {
+        if option_needing_value.take().is_some() {
+            continue;
+        }
+
+        match word.strip_prefix("--") {
+            Some(option) => {
+                given.push(word.as_str());
+                if !option.contains('=') && !SWITCHES.contains(&option) {
+                    option_needing_value = Some(option);
+                }
+            },
+            None => positional.push(word.as_str()),
+        }
+    }
+
+    if let Some(option) = option_needing_value {
+        return option_values(db, option);
+    }
+
+    if current.starts_with('-') {
+        let options = options(&positional).iter().filter(|option| !given.contains(option));
+        return Ok(options.map(|option| (option.to_string(), String::new())).collect());
+    }
+
+    match positional.as_slice() {
+        [] => Ok(fixed(&[
+            ("project", "Manage projects"),
+            ("user", "Manage users"),
+            ("label", "Manage labels"),
+            ("epic", "Manage epics"),
+            ("story", "Manage stories"),
+            ("apply", "Run a plan file in one transaction"),
+            ("check", "Check the board for integrity issues"),
+            ("completions", "Print a shell completion script"),
+            ("help", "Show usage"),
+        ])),
+        ["project"] => Ok(fixed(&[("list", "List projects"), ("create", "Create a project")])),
+        ["user"] => Ok(fixed(&[("list", "List users"), ("create", "Create a user")])),
+        ["label"] => Ok(fixed(&[
+            ("list", "List the labels in use"),
+            ("rename", "Rename a label everywhere"),
+        ])),
+        ["label", "rename"] => labels(db),
+        ["epic"] => Ok(fixed(&[
+            ("list", "List epics"),
+            ("show", "Show an epic"),
+            ("create", "Create an epic"),
+            ("status", "Change an epic's status"),
+            ("assign", "Give an epic an owner"),
+            ("unassign", "Leave an epic without an owner"),
+            ("label", "Add a label to an epic"),
+            ("unlabel", "Remove a label from an epic"),
+            ("schedule", "Set an epic's start and due dates"),
+            ("delete", "Move an epic to the trash"),
+        ])),
+        ["story"] => Ok(fixed(&[
+            ("list", "List stories"),
+            ("show", "Show a story"),
+            ("create", "Create a story"),
+            ("status", "Change a story's status"),
+            ("move", "Move a story to another epic"),
+            ("rank", "Move a story up or down its epic"),
+            ("assign", "Assign a story to a user"),
+            ("unassign", "Leave a story unassigned"),
+            ("label", "Add a label to a story"),
+            ("unlabel", "Remove a label from a story"),
+            ("schedule", "Set a story's start and due dates"),
+            ("delete", "Move a story to the trash"),
+        ])),
+        [
+            "epic",
+            "show" | "status" | "assign" | "unassign" | "label" | "unlabel" | "schedule"
+            | "delete",
+        ] => epics(db),
+        [
+            "story",
+            "show" | "status" | "move" | "rank" | "assign" | "unassign" | "label" | "unlabel"
+            | "schedule" | "delete",
+        ] => stories(db),
+        ["epic" | "story", "assign", _] => users(db),
+        ["epic" | "story", "label" | "unlabel", _] => labels(db),
+        ["story", "rank", _] => {
+            Ok(fixed(&[("up", ""), ("down", ""), ("top", "Top of the backlog")]))
+        },
+        ["epic" | "story", "status", _] => Ok(statuses()),
+        ["completions"] => Ok(fixed(&[("bash", ""), ("zsh", ""), ("fish", "")])),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The options a command takes, besides the global ones before it.
+fn options(positional: &[&str]) -> &'static [&'static str] {
+    match positional {
+        [] => &["--db", "--help"],
+        ["check"] => &["--repair"],
+        ["project" | "user", "list"] | [_, "show", _] => &["--output"],
+        ["project", "create"] => &["--name", "--key", "--description"],
+        ["user", "create"] => &["--username", "--name"],
+        ["epic", "list"] => &[
+            "--project",
+            "--status",
+            "--priority",
+            "--owner",
+            "--label",
+            "--overdue",
+            "--output",
+        ],
+        ["epic", "create"] => &["--project", "--name", "--description", "--priority"],
+        ["story", "list"] => &[
+            "--project",
+            "--epic",
+            "--status",
+            "--priority",
+            "--assignee",
+            "--mine",
+            "--label",
+            "--overdue",
+            "--output",
+        ],
+        ["story", "create"] => &["--epic", "--name", "--description", "--priority"],
+        ["story", "move", _] => &["--epic"],
+        [_, "schedule", _] => &["--start", "--due"],
+        [_, "delete", _] => &["--yes"],
+        _ => &[],
+    }
+}
+
+fn option_values(db: &JiraDatabase, option: &str) -> Result<Vec<Candidate>> {
+    match option {
+        "project" => projects(db),
+        "epic" => epics(db),
+        "status" => Ok(statuses()),
+        "priority" => Ok(priorities()),
+        "owner" | "assignee" => users(db),
+        "label" => labels(db),
+        "start" | "due" => Ok(fixed(&[
+            ("today", ""),
+            ("+1d", "Tomorrow"),
+            ("+1w", "In a week"),
+            ("none", "No date"),
+        ])),
+        "output" => Ok(fixed(&[("table", ""), ("json", ""), ("csv", "")])),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn fixed(candidates: &[(&str, &str)]) -> Vec<Candidate> {
+    candidates
+        .iter()
+        .map(|(value, description)| (value.to_string(), description.to_string()))
+        .collect()
+}
+
+fn statuses() -> Vec<Candidate> {
+    [Status::Open, Status::InProgress, Status::Resolved, Status::Closed]
+        .iter()
+        .map(|status| (status.slug().to_string(), String::new()))
+        .collect()
+}
+
+fn priorities() -> Vec<Candidate> {
+    Priority::ALL.iter().map(|priority| (priority.slug().to_string(), String::new())).collect()
+}
+
+fn projects(db: &JiraDatabase) -> Result<Vec<Candidate>> {
+    let projects = db.list_projects()?;
+    Ok(projects.into_iter().map(|(_, project)| (project.key, project.name)).collect())
+}
+
+fn users(db: &JiraDatabase) -> Result<Vec<Candidate>> {
+    Ok(db.list_users()?.into_iter().map(|(username, user)| (username, user.name)).collect())
+}
+
+fn labels(db: &JiraDatabase) -> Result<Vec<Candidate>> {
+    Ok(db.list_labels()?.into_iter().map(|label| (label, String::new())).collect())
+}
+
+fn epics(db: &JiraDatabase) -> Result<Vec<Candidate>> {
+    let keys = db.issue_keys()?;
+    let epics = db.list_epics(&EpicFilter::default())?;
+    Ok(epics
+        .into_iter()
+        .map(|(id, epic)| (keys.get(&id).cloned().unwrap_or_else(|| id.to_string()), epic.name))
+        .collect())
+}
+
+fn stories(db: &JiraDatabase) -> Result<Vec<Candidate>> {
+    let keys = db.issue_keys()?;
+    let stories = db.list_stories(&StoryFilter::default())?;
+    Ok(stories
+        .into_iter()
+        .map(|(id, story)| {
+            (keys.get(&id).cloned().unwrap_or_else(|| id.to_string()), story.name)
+        })
+        .collect())
+}
+
+const BASH_SCRIPT: &str = r#"# jiraffe completions for bash: eval "$(jiraffe completions bash)"
+_jiraffe() {
+    local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}
+    if [[ $prev == --db ]]; then
+        COMPREPLY=($(compgen -f -- "$cur"))
+        return
+    fi
+
+    local IFS=$'\n'
+    COMPREPLY=($("$1" complete "${COMP_WORDS[@]:1:COMP_CWORD}" 2>/dev/null | cut -f1))
+}
+complete -o default -F _jiraffe jiraffe
+"#;
+
+const ZSH_SCRIPT: &str = r#"#compdef jiraffe
+# jiraffe completions for zsh: eval "$(jiraffe completions zsh)"
+_jiraffe() {
+    if [[ ${words[CURRENT-1]} == --db ]]; then
+        _files
+        return
+    fi
+
+    local -a candidates
+    local line
+    for line in "${(@f)$(${words[1]} complete "${(@Q)words[2,CURRENT]}" 2>/dev/null)}"; do
+        [[ -z $line ]] && continue
+        local value=${line%%$'\t'*} description=${line#*$'\t'}
+        candidates+=("${value//:/\\:}${description:+:$description}")
+    done
+
+    if (( ${#candidates} )); then
+        _describe 'jiraffe' candidates
+    else
+        _files
+    fi
+}
+compdef _jiraffe jiraffe
+"#;
+
+const FISH_SCRIPT: &str = r#"# jiraffe completions for fish: jiraffe completions fish | source
+function __jiraffe_candidates
+    set -l tokens (commandline -opc)
+    set -l current (commandline -ct)
+    test "$tokens[-1]" = --db; and return
+    $tokens[1] complete $tokens[2..-1] "$current" 2>/dev/null
+end
+
+function __jiraffe_no_candidates
+    set -l candidates (__jiraffe_candidates)
+    test (count $candidates) -eq 0
+end
+
+complete -c jiraffe -f -a '(__jiraffe_candidates)'
+complete -c jiraffe -n __jiraffe_no_candidates -F
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::{MockDB, test_project};
+    use crate::models::{Epic, Story, User};
+
+    fn complete_line(db: &JiraDatabase, line: &str) -> Vec<String> {
+        let words = line.split(' ').map(String::from).collect::<Vec<_>>();
+        let mut out = Vec::new();
+        complete(db, &words, &mut out).unwrap();
+        String::from_utf8(out).unwrap().lines().map(String::from).collect()
+    }
+
+    fn board() -> JiraDatabase {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id = db
+            .create_epic(Epic::new("Checkout".to_string(), "".to_string()), project_id)
+            .unwrap();
+        let story_id =
+            db.create_story(Story::new("Cart".to_string(), "".to_string()), epic_id).unwrap();
+        db.add_label(story_id, "ui").unwrap();
+        db.create_user("ana", User { name: "Ana".to_string() }).unwrap();
+        db
+    }
+
+    /// The names of the files in `dir`, sorted.
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn complete_board_should_leave_a_missing_board_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("data").join("db.json");
+        let words = ["story", "status", ""].map(String::from);
+
+        for backend in [Backend::Json, Backend::Sqlite, Backend::EventLog] {
+            let mut out = Vec::new();
+            complete_board(backend, &db_path, &words, &mut out).unwrap();
+            assert!(out.is_empty());
+        }
+        assert!(files_in(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn complete_board_should_read_an_existing_board_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("db.sqlite");
+        let db = JiraDatabase::open(Backend::Sqlite, &db_path).unwrap();
+        let project_id = test_project(&db);
+        db.create_epic(Epic::new("Checkout".to_string(), "".to_string()), project_id).unwrap();
+        drop(db);
+
+        let files = files_in(dir.path());
+        let modified = std::fs::metadata(&db_path).unwrap().modified().unwrap();
+        let words = ["epic", "show", ""].map(String::from);
+        let mut out = Vec::new();
+        complete_board(Backend::Sqlite, &db_path, &words, &mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "TEST-2\tCheckout\n");
+        assert_eq!(files_in(dir.path()), files);
+        assert_eq!(std::fs::metadata(&db_path).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn complete_should_list_live_keys_with_names() {
+        let db = board();
+
+        assert_eq!(complete_line(&db, "story status "), vec!["TEST-3\tCart"]);
+        assert_eq!(complete_line(&db, "epic delete test"), vec!["TEST-2\tCheckout"]);
+        assert_eq!(complete_line(&db, "story create --epic "), vec!["TEST-2\tCheckout"]);
+        assert_eq!(complete_line(&db, "story move TEST-3 --epic "), vec!["TEST-2\tCheckout"]);
+        assert_eq!(complete_line(&db, "epic list --project "), vec!["TEST\ttest"]);
+        assert_eq!(complete_line(&db, "story assign TEST-3 "), vec!["ana\tAna"]);
+        assert_eq!(complete_line(&db, "story list --assignee a"), vec!["ana\tAna"]);
+        assert_eq!(complete_line(&db, "epic list --label "), vec!["ui\t"]);
+        assert_eq!(complete_line(&db, "story unlabel TEST-3 "), vec!["ui\t"]);
+        assert!(complete_line(&db, "story status PAY-").is_empty());
+    }
+
+    #[test]
+    fn complete_should_follow_the_command_line() {
+        let db = board();
+
+        assert_eq!(complete_line(&db, "st"), vec!["story\tManage stories"]);
+        assert_eq!(complete_line(&db, "story status TEST-3 "), vec![
+            "open\t",
+            "in-progress\t",
+            "resolved\t",
+            "closed\t"
+        ]);
+        assert_eq!(complete_line(&db, "story list --status closed --output j"), vec!["json\t"]);
+        assert_eq!(complete_line(&db, "epic create --priority h"), vec!["highest\t", "high\t"]);
+        assert_eq!(complete_line(&db, "epic list --status open --"), vec![
+            "--project\t",
+            "--priority\t",
+            "--owner\t",
+            "--label\t",
+            "--overdue\t",
+            "--output\t"
+        ]);
+        assert_eq!(complete_line(&db, "epic delete TEST-2 --"), vec!["--yes\t"]);
+        assert_eq!(complete_line(&db, "story schedule TEST-3 --start 2026-10-01 --"), vec![
+            "--due\t"
+        ]);
+        assert_eq!(complete_line(&db, "story schedule TEST-3 --due +"), vec![
+            "+1d\tTomorrow",
+            "+1w\tIn a week"
+        ]);
+        assert!(complete_line(&db, "epic create --name ").is_empty());
+        assert!(complete_line(&db, "apply ").is_empty());
+    }
+}
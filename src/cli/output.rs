@@ -0,0 +1,398 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::Result;
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
+
+use crate::database::{EpicFilter, JiraDatabase};
+use crate::models::{Date, Epic, Priority, Project, Status, Story};
+
+/// How list and show commands print what they found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for people to read. Not meant to be parsed.
+    #[default]
+    Table,
+    /// A JSON array of records for lists, or a single record for `show`.
+    Json,
+    /// A header row naming the record fields, then one row per record.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A project as printed by `--output json|csv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProjectRecord {
+    pub id:          u32,
+    pub key:         String,
+    pub name:        String,
+    pub description: String,
+    /// Issue keys of the project's epics, in order.
+    pub epics:       Vec<String>,
+}
+
+/// A user as printed by `--output json|csv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserRecord {
+    pub username: String,
+    pub name:     String,
+}
+
+/// An epic as printed by `--output json|csv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EpicRecord {
+    pub id:          u32,
+    pub key:         String,
+    /// Key of the project the epic belongs to.
+    pub project:     String,
+    pub name:        String,
+    pub description: String,
+    /// One of `open`, `in-progress`, `resolved` or `closed`.
+    #[serde(serialize_with = "serialize_status")]
+    pub status:      Status,
+    /// Issue keys of the epic's stories, in order.
+    pub stories:     Vec<String>,
+    /// One of `highest`, `high`, `medium`, `low` or `lowest`.
+    #[serde(serialize_with = "serialize_priority")]
+    pub priority:    Priority,
+    /// Username of the epic's owner, if it has one.
+    pub owner:       Option<String>,
+    /// Normalised labels, sorted.
+    pub labels:      Vec<String>,
+    /// `YYYY-MM-DD`, if set.
+    pub start_date:  Option<Date>,
+    pub due_date:    Option<Date>,
+}
+
+/// A story as printed by `--output json|csv`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StoryRecord {
+    pub id:          u32,
+    pub key:         String,
+    /// Issue key of the epic the story belongs to.
+    pub epic:        String,
+    pub name:        String,
+    pub description: String,
+    /// One of `open`, `in-progress`, `resolved` or `closed`.
+    #[serde(serialize_with = "serialize_status")]
+    pub status:      Status,
+    /// One of `highest`, `high`, `medium`, `low` or `lowest`.
+    #[serde(serialize_with = "serialize_priority")]
+    pub priority:    Priority,
+    /// Username of the user the story is assigned to, if anyone.
+    pub assignee:    Option<String>,
+    /// Username of the user who reported the story, if known.
+    pub reporter:    Option<String>,
+    /// Normalised labels, sorted.
+    pub labels:      Vec<String>,
+    /// `YYYY-MM-DD`, if set.
+    pub start_date:  Option<Date>,
+    pub due_date:    Option<Date>,
+}
+
+/// A record that can be printed in every output format. Lists in a CSV
+/// field are separated by spaces.
+pub trait Record: Serialize {
+    const COLUMNS: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+    fn write_table_row(&self, out: &mut dyn Write) -> Result<()>;
+}
+
+impl Record for ProjectRecord {
+    const COLUMNS: &'static [&'static str] = &["id", "key", "name", "description", "epics"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.key.clone(),
+            self.name.clone(),
+            self.description.clone(),
+            self.epics.join(" "),
+        ]
+    }
+
+    fn write_table_row(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "{:<11} {}", self.key, self.name)?;
+        Ok(())
+    }
+}
+
+impl Record for UserRecord {
+    const COLUMNS: &'static [&'static str] = &["username", "name"];
+
+    fn fields(&self) -> Vec<String> { vec![self.username.clone(), self.name.clone()] }
+
+    fn write_table_row(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "{:<11} {}", self.username, self.name)?;
+        Ok(())
+    }
+}
+
+impl Record for EpicRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "key",
+        "project",
+        "name",
+        "description",
+        "status",
+        "stories",
+        "priority",
+        "owner",
+        "labels",
+        "start_date",
+        "due_date",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.key.clone(),
+            self.project.clone(),
+            self.name.clone(),
+            self.description.clone(),
+            self.status.slug().to_string(),
+            self.stories.join(" "),
+            self.priority.slug().to_string(),
+            self.owner.clone().unwrap_or_default(),
+            self.labels.join(" "),
+            date_field(self.start_date),
+            date_field(self.due_date),
+        ]
+    }
+
+    fn write_table_row(&self, out: &mut dyn Write) -> Result<()> {
+        write_table_row(out, &self.key, &self.status, &self.priority, &self.name, &self.labels)
+    }
+}
+
+impl Record for StoryRecord {
+    const COLUMNS: &'static [&'static str] = &[
+        "id",
+        "key",
+        "epic",
+        "name",
+        "description",
+        "status",
+        "priority",
+        "assignee",
+        "reporter",
+        "labels",
+        "start_date",
+        "due_date",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.key.clone(),
+            self.epic.clone(),
+            self.name.clone(),
+            self.description.clone(),
+            self.status.slug().to_string(),
+            self.priority.slug().to_string(),
+            self.assignee.clone().unwrap_or_default(),
+            self.reporter.clone().unwrap_or_default(),
+            self.labels.join(" "),
+            date_field(self.start_date),
+            date_field(self.due_date),
+        ]
+    }
+
+    /// Stories also show their assignee, between the priority and the name.
+    fn write_table_row(&self, out: &mut dyn Write) -> Result<()> {
+        let assignee = format!("{:<10}", self.assignee.as_deref().unwrap_or("-"));
+        let name = format!("{assignee} {}", self.name);
+        write_table_row(out, &self.key, &self.status, &self.priority, &name, &self.labels)
+    }
+}
+
+/// Issue keys and parent links, loaded once to turn models into records.
+pub struct Lookup {
+    keys:          HashMap<u32, String>,
+    epic_projects: HashMap<u32, String>,
+    story_epics:   HashMap<u32, u32>,
+}
+
+impl Lookup {
+    pub fn load(db: &JiraDatabase) -> Result<Self> {
+        let epic_projects = db
+            .list_projects()?
+            .into_iter()
+            .flat_map(|(_, project)| {
+                let key = project.key;
+                project.epics.into_iter().map(move |epic_id| (epic_id, key.clone()))
+            })
+            .collect();
+        let story_epics = db
+            .list_epics(&EpicFilter::default())?
+            .into_iter()
+            .flat_map(|(epic_id, epic)| epic.stories.into_iter().map(move |id| (id, epic_id)))
+            .collect();
+
+        Ok(Self { keys: db.issue_keys()?, epic_projects, story_epics })
+    }
+
+    /// The item's issue key, or its bare id if it has none.
+    pub fn key(&self, item_id: u32) -> String {
+        self.keys.get(&item_id).cloned().unwrap_or_else(|| item_id.to_string())
+    }
+
+    pub fn project(&self, project_id: u32, project: &Project) -> ProjectRecord {
+        ProjectRecord {
+            id:          project_id,
+            key:         project.key.clone(),
+            name:        project.name.clone(),
+            description: project.description.clone(),
+            epics:       project.epics.iter().map(|id| self.key(*id)).collect(),
+        }
+    }
+
+    pub fn epic(&self, epic_id: u32, epic: &Epic) -> EpicRecord {
+        EpicRecord {
+            id:          epic_id,
+            key:         self.key(epic_id),
+            project:     self.epic_projects.get(&epic_id).cloned().unwrap_or_default(),
+            name:        epic.name.clone(),
+            description: epic.description.clone(),
+            status:      epic.status,
+            stories:     epic.stories.iter().map(|id| self.key(*id)).collect(),
+            priority:    epic.priority,
+            owner:       epic.owner.clone(),
+            labels:      epic.labels.clone(),
+            start_date:  epic.start_date,
+            due_date:    epic.due_date,
+        }
+    }
+
+    pub fn story(&self, story_id: u32, story: &Story) -> StoryRecord {
+        StoryRecord {
+            id:          story_id,
+            key:         self.key(story_id),
+            epic:        self
+                .story_epics
+                .get(&story_id)
+                .map(|id| self.key(*id))
+                .unwrap_or_default(),
+            name:        story.name.clone(),
+            description: story.description.clone(),
+            status:      story.status,
+            priority:    story.priority,
+            assignee:    story.assignee.clone(),
+            reporter:    story.reporter.clone(),
+            labels:      story.labels.clone(),
+            start_date:  story.start_date,
+            due_date:    story.due_date,
+        }
+    }
+}
+
+/// Prints the records of a list command.
+pub fn write_list<R: Record>(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    records: &[R],
+) -> Result<()> {
+    match format {
+        OutputFormat::Table => {
+            for record in records {
+                record.write_table_row(out)?;
+            }
+        },
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        },
+        OutputFormat::Csv => {
+            write_csv_row(out, R::COLUMNS.iter().map(|column| column.to_string()))?;
+            for record in records {
+                write_csv_row(out, record.fields())?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Prints the record of a show command as JSON or CSV. Tables are laid out
+/// by the command itself, as they show more than the record holds.
+pub fn write_record<R: Record>(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    record: &R,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, record)?;
+            writeln!(out)?;
+            Ok(())
+        },
+        _ => write_list(out, format, std::slice::from_ref(record)),
+    }
+}
+
+/// Labels follow the name in brackets, as they vary too much in length for
+/// a column of their own.
+fn write_table_row(
+    out: &mut dyn Write,
+    key: &str,
+    status: &Status,
+    priority: &Priority,
+    name: &str,
+    labels: &[String],
+) -> Result<()> {
+    write!(out, "{key:<11} {:<12} {:<8} {name}", status.to_string(), priority.to_string())?;
+    if !labels.is_empty() {
+        write!(out, " [{}]", labels.join(", "))?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn date_field(date: Option<Date>) -> String {
+    date.map(|date| date.to_string()).unwrap_or_default()
+}
+
+fn serialize_status<S: Serializer>(status: &Status, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(status.slug())
+}
+
+fn serialize_priority<S: Serializer>(
+    priority: &Priority,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(priority.slug())
+}
+
+fn write_csv_row(out: &mut dyn Write, fields: impl IntoIterator<Item = String>) -> Result<()> {
+    writeln!(
+        out,
+        "{}",
+        fields.into_iter().map(|field| csv_field(&field).into_owned()).join(",")
+    )?;
+    Ok(())
+}
+
+/// Quotes a field that would otherwise break the row, as in RFC 4180.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
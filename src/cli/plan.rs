@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use anyhow::{Context, Result, anyhow};
+
+use super::{Command, UsageError, execute, parse};
+use crate::database::JiraDatabase;
+
+/// One line of a plan: a command, and the variable to keep the key it
+/// creates in, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    line:     usize,
+    variable: Option<String>,
+    words:    Vec<String>,
+}
+
+/// Runs every command of a plan in one transaction, so either all of them
+/// apply or, if any fails, none do. Output is only printed once all succeed.
+pub fn apply(
+    db: &JiraDatabase,
+    user: Option<&str>,
+    plan: &str,
+    out: &mut impl Write,
+) -> Result<()> {
+    let steps = parse_plan(plan)?;
+
+    let mut output = Vec::new();
+    db.batch("apply", || {
+        let mut variables = HashMap::new();
+        for step in &steps {
+            run_step(db, user, step, &mut variables, &mut output).with_context(|| {
+                format!("Failed on line {}, no changes were made", step.line)
+            })?;
+        }
+        Ok(())
+    })?;
+
+    out.write_all(&output)?;
+    writeln!(out, "Applied {} command(s)", steps.len())?;
+    Ok(())
+}
+
+/// Checks the whole plan up front so that a typo on the last line doesn't
+/// cost a run of everything before it.
+fn parse_plan(plan: &str) -> Result<Vec<Step>, UsageError> {
+    let mut steps = Vec::new();
+
+    for (index, line) in plan.lines().enumerate() {
+        let line_number = index + 1;
+        let at_line = |message: String| UsageError(format!("Line {line_number}: {message}"));
+
+        let mut words = split_words(line).map_err(at_line)?;
+        if words.is_empty() {
+            continue;
+        }
+
+        let variable = match words.get(1).map(String::as_str) {
+            Some("=") => {
+                let variable = words.remove(0);
+                words.remove(0);
+                if !is_identifier(&variable) {
+                    return Err(at_line(format!("Invalid variable name {variable:?}")));
+                }
+                Some(variable)
+            },
+            _ => None,
+        };
+
+        let command = parse(&words).map_err(|error| at_line(error.0))?;
+        let Some(command) = command.filter(Command::is_change) else {
+            return Err(at_line(format!("Not a create, update or delete: {}", line.trim())));
+        };
+        if variable.is_some() && !command.is_create() {
+            return Err(at_line("Only create commands can set a variable".to_string()));
+        }
+
+        steps.push(Step { line: line_number, variable, words });
+    }
+
+    Ok(steps)
+}
+
+fn run_step(
+    db: &JiraDatabase,
+    user: Option<&str>,
+    step: &Step,
+    variables: &mut HashMap<String, String>,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let words = step
+        .words
+        .iter()
+        .map(|word| match word.strip_prefix('$') {
+            Some(name) if is_identifier(name) => variables
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Variable ${name} is not set")),
+            _ => Ok(word.clone()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let command = parse(&words)?.context("Missing command")?;
+    let mut output = Vec::new();
+    // Changes report failure as an error, so the exit code is always success.
+    let _ = execute(db, user, command, &mut output)?;
+
+    if let Some(variable) = &step.variable {
+        let key = String::from_utf8(output.clone())?.trim().to_string();
+        variables.insert(variable.clone(), key);
+    }
+    out.extend(output);
+    Ok(())
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a line into words like a shell would: on whitespace, keeping
+/// quoted text together and dropping `#` comments.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '#' if word.is_none() => break,
+            '\'' => {
+                let quoted = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => quoted.push(c),
+                        None => return Err("Unterminated ' quote".to_string()),
+                    }
+                }
+            },
+            '"' => {
+                let quoted = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => quoted.push(c),
+                            Some(c) => quoted.extend(['\\', c]),
+                            None => return Err("Unterminated \" quote".to_string()),
+                        },
+                        Some(c) => quoted.push(c),
+                        None => return Err("Unterminated \" quote".to_string()),
+                    }
+                }
+            },
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_default().push(c),
+                None => return Err("Trailing \\".to_string()),
+            },
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::MockDB;
+
+    fn board() -> JiraDatabase {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let mut out = Vec::new();
+        apply(&db, None, "project create --name Payments --key PAY", &mut out).unwrap();
+        db
+    }
+
+    #[test]
+    fn split_words_should_handle_quotes_and_comments() {
+        let words =
+            split_words(r#"epic create --name "Sprint \"12\"" --description 'a # b' # note"#)
+                .unwrap();
+        assert_eq!(words, vec![
+            "epic",
+            "create",
+            "--name",
+            "Sprint \"12\"",
+            "--description",
+            "a # b"
+        ]);
+
+        assert!(split_words("epic create --name 'open").is_err());
+        assert!(split_words("# only a comment").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_plan_should_reject_bad_lines_before_running() {
+        assert!(parse_plan("epic list --project PAY").is_err());
+        assert!(parse_plan("x = epic delete PAY-1 --yes").is_err());
+        assert!(parse_plan("1x = epic create --project PAY --name a").is_err());
+        assert!(parse_plan("epic create --project PAY").is_err());
+
+        let steps =
+            parse_plan("\n# seed\nsprint = epic create --project PAY --name a\n").unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].line, 3);
+        assert_eq!(steps[0].variable.as_deref(), Some("sprint"));
+    }
+
+    #[test]
+    fn apply_should_pass_created_keys_to_later_lines() {
+        let db = board();
+        let plan = "\
+            sprint = epic create --project PAY --name Sprint
+            story create --epic $sprint --name 'First story'
+            story create --epic $sprint --name 'Second story'
+            epic status $sprint in-progress
+        ";
+
+        let mut out = Vec::new();
+        apply(&db, None, plan, &mut out).unwrap();
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.epics.len(), 1);
+        assert_eq!(db_state.stories.len(), 2);
+        assert!(String::from_utf8(out).unwrap().ends_with("Applied 4 command(s)\n"));
+    }
+
+    #[test]
+    fn apply_should_roll_back_every_change_if_one_fails() {
+        let db = board();
+        let before = db.read().unwrap();
+        let plan = "\
+            sprint = epic create --project PAY --name Sprint
+            story create --epic $sprint --name 'First story'
+            story create --epic PAY-999 --name 'Orphan'
+        ";
+
+        let mut out = Vec::new();
+        let error = apply(&db, None, plan, &mut out).unwrap_err();
+
+        assert!(format!("{error:#}").contains("line 3"));
+        assert!(out.is_empty());
+        assert_eq!(db.read().unwrap(), before);
+    }
+
+    #[test]
+    fn apply_should_fail_on_unset_variable() {
+        let db = board();
+        let mut out = Vec::new();
+
+        assert!(apply(&db, None, "story create --epic $missing --name a", &mut out).is_err());
+        assert!(db.read().unwrap().stories.is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::database::{Backend, DEFAULT_TRASH_RETENTION};
+
+/// Settings read from `$XDG_CONFIG_HOME/jiraffe/config.toml`, e.g.
+///
+/// ```toml
+/// db = "~/boards/work.sqlite"
+/// backend = "sqlite"
+/// trash_retention_days = 14
+/// user = "ana"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub db:                   Option<PathBuf>,
+    pub backend:              Option<String>,
+    pub trash_retention_days: Option<u64>,
+    /// The username to assign new work to and to list under "my work".
+    pub user:                 Option<String>,
+}
+
+impl Config {
+    /// Where the config file lives, if a home or config directory is known.
+    pub fn default_path(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+        let config_dir = match env("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env("HOME")?).join(".config"),
+        };
+
+        Some(config_dir.join("jiraffe").join("config.toml"))
+    }
+
+    /// Reads the config file, treating a missing one as empty. Relative
+    /// paths in it are taken relative to the file itself.
+    pub fn load(path: &Path, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to read file: {}", path.display()));
+            },
+        };
+
+        let mut config: Config = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+        if let Some(db) = config.db.take() {
+            let db = expand_home(db, &env);
+            config.db = Some(match path.parent() {
+                Some(parent) if db.is_relative() => parent.join(db),
+                _ => db,
+            });
+        }
+
+        Ok(config)
+    }
+}
+
+/// Everything needed to open a board, after weighing the command line, the
+/// environment, the config file and the defaults against each other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub db_path:         PathBuf,
+    pub backend:         Backend,
+    pub trash_retention: Duration,
+    /// The current user, if one is configured or `$USER` is set.
+    pub user:            Option<String>,
+}
+
+impl Settings {
+    /// `--db` wins over `JIRAFFE_DB`, which wins over the config file. The
+    /// backend is taken from `JIRAFFE_BACKEND` or the config file, falling
+    /// back to a guess from the database path. The current user comes from
+    /// `JIRAFFE_USER`, the config file or `$USER`, in that order.
+    pub fn resolve(
+        cli_db: Option<PathBuf>,
+        env: impl Fn(&str) -> Option<String>,
+        config: Config,
+    ) -> Result<Self> {
+        let backend = match env("JIRAFFE_BACKEND").or(config.backend) {
+            Some(backend) => Some(backend.parse::<Backend>()?),
+            None => None,
+        };
+
+        let db_path = cli_db
+            .or_else(|| env("JIRAFFE_DB").map(|db| expand_home(PathBuf::from(db), &env)))
+            .or(config.db);
+
+        let (db_path, backend) = match (db_path, backend) {
+            (Some(db_path), Some(backend)) => (db_path, backend),
+            (Some(db_path), None) => {
+                let backend = Backend::from_path(&db_path).unwrap_or(Backend::Json);
+                (db_path, backend)
+            },
+            (None, backend) => {
+                let backend = backend.unwrap_or(Backend::Json);
+                (PathBuf::from(backend.default_path()), backend)
+            },
+        };
+
+        let retention_days = match env("JIRAFFE_TRASH_RETENTION_DAYS") {
+            Some(days) => {
+                Some(days.trim().parse().context("Invalid JIRAFFE_TRASH_RETENTION_DAYS")?)
+            },
+            None => config.trash_retention_days,
+        };
+        let trash_retention = retention_days.map_or(DEFAULT_TRASH_RETENTION, |days: u64| {
+            Duration::from_secs(days * 24 * 60 * 60)
+        });
+
+        let user = env("JIRAFFE_USER")
+            .or(config.user)
+            .or_else(|| env("USER"))
+            .map(|user| user.trim().to_string())
+            .filter(|user| !user.is_empty());
+
+        Ok(Self { db_path, backend, trash_retention, user })
+    }
+}
+
+fn expand_home(path: PathBuf, env: impl Fn(&str) -> Option<String>) -> PathBuf {
+    match (path.strip_prefix("~"), env("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> =
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn default_path_should_follow_xdg() {
+        assert_eq!(
+            Config::default_path(env(&[("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home/me")])),
+            Some(PathBuf::from("/xdg/jiraffe/config.toml"))
+        );
+        assert_eq!(
+            Config::default_path(env(&[("HOME", "/home/me")])),
+            Some(PathBuf::from("/home/me/.config/jiraffe/config.toml"))
+        );
+        assert_eq!(Config::default_path(env(&[])), None);
+    }
+
+    #[test]
+    fn load_should_treat_missing_file_as_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("config.toml"), env(&[])).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn load_should_resolve_db_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        fs::write(&path, "db = \"boards/work.json\"\ntrash_retention_days = 7\n").unwrap();
+        let config = Config::load(&path, env(&[])).unwrap();
+        assert_eq!(config.db, Some(dir.path().join("boards/work.json")));
+        assert_eq!(config.trash_retention_days, Some(7));
+
+        fs::write(&path, "db = \"~/work.json\"\n").unwrap();
+        let config = Config::load(&path, env(&[("HOME", "/home/me")])).unwrap();
+        assert_eq!(config.db, Some(PathBuf::from("/home/me/work.json")));
+    }
+
+    #[test]
+    fn load_should_reject_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "database = \"db.json\"\n").unwrap();
+
+        assert!(Config::load(&path, env(&[])).is_err());
+    }
+
+    #[test]
+    fn resolve_should_prefer_cli_then_env_then_config() {
+        let config = Config { db: Some("config.json".into()), ..Config::default() };
+        let vars = env(&[("JIRAFFE_DB", "env.json")]);
+
+        let settings =
+            Settings::resolve(Some("cli.json".into()), &vars, config.clone()).unwrap();
+        assert_eq!(settings.db_path, PathBuf::from("cli.json"));
+
+        let settings = Settings::resolve(None, &vars, config.clone()).unwrap();
+        assert_eq!(settings.db_path, PathBuf::from("env.json"));
+
+        let settings = Settings::resolve(None, env(&[]), config).unwrap();
+        assert_eq!(settings.db_path, PathBuf::from("config.json"));
+
+        let settings = Settings::resolve(None, env(&[]), Config::default()).unwrap();
+        assert_eq!(settings.db_path, PathBuf::from(Backend::Json.default_path()));
+        assert_eq!(settings.trash_retention, DEFAULT_TRASH_RETENTION);
+    }
+
+    #[test]
+    fn resolve_should_pick_backend() {
+        let settings =
+            Settings::resolve(Some("work.sqlite".into()), env(&[]), Config::default()).unwrap();
+        assert_eq!(settings.backend, Backend::Sqlite);
+
+        let vars = env(&[("JIRAFFE_BACKEND", "events")]);
+        let settings =
+            Settings::resolve(Some("work.db".into()), vars, Config::default()).unwrap();
+        assert_eq!(settings.backend, Backend::EventLog);
+        assert_eq!(settings.db_path, PathBuf::from("work.db"));
+
+        let config = Config { backend: Some("sqlite".to_string()), ..Config::default() };
+        let settings = Settings::resolve(None, env(&[]), config).unwrap();
+        assert_eq!(settings.db_path, PathBuf::from(Backend::Sqlite.default_path()));
+
+        let config = Config { backend: Some("xml".to_string()), ..Config::default() };
+        assert!(Settings::resolve(None, env(&[]), config).is_err());
+    }
+
+    #[test]
+    fn resolve_should_pick_user() {
+        let config = Config { user: Some("ana".to_string()), ..Config::default() };
+        let vars = env(&[("USER", "root")]);
+
+        let settings = Settings::resolve(None, &vars, config).unwrap();
+        assert_eq!(settings.user.as_deref(), Some("ana"));
+
+        let settings = Settings::resolve(None, &vars, Config::default()).unwrap();
+        assert_eq!(settings.user.as_deref(), Some("root"));
+
+        let vars = env(&[("USER", "root"), ("JIRAFFE_USER", "bo")]);
+        let config = Config { user: Some("ana".to_string()), ..Config::default() };
+        let settings = Settings::resolve(None, vars, config).unwrap();
+        assert_eq!(settings.user.as_deref(), Some("bo"));
+
+        let settings = Settings::resolve(None, env(&[]), Config::default()).unwrap();
+        assert_eq!(settings.user, None);
+    }
+}
@@ -1,171 +1,1318 @@
-use std::fs;
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{error, fmt, mem, thread};
+
+use anyhow::{Context, Result, anyhow, bail};
+use itertools::Itertools;
+
+use crate::models::{
+    DBState,
+    Date,
+    Details,
+    Epic,
+    IssueKey,
+    Priority,
+    Project,
+    Schedule,
+    Status,
+    Story,
+    TrashItem,
+    Trashed,
+    User,
+    normalize_label,
+};
+
+mod document;
+mod event_log;
+mod integrity;
+mod json;
+mod migrations;
+mod sqlite;
+mod state;
+
+pub use document::{DocumentDatabase, DocumentStore};
+pub use event_log::EventLogDatabase;
+pub use integrity::Issue;
+use json::JSONFileDatabase;
+pub use sqlite::SQLiteDatabase;
+
+/// How long deleted items stay in the trash unless configured otherwise.
+pub const DEFAULT_TRASH_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The storage engines a `JiraDatabase` can be opened with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+    EventLog,
+}
 
-use anyhow::{Context, Result, anyhow};
+impl Backend {
+    pub fn default_path(self) -> &'static str {
+        match self {
+            Backend::Json => "./data/db.json",
+            Backend::Sqlite => "./data/db.sqlite",
+            Backend::EventLog => "./data/db.jsonl",
+        }
+    }
+
+    /// Guesses the backend from a database file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Backend::Json),
+            "sqlite" | "sqlite3" | "db" => Some(Backend::Sqlite),
+            "jsonl" => Some(Backend::EventLog),
+            _ => None,
+        }
+    }
+}
 
-use crate::models::{DBState, Epic, Status, Story};
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "json" => Ok(Backend::Json),
+            "sqlite" => Ok(Backend::Sqlite),
+            "events" => Ok(Backend::EventLog),
+            other => Err(anyhow!(
+                "Unknown storage backend: {other} (expected json, sqlite or events)"
+            )),
+        }
+    }
+}
 
 pub struct JiraDatabase {
-    pub database: Box<dyn Database>,
+    pub database:  Box<dyn Database>,
+    seen_revision: Cell<Option<u64>>,
+    /// Set while a `batch` holds a transaction open for nested operations.
+    in_batch:      Cell<bool>,
 }
 
 impl JiraDatabase {
     pub fn new(file_path: String) -> Self {
-        Self { database: Box::new(JSONFileDatabase { file_path: file_path.into() }) }
+        Self::from_store(JSONFileDatabase::new(file_path.into()))
     }
 
-    pub fn read(&self) -> Result<DBState> {
-        self.database.read().context("Failed to read from database")
+    /// Opens the given backend. A fresh SQLite database or event log is seeded
+    /// once from a `.json` file next to it, so existing boards carry over.
+    pub fn open(backend: Backend, file_path: impl Into<PathBuf>) -> Result<Self> {
+        let file_path = file_path.into();
+        let json_path = file_path.with_extension("json");
+
+        let database: Box<dyn Database> = match backend {
+            Backend::Json => Box::new(DocumentDatabase::new(JSONFileDatabase::new(file_path))),
+            Backend::Sqlite => {
+                let database = SQLiteDatabase::open(file_path)?;
+                if database.is_empty()? && json_path.exists() {
+                    database.import_json(&json_path)?;
+                    report_import(&json_path, &database.file_path);
+                }
+
+                Box::new(database)
+            },
+            Backend::EventLog => {
+                let database = EventLogDatabase::open(file_path)?;
+                if database.is_empty()? && json_path.exists() {
+                    database.import_json(&json_path)?;
+                    report_import(&json_path, &database.file_path);
+                }
+
+                Box::new(database)
+            },
+        };
+
+        Ok(Self::from_database(database))
     }
 
-    pub fn create_epic(&self, epic: Epic) -> Result<u32> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
+    /// Opens the board at `file_path` only if it is already there, without
+    /// creating, upgrading or importing anything. Returns `None` otherwise.
+    pub fn open_existing(
+        backend: Backend,
+        file_path: impl Into<PathBuf>,
+    ) -> Result<Option<Self>> {
+        let file_path = file_path.into();
+        if !file_path.exists() {
+            return Ok(None);
+        }
 
-        db_state.last_item_id += 1;
-        let epic_id = db_state.last_item_id;
+        let database: Box<dyn Database> = match backend {
+            Backend::Json => Box::new(DocumentDatabase::new(JSONFileDatabase::new(file_path))),
+            Backend::Sqlite => Box::new(SQLiteDatabase::open_read_only(file_path)?),
+            Backend::EventLog => Box::new(EventLogDatabase::open(file_path)?),
+        };
+        Ok(Some(Self::from_database(database)))
+    }
 
-        db_state.epics.insert(epic_id, epic);
+    pub fn from_database(database: Box<dyn Database>) -> Self {
+        Self { database, seen_revision: Cell::new(None), in_batch: Cell::new(false) }
+    }
 
-        self.database.write(&db_state).context("Failed to write to database")?;
-        Ok(epic_id)
+    pub fn from_store(store: impl DocumentStore + 'static) -> Self {
+        Self::from_database(Box::new(DocumentDatabase::new(store)))
     }
 
-    pub fn create_story(&self, story: Story, epic_id: u32) -> Result<u32> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
-        let epic = db_state
-            .epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+    /// Reads the whole board at once. Prefer the narrower queries below for
+    /// anything that only needs a few items.
+    pub fn read(&self) -> Result<DBState> { self.query(|db| db.snapshot()) }
+
+    pub fn get_project(&self, project_id: u32) -> Result<Option<Project>> {
+        self.query(|db| db.get_project(project_id))
+    }
+
+    /// Looks a project up by its key, ignoring case.
+    pub fn find_project(&self, key: &str) -> Result<Option<(u32, Project)>> {
+        let key = key.trim().to_uppercase();
+        self.query(|db| Ok(db.list_projects()?.into_iter().find(|(_, p)| p.key == key)))
+    }
+
+    pub fn list_projects(&self) -> Result<Vec<(u32, Project)>> {
+        self.query(|db| db.list_projects())
+    }
 
-        db_state.last_item_id += 1;
-        let story_id = db_state.last_item_id;
+    pub fn get_epic(&self, epic_id: u32) -> Result<Option<Epic>> {
+        self.query(|db| db.get_epic(epic_id))
+    }
 
-        db_state.stories.insert(story_id, story);
-        epic.stories.push(story_id);
+    pub fn get_story(&self, story_id: u32) -> Result<Option<Story>> {
+        self.query(|db| db.get_story(story_id))
+    }
 
-        self.database.write(&db_state).context("Failed to write to database")?;
-        Ok(story_id)
+    pub fn list_epics(&self, filter: &EpicFilter) -> Result<Vec<(u32, Epic)>> {
+        self.query(|db| db.list_epics(filter))
     }
 
-    pub fn delete_epic(&self, epic_id: u32) -> Result<()> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
-        let epic = db_state
-            .epics
-            .get(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+    pub fn list_stories(&self, filter: &StoryFilter) -> Result<Vec<(u32, Story)>> {
+        self.query(|db| db.list_stories(filter))
+    }
+
+    /// Finds the epic or story behind an issue key like `PAY-12`. A bare id is
+    /// accepted too, as long as some epic or story has it.
+    pub fn resolve_issue_key(&self, key: &str) -> Result<u32> {
+        if let Ok(item_id) = key.trim().parse::<u32>() {
+            let found = self.query(|db| {
+                Ok(db.get_epic(item_id)?.is_some() || db.get_story(item_id)?.is_some())
+            })?;
+            if !found {
+                bail!("No epic or story with id {item_id}!");
+            }
+            return Ok(item_id);
+        }
+
+        let key = key
+            .parse::<IssueKey>()
+            .map_err(|_| anyhow!("Invalid issue key {key:?}, expected e.g. PAY-12"))?;
 
-        for story_id in &epic.stories {
-            db_state.stories.remove(story_id);
+        let found = self.query(|db| {
+            let projects = db.list_projects()?;
+            match projects.iter().find(|(_, project)| project.key == key.project) {
+                Some((_, project)) => project_contains(db, project, key.item_id),
+                None => Ok(false),
+            }
+        })?;
+        if !found {
+            bail!("No epic or story with key {key}!");
         }
+        Ok(key.item_id)
+    }
+
+    /// Maps every epic and story, including those in the trash, to its issue
+    /// key. Items whose project can't be found are left out.
+    pub fn issue_keys(&self) -> Result<HashMap<u32, String>> {
+        self.query(|db| {
+            let projects: HashMap<u32, Project> = db.list_projects()?.into_iter().collect();
+            let epics: HashMap<u32, Epic> =
+                db.list_epics(&EpicFilter::default())?.into_iter().collect();
+            let trash = db.list_trash()?;
+
+            let mut epic_projects: HashMap<u32, u32> = projects
+                .iter()
+                .flat_map(|(project_id, project)| {
+                    project.epics.iter().map(move |epic_id| (*epic_id, *project_id))
+                })
+                .collect();
+            for (item_id, trash_item) in &trash {
+                if let Trashed::Epic { project_id, .. } = trash_item.item {
+                    epic_projects.insert(*item_id, project_id);
+                }
+            }
+
+            let mut keys = HashMap::new();
+            for (epic_id, project_id) in &epic_projects {
+                let Some(project) = projects.get(project_id) else { continue };
+                keys.insert(*epic_id, project.issue_key(*epic_id));
+
+                for story_id in epics.get(epic_id).into_iter().flat_map(|epic| &epic.stories) {
+                    keys.insert(*story_id, project.issue_key(*story_id));
+                }
+            }
+            for (item_id, trash_item) in &trash {
+                if let Trashed::Story { epic_id, .. } = trash_item.item
+                    && let Some(project) =
+                        epic_projects.get(&epic_id).and_then(|id| projects.get(id))
+                {
+                    keys.insert(*item_id, project.issue_key(*item_id));
+                }
+            }
+
+            Ok(keys)
+        })
+    }
+
+    /// Creates a project after upper-casing its key, which must be unique.
+    pub fn create_project(&self, mut project: Project) -> Result<u32> {
+        project.key = project.key.trim().to_uppercase();
+        validate_project_key(&project.key)?;
 
-        db_state.epics.remove(&epic_id);
+        self.transaction("create_project", |db| {
+            if db.list_projects()?.iter().any(|(_, existing)| existing.key == project.key) {
+                bail!("A project with key {} already exists!", project.key);
+            }
+
+            let project_id = db.next_id()?;
+            db.insert_project(project_id, &project)?;
+            Ok(project_id)
+        })
+    }
+
+    pub fn create_epic(&self, epic: Epic, project_id: u32) -> Result<u32> {
+        self.transaction("create_epic", |db| {
+            let mut project = require_project(db, project_id)?;
+
+            let epic_id = db.next_id()?;
+            db.insert_epic(epic_id, &epic)?;
+
+            project.epics.push(epic_id);
+            db.update_project(project_id, &project)?;
+            Ok(epic_id)
+        })
+    }
+
+    pub fn create_story(&self, story: Story, epic_id: u32) -> Result<u32> {
+        self.transaction("create_story", |db| {
+            let mut epic = require_epic(db, epic_id)?;
+
+            let story_id = db.next_id()?;
+            db.insert_story(story_id, &story)?;
+
+            epic.stories.push(story_id);
+            db.update_epic(epic_id, &epic)?;
+            Ok(story_id)
+        })
+    }
+
+    /// Moves the epic to the trash, taking its stories along with it.
+    pub fn delete_epic(&self, project_id: u32, epic_id: u32) -> Result<()> {
+        self.transaction("delete_epic", |db| {
+            let mut project = require_project(db, project_id)?;
+            let epic = require_epic(db, epic_id)?;
+
+            let Some(position) = project.epics.iter().position(|&id| id == epic_id) else {
+                bail!("Epic with id {epic_id} not found in project with id {project_id}!");
+            };
+            project.epics.remove(position);
+            db.update_project(project_id, &project)?;
+
+            let mut stories = HashMap::with_capacity(epic.stories.len());
+            for story_id in &epic.stories {
+                if let Some(story) = db.get_story(*story_id)? {
+                    stories.insert(*story_id, story);
+                }
+                db.delete_story(*story_id)?;
+            }
+
+            db.delete_epic(epic_id)?;
 
-        self.database.write(&db_state).context("Failed to write to database")
+            let item = Trashed::Epic { project_id, position, epic, stories };
+            db.insert_trash_item(epic_id, &TrashItem { deleted_at: unix_timestamp(), item })
+        })
     }
 
+    /// Moves the story to the trash, remembering its place in the epic.
     pub fn delete_story(&self, epic_id: u32, story_id: u32) -> Result<()> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
+        self.transaction("delete_story", |db| {
+            let mut epic = require_epic(db, epic_id)?;
+            let story = require_story(db, story_id)?;
+
+            let Some(position) = epic.stories.iter().position(|&id| id == story_id) else {
+                bail!("Story with id {story_id} not found in epic with id {epic_id}!");
+            };
+            epic.stories.remove(position);
+            db.update_epic(epic_id, &epic)?;
+            db.delete_story(story_id)?;
+
+            let item = Trashed::Story { epic_id, position, story };
+            db.insert_trash_item(story_id, &TrashItem { deleted_at: unix_timestamp(), item })
+        })
+    }
+
+    /// Moves the story to the end of another epic's stories, returning the
+    /// position it had in the epic it left.
+    pub fn move_story(&self, story_id: u32, from_epic: u32, to_epic: u32) -> Result<usize> {
+        self.move_story_to(story_id, from_epic, to_epic, usize::MAX)
+    }
+
+    /// Moves the story to `position` among another epic's stories, or to the
+    /// end if there are fewer. Returns the position it had in the epic it left.
+    /// The epics must be in the same project, as the story's key would change
+    /// otherwise.
+    pub fn move_story_to(
+        &self,
+        story_id: u32,
+        from_epic: u32,
+        to_epic: u32,
+        position: usize,
+    ) -> Result<usize> {
+        self.transaction("move_story", |db| {
+            require_story(db, story_id)?;
+            let mut from = require_epic(db, from_epic)?;
+
+            let Some(previous) = from.stories.iter().position(|&id| id == story_id) else {
+                bail!("Story with id {story_id} not found in epic with id {from_epic}!");
+            };
+            from.stories.remove(previous);
+
+            let mut to = if to_epic == from_epic {
+                from
+            } else {
+                let to = require_epic(db, to_epic)?;
+                if epic_project(db, from_epic)? != epic_project(db, to_epic)? {
+                    bail!("Epic with id {to_epic} is not in the same project as the story!");
+                }
+                db.update_epic(from_epic, &from)?;
+                to
+            };
+            to.stories.insert(position.min(to.stories.len()), story_id);
+            db.update_epic(to_epic, &to)?;
+            Ok(previous)
+        })
+    }
+
+    /// Moves the story to `position` in its epic's ranking, returning the
+    /// position it had before.
+    pub fn rank_story(&self, epic_id: u32, story_id: u32, position: usize) -> Result<usize> {
+        self.move_story_to(story_id, epic_id, epic_id, position)
+    }
+
+    /// Finds the epic whose stories include this one.
+    pub fn find_story_epic(&self, story_id: u32) -> Result<Option<u32>> {
+        let epics = self.list_epics(&EpicFilter::default())?;
+        Ok(epics
+            .into_iter()
+            .find(|(_, epic)| epic.stories.contains(&story_id))
+            .map(|(epic_id, _)| epic_id))
+    }
+
+    pub fn list_trash(&self) -> Result<Vec<(u32, TrashItem)>> {
+        self.query(|db| db.list_trash())
+    }
+
+    /// Takes an item out of the trash and puts it back under its original id.
+    pub fn restore(&self, item_id: u32) -> Result<Trashed> {
+        self.transaction("restore", |db| {
+            let trash_item = require_trash_item(db, item_id)?;
+
+            match &trash_item.item {
+                Trashed::Epic { project_id, position, epic, stories } => {
+                    let mut project = require_project(db, *project_id)?;
+
+                    for story_id in &epic.stories {
+                        if let Some(story) = stories.get(story_id) {
+                            db.insert_story(*story_id, story)?;
+                        }
+                    }
+                    db.insert_epic(item_id, epic)?;
+
+                    project.epics.insert((*position).min(project.epics.len()), item_id);
+                    db.update_project(*project_id, &project)?;
+                },
+                Trashed::Story { epic_id, position, story } => {
+                    if db.get_trash_item(*epic_id)?.is_some() {
+                        bail!("Epic with id {epic_id} is in the trash, restore it first!");
+                    }
+
+                    let mut epic = require_epic(db, *epic_id)?;
+                    db.insert_story(item_id, story)?;
+
+                    epic.stories.insert((*position).min(epic.stories.len()), item_id);
+                    db.update_epic(*epic_id, &epic)?;
+                },
+            }
+
+            db.delete_trash_item(item_id)?;
+            Ok(trash_item.item)
+        })
+    }
+
+    /// Deletes an item from the trash for good.
+    pub fn purge(&self, item_id: u32) -> Result<()> {
+        self.transaction("purge", |db| {
+            require_trash_item(db, item_id)?;
+            db.delete_trash_item(item_id)
+        })
+    }
+
+    /// Purges everything in the trash and returns how many items went.
+    pub fn empty_trash(&self) -> Result<usize> { self.purge_where("empty_trash", |_| true) }
+
+    /// Purges items that have been in the trash for longer than `retention`.
+    pub fn purge_expired(&self, retention: Duration) -> Result<usize> {
+        let cutoff = unix_timestamp().saturating_sub(retention.as_secs());
+        self.purge_where("purge_expired", |item| item.deleted_at <= cutoff)
+    }
 
-        let epic = db_state
-            .epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+    /// Reports broken references and id clashes without changing anything.
+    pub fn check_integrity(&self) -> Result<Vec<Issue>> {
+        self.query(|db| Ok(integrity::check(&db.snapshot()?)))
+    }
+
+    /// Fixes everything `check_integrity` reports and returns what was fixed.
+    pub fn repair_integrity(&self) -> Result<Vec<Issue>> {
+        if self.check_integrity()?.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.transaction("repair_integrity", |db| {
+            let db_state = db.snapshot()?;
+            let issues = integrity::check(&db_state);
+
+            integrity::write_changes(db, &db_state, &integrity::repair(&db_state))?;
+            Ok(issues)
+        })
+    }
 
-        if !db_state.stories.contains_key(&story_id) {
-            return Err(anyhow!("Story with id {story_id} not found!"));
+    fn purge_where(
+        &self,
+        operation: &str,
+        purge: impl Fn(&TrashItem) -> bool,
+    ) -> Result<usize> {
+        // Avoid bumping the revision when there is nothing to purge.
+        if !self.list_trash()?.iter().any(|(_, item)| purge(item)) {
+            return Ok(0);
         }
 
-        epic.stories.retain(|&id| id != story_id);
-        db_state.stories.remove(&story_id);
+        self.transaction(operation, |db| {
+            let mut purged = 0;
+            for (item_id, item) in db.list_trash()? {
+                if purge(&item) {
+                    db.delete_trash_item(item_id)?;
+                    purged += 1;
+                }
+            }
+            Ok(purged)
+        })
+    }
+
+    /// Returns the status the epic had before.
+    pub fn update_epic_status(&self, epic_id: u32, status: Status) -> Result<Status> {
+        self.transaction("update_epic_status", |db| {
+            let mut epic = require_epic(db, epic_id)?;
+
+            let previous = mem::replace(&mut epic.status, status);
+            db.update_epic(epic_id, &epic)?;
+            Ok(previous)
+        })
+    }
+
+    /// Returns the status the story had before.
+    pub fn update_story_status(&self, story_id: u32, status: Status) -> Result<Status> {
+        self.transaction("update_story_status", |db| {
+            let mut story = require_story(db, story_id)?;
+
+            let previous = mem::replace(&mut story.status, status);
+            db.update_story(story_id, &story)?;
+            Ok(previous)
+        })
+    }
+
+    /// Renames the epic and replaces its description and priority, returning
+    /// the previous ones.
+    pub fn update_epic_details(&self, epic_id: u32, details: Details) -> Result<Details> {
+        self.transaction("update_epic_details", |db| {
+            let mut epic = require_epic(db, epic_id)?;
+
+            let previous = Details {
+                name:        mem::replace(&mut epic.name, details.name),
+                description: mem::replace(&mut epic.description, details.description),
+                priority:    mem::replace(&mut epic.priority, details.priority),
+            };
+            db.update_epic(epic_id, &epic)?;
+            Ok(previous)
+        })
+    }
+
+    /// Renames the story and replaces its description and priority, returning
+    /// the previous ones.
+    pub fn update_story_details(&self, story_id: u32, details: Details) -> Result<Details> {
+        self.transaction("update_story_details", |db| {
+            let mut story = require_story(db, story_id)?;
+
+            let previous = Details {
+                name:        mem::replace(&mut story.name, details.name),
+                description: mem::replace(&mut story.description, details.description),
+                priority:    mem::replace(&mut story.priority, details.priority),
+            };
+            db.update_story(story_id, &story)?;
+            Ok(previous)
+        })
+    }
+
+    /// Adds a user to the board's directory under a username that isn't taken
+    /// yet.
+    pub fn create_user(&self, username: &str, user: User) -> Result<()> {
+        validate_username(username)?;
+
+        self.transaction("create_user", |db| {
+            if db.get_user(username)?.is_some() {
+                bail!("User {username} already exists!");
+            }
+            db.insert_user(username, &user)
+        })
+    }
+
+    pub fn get_user(&self, username: &str) -> Result<Option<User>> {
+        self.query(|db| db.get_user(username))
+    }
+
+    pub fn list_users(&self) -> Result<Vec<(String, User)>> { self.query(|db| db.list_users()) }
+
+    /// Makes a user the epic's owner, or leaves it without one for `None`,
+    /// returning the previous owner.
+    pub fn assign_epic(&self, epic_id: u32, owner: Option<String>) -> Result<Option<String>> {
+        self.transaction("assign_epic", |db| {
+            if let Some(username) = &owner {
+                require_user(db, username)?;
+            }
+
+            let mut epic = require_epic(db, epic_id)?;
+            let previous = mem::replace(&mut epic.owner, owner);
+            db.update_epic(epic_id, &epic)?;
+            Ok(previous)
+        })
+    }
+
+    /// Assigns the story to a user, or unassigns it for `None`, returning the
+    /// previous assignee.
+    pub fn assign_story(
+        &self,
+        story_id: u32,
+        assignee: Option<String>,
+    ) -> Result<Option<String>> {
+        self.transaction("assign_story", |db| {
+            if let Some(username) = &assignee {
+                require_user(db, username)?;
+            }
+
+            let mut story = require_story(db, story_id)?;
+            let previous = mem::replace(&mut story.assignee, assignee);
+            db.update_story(story_id, &story)?;
+            Ok(previous)
+        })
+    }
+
+    /// Adds a label to an epic or story, returning it as normalised, or
+    /// `None` if the item already had it.
+    pub fn add_label(&self, item_id: u32, label: &str) -> Result<Option<String>> {
+        let label = parse_label(label)?;
+
+        self.transaction("add_label", |db| {
+            let added =
+                edit_labels(db, item_id, |labels| match labels.binary_search(&label) {
+                    Ok(_) => false,
+                    Err(index) => {
+                        labels.insert(index, label.clone());
+                        true
+                    },
+                })?;
+            Ok(added.then_some(label))
+        })
+    }
+
+    /// Removes a label from an epic or story, returning whether it had it.
+    pub fn remove_label(&self, item_id: u32, label: &str) -> Result<bool> {
+        let label = parse_label(label)?;
 
-        self.database.write(&db_state).context("Failed to write to database")
+        self.transaction("remove_label", |db| {
+            edit_labels(db, item_id, |labels| {
+                let count = labels.len();
+                labels.retain(|existing| *existing != label);
+                labels.len() != count
+            })
+        })
     }
 
-    pub fn update_epic_status(&self, epic_id: u32, status: Status) -> Result<()> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
+    /// Sets when an epic or story starts and is due, returning its previous
+    /// schedule.
+    pub fn schedule(&self, item_id: u32, schedule: Schedule) -> Result<Schedule> {
+        if let (Some(start), Some(due)) = (schedule.start, schedule.due)
+            && start > due
+        {
+            bail!("Start date {start} is after due date {due}");
+        }
 
-        let epic = db_state
-            .epics
-            .get_mut(&epic_id)
-            .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+        self.transaction("schedule", |db| {
+            if let Some(mut epic) = db.get_epic(item_id)? {
+                let previous = Schedule::from(&epic);
+                (epic.start_date, epic.due_date) = (schedule.start, schedule.due);
+                db.update_epic(item_id, &epic)?;
+                return Ok(previous);
+            }
+
+            let mut story = require_story(db, item_id)?;
+            let previous = Schedule::from(&story);
+            (story.start_date, story.due_date) = (schedule.start, schedule.due);
+            db.update_story(item_id, &story)?;
+            Ok(previous)
+        })
+    }
 
-        epic.status = status;
-        self.database.write(&db_state).context("Failed to write to database")
+    /// Lists the labels of live epics and stories, sorted.
+    pub fn list_labels(&self) -> Result<Vec<String>> {
+        self.query(|db| {
+            let epics = db.list_epics(&EpicFilter::default())?;
+            let stories = db.list_stories(&StoryFilter::default())?;
+            Ok(epics
+                .into_iter()
+                .flat_map(|(_, epic)| epic.labels)
+                .chain(stories.into_iter().flat_map(|(_, story)| story.labels))
+                .sorted()
+                .dedup()
+                .collect())
+        })
     }
 
-    pub fn update_story_status(&self, story_id: u32, status: Status) -> Result<()> {
-        let mut db_state = self.database.read().context("Failed to read from database")?;
+    /// Renames a label on every epic and story, trashed ones included. Items
+    /// that already have the new label keep just one of it. Returns how many
+    /// items were changed.
+    pub fn rename_label(&self, from: &str, to: &str) -> Result<usize> {
+        let (from, to) = (parse_label(from)?, parse_label(to)?);
+        if from == to {
+            bail!("Label {from} is already called {to}");
+        }
+
+        let rename = |labels: &mut Vec<String>| {
+            let Some(index) = labels.iter().position(|label| *label == from) else {
+                return false;
+            };
+            labels[index] = to.clone();
+            labels.sort();
+            labels.dedup();
+            true
+        };
+
+        self.transaction("rename_label", |db| {
+            let mut renamed = 0;
+
+            for (epic_id, mut epic) in db.list_epics(&EpicFilter::default())? {
+                if rename(&mut epic.labels) {
+                    db.update_epic(epic_id, &epic)?;
+                    renamed += 1;
+                }
+            }
+
+            for (story_id, mut story) in db.list_stories(&StoryFilter::default())? {
+                if rename(&mut story.labels) {
+                    db.update_story(story_id, &story)?;
+                    renamed += 1;
+                }
+            }
+
+            for (item_id, mut trash_item) in db.list_trash()? {
+                let count = match &mut trash_item.item {
+                    Trashed::Epic { epic, stories, .. } => {
+                        let stories = stories.values_mut().map(|story| &mut story.labels);
+                        [&mut epic.labels]
+                            .into_iter()
+                            .chain(stories)
+                            .map(&rename)
+                            .filter(|&renamed| renamed)
+                            .count()
+                    },
+                    Trashed::Story { story, .. } => usize::from(rename(&mut story.labels)),
+                };
+                if count > 0 {
+                    db.delete_trash_item(item_id)?;
+                    db.insert_trash_item(item_id, &trash_item)?;
+                    renamed += count;
+                }
+            }
+
+            Ok(renamed)
+        })
+    }
 
-        let story = db_state
-            .stories
-            .get_mut(&story_id)
-            .ok_or_else(|| anyhow!("Story with id {} not found!", story_id))?;
+    /// Runs `operations` as a single transaction: every query and change made
+    /// through this database in the meantime joins it, and if `operations`
+    /// fails none of the changes are kept.
+    pub fn batch<T>(
+        &self,
+        operation: &str,
+        operations: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if self.in_batch.get() {
+            bail!("A batch is already in progress");
+        }
 
-        story.status = status;
-        self.database.write(&db_state).context("Failed to write to database")
+        self.transaction(operation, |_| {
+            self.in_batch.set(true);
+            let output = operations();
+            self.in_batch.set(false);
+            output
+        })
     }
+
+    /// Runs a read under a shared lock and remembers the revision it saw, so a
+    /// later mutation can tell whether someone else wrote in between.
+    fn query<T>(&self, read: impl FnOnce(&dyn Database) -> Result<T>) -> Result<T> {
+        if self.in_batch.get() {
+            return read(&*self.database);
+        }
+
+        let _lock = self.database.lock(LockMode::Shared)?;
+
+        let revision = self.database.revision().context("Failed to read from database")?;
+        let output = read(&*self.database).context("Failed to read from database")?;
+
+        self.seen_revision.set(Some(revision));
+        Ok(output)
+    }
+
+    /// Runs `change` as a single transaction under an exclusive lock, refusing
+    /// to apply it if the state changed since this session last read it.
+    fn transaction<T>(
+        &self,
+        operation: &str,
+        change: impl FnOnce(&dyn Database) -> Result<T>,
+    ) -> Result<T> {
+        if self.in_batch.get() {
+            return change(&*self.database);
+        }
+
+        let _lock = self.database.lock(LockMode::Exclusive)?;
+        self.database.begin(operation).context("Failed to read from database")?;
+
+        let output = match self.check_revision().and_then(|()| change(&*self.database)) {
+            Ok(output) => output,
+            Err(error) => {
+                self.database.rollback()?;
+                return Err(error);
+            },
+        };
+
+        let revision = match self.database.commit() {
+            Ok(revision) => revision,
+            Err(error) => {
+                self.database.rollback()?;
+                return Err(error.context("Failed to write to database"));
+            },
+        };
+
+        self.seen_revision.set(Some(revision));
+        Ok(output)
+    }
+
+    fn check_revision(&self) -> Result<()> {
+        let revision = self.database.revision()?;
+
+        match self.seen_revision.get() {
+            Some(seen) if seen != revision => {
+                Err(RevisionConflict { expected: seen, actual: revision }.into())
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+fn report_import(json_path: &Path, file_path: &Path) {
+    eprintln!("Imported {} into {}", json_path.display(), file_path.display());
+}
+
+/// Project keys are 2 to 10 upper-case letters or digits, starting with a
+/// letter, so that issue keys like `PAY-12` parse unambiguously.
+fn validate_project_key(key: &str) -> Result<()> {
+    let valid = (2..=10).contains(&key.len())
+        && key.starts_with(|c: char| c.is_ascii_uppercase())
+        && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+    if !valid {
+        bail!(
+            "Invalid project key {key:?}: use 2 to 10 letters or digits, starting with a \
+             letter"
+        );
+    }
+    Ok(())
+}
+
+/// Usernames are 1 to 32 letters, digits, dots, dashes or underscores, so
+/// that `$USER` is usually a valid one.
+fn validate_username(username: &str) -> Result<()> {
+    let valid = (1..=32).contains(&username.len())
+        && !username.starts_with('-')
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+
+    if !valid {
+        bail!(
+            "Invalid username {username:?}: use up to 32 letters, digits, dots, dashes or \
+             underscores"
+        );
+    }
+    Ok(())
+}
+
+fn require_project(db: &dyn Database, project_id: u32) -> Result<Project> {
+    db.get_project(project_id)?
+        .ok_or_else(|| anyhow!("Project with id {project_id} not found!"))
+}
+
+/// Whether the epic or story with this id belongs to the project.
+fn project_contains(db: &dyn Database, project: &Project, item_id: u32) -> Result<bool> {
+    if project.epics.contains(&item_id) {
+        return Ok(true);
+    }
+
+    for epic_id in &project.epics {
+        if db.get_epic(*epic_id)?.is_some_and(|epic| epic.stories.contains(&item_id)) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// The id of the project that lists the epic, if any does.
+fn epic_project(db: &dyn Database, epic_id: u32) -> Result<Option<u32>> {
+    let projects = db.list_projects()?;
+    Ok(projects
+        .into_iter()
+        .find(|(_, project)| project.epics.contains(&epic_id))
+        .map(|(project_id, _)| project_id))
+}
+
+fn require_epic(db: &dyn Database, epic_id: u32) -> Result<Epic> {
+    db.get_epic(epic_id)?.ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))
+}
+
+fn require_story(db: &dyn Database, story_id: u32) -> Result<Story> {
+    db.get_story(story_id)?.ok_or_else(|| anyhow!("Story with id {story_id} not found!"))
+}
+
+fn parse_label(label: &str) -> Result<String> {
+    normalize_label(label).ok_or_else(|| {
+        anyhow!("Invalid label {label:?}: use up to 32 letters, digits or dashes")
+    })
+}
+
+/// Lets `edit` change the labels of the epic or story with `item_id`, saving
+/// the item if it returns true.
+fn edit_labels(
+    db: &dyn Database,
+    item_id: u32,
+    edit: impl FnOnce(&mut Vec<String>) -> bool,
+) -> Result<bool> {
+    if let Some(mut epic) = db.get_epic(item_id)? {
+        let changed = edit(&mut epic.labels);
+        if changed {
+            db.update_epic(item_id, &epic)?;
+        }
+        return Ok(changed);
+    }
+
+    let mut story = require_story(db, item_id)?;
+    let changed = edit(&mut story.labels);
+    if changed {
+        db.update_story(item_id, &story)?;
+    }
+    Ok(changed)
+}
+
+fn require_user(db: &dyn Database, username: &str) -> Result<User> {
+    db.get_user(username)?.ok_or_else(|| anyhow!("User {username} not found!"))
+}
+
+fn require_trash_item(db: &dyn Database, item_id: u32) -> Result<TrashItem> {
+    db.get_trash_item(item_id)?
+        .ok_or_else(|| anyhow!("Item with id {item_id} not found in the trash!"))
+}
+
+/// Seconds since the Unix epoch, as stored in deletion and event timestamps.
+fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
 }
 
+/// Entity-level storage. Mutations are only accepted between `begin` and
+/// `commit`, and other sessions see all of them at once or none at all.
 pub trait Database {
-    fn read(&self) -> Result<DBState>;
-    fn write(&self, db_state: &DBState) -> Result<()>;
+    /// Blocks until the requested advisory lock is held or the backend gives
+    /// up. Backends without cross-process access don't need to lock at all.
+    fn lock(&self, _mode: LockMode) -> Result<DatabaseLock> { Ok(DatabaseLock::default()) }
+
+    /// Starts a transaction for the named `JiraDatabase` operation, which
+    /// backends may record alongside the changes.
+    fn begin(&self, operation: &str) -> Result<()>;
+    /// Applies every change since `begin` and returns the new revision.
+    fn commit(&self) -> Result<u64>;
+    /// Discards every change since `begin`; a no-op outside a transaction.
+    fn rollback(&self) -> Result<()>;
+
+    fn revision(&self) -> Result<u64>;
+    /// Allocates an id that no epic or story has used before.
+    fn next_id(&self) -> Result<u32>;
+    /// Makes sure no id up to `last_item_id` is handed out again.
+    fn reserve_ids(&self, last_item_id: u32) -> Result<()>;
+    fn snapshot(&self) -> Result<DBState>;
+
+    fn get_project(&self, project_id: u32) -> Result<Option<Project>>;
+    /// Lists every project ordered by id.
+    fn list_projects(&self) -> Result<Vec<(u32, Project)>>;
+    fn insert_project(&self, project_id: u32, project: &Project) -> Result<()>;
+    fn update_project(&self, project_id: u32, project: &Project) -> Result<()>;
+    /// Removes the project only; its epics are left for the caller to handle.
+    /// Deleting a missing project is a no-op.
+    fn delete_project(&self, project_id: u32) -> Result<()>;
+
+    fn get_epic(&self, epic_id: u32) -> Result<Option<Epic>>;
+    /// Lists matching epics ordered by id.
+    fn list_epics(&self, filter: &EpicFilter) -> Result<Vec<(u32, Epic)>>;
+    fn insert_epic(&self, epic_id: u32, epic: &Epic) -> Result<()>;
+    fn update_epic(&self, epic_id: u32, epic: &Epic) -> Result<()>;
+    /// Removes the epic only; its stories are left for the caller to handle.
+    /// Deleting a missing epic is a no-op.
+    fn delete_epic(&self, epic_id: u32) -> Result<()>;
+
+    fn get_story(&self, story_id: u32) -> Result<Option<Story>>;
+    /// Lists matching stories, in the epic's order when filtering by epic and
+    /// ordered by id otherwise.
+    fn list_stories(&self, filter: &StoryFilter) -> Result<Vec<(u32, Story)>>;
+    fn insert_story(&self, story_id: u32, story: &Story) -> Result<()>;
+    fn update_story(&self, story_id: u32, story: &Story) -> Result<()>;
+    /// Deleting a missing story is a no-op.
+    fn delete_story(&self, story_id: u32) -> Result<()>;
+
+    fn get_trash_item(&self, item_id: u32) -> Result<Option<TrashItem>>;
+    /// Lists the trash, most recently deleted first.
+    fn list_trash(&self) -> Result<Vec<(u32, TrashItem)>>;
+    fn insert_trash_item(&self, item_id: u32, item: &TrashItem) -> Result<()>;
+    /// Deleting a missing trash item is a no-op.
+    fn delete_trash_item(&self, item_id: u32) -> Result<()>;
+
+    fn get_user(&self, username: &str) -> Result<Option<User>>;
+    /// Lists every user ordered by username.
+    fn list_users(&self) -> Result<Vec<(String, User)>>;
+    fn insert_user(&self, username: &str, user: &User) -> Result<()>;
 }
 
-struct JSONFileDatabase {
-    pub file_path: PathBuf,
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpicFilter {
+    pub project_id: Option<u32>,
+    pub status:     Option<Status>,
+    pub priority:   Option<Priority>,
+    pub owner:      Option<String>,
+    /// A normalised label the epic must have.
+    pub label:      Option<String>,
+    /// Only epics that are overdue on this day.
+    pub overdue_on: Option<Date>,
 }
 
-impl Database for JSONFileDatabase {
-    fn read(&self) -> Result<DBState> {
-        let content = fs::read_to_string(&self.file_path)
-            .with_context(|| format!("Failed to read file: {}", self.file_path.display()))?;
+impl EpicFilter {
+    /// Checks the epic's own fields; `project_id` is resolved by the backend.
+    pub fn matches(&self, epic: &Epic) -> bool {
+        self.status.is_none_or(|status| status == epic.status)
+            && self.priority.is_none_or(|priority| priority == epic.priority)
+            && self.owner.as_ref().is_none_or(|owner| epic.owner.as_ref() == Some(owner))
+            && self.label.as_ref().is_none_or(|label| epic.labels.contains(label))
+            && self.overdue_on.is_none_or(|today| epic.is_overdue(today))
+    }
+}
 
-        serde_json::from_str(&content).with_context(|| {
-            format!("Failed to parse JSON from file: {}", self.file_path.display())
-        })
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoryFilter {
+    pub project_id: Option<u32>,
+    pub epic_id:    Option<u32>,
+    pub status:     Option<Status>,
+    pub priority:   Option<Priority>,
+    pub assignee:   Option<String>,
+    /// A normalised label the story must have.
+    pub label:      Option<String>,
+    /// Only stories that are overdue on this day.
+    pub overdue_on: Option<Date>,
+}
+
+impl StoryFilter {
+    /// Checks the story's own fields; `project_id` and `epic_id` are resolved
+    /// by the backend.
+    pub fn matches(&self, story: &Story) -> bool {
+        self.status.is_none_or(|status| status == story.status)
+            && self.priority.is_none_or(|priority| priority == story.priority)
+            && self
+                .assignee
+                .as_ref()
+                .is_none_or(|assignee| story.assignee.as_ref() == Some(assignee))
+            && self.label.as_ref().is_none_or(|label| story.labels.contains(label))
+            && self.overdue_on.is_none_or(|today| story.is_overdue(today))
+    }
+}
+
+/// Returned when a write was based on a revision that is no longer current.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RevisionConflict {
+    pub expected: u64,
+    pub actual:   u64,
+}
+
+impl fmt::Display for RevisionConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Database was changed by another session (expected revision {}, found {})",
+            self.expected, self.actual
+        )
     }
+}
+
+impl error::Error for RevisionConflict {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+/// An advisory lock on the database, released when dropped.
+#[derive(Debug, Default)]
+pub struct DatabaseLock {
+    _file: Option<File>,
+}
 
-    fn write(&self, data: &DBState) -> Result<()> {
-        if let Some(parent) = self.file_path.parent() {
+impl DatabaseLock {
+    /// Polls for an OS-level lock on `lock_path` until `timeout` runs out.
+    fn acquire(lock_path: &Path, mode: LockMode, timeout: Duration) -> Result<Self> {
+        if let Some(parent) = lock_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let json_data =
-            serde_json::to_vec_pretty(data).context("Failed to serialize data to JSON")?;
-
-        fs::write(&self.file_path, json_data).with_context(|| {
-            format!("Failed to write to file: {}", self.file_path.display())
-        })?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(lock_path)
+            .with_context(|| format!("Failed to open lock file: {}", lock_path.display()))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let result = match mode {
+                LockMode::Shared => file.try_lock_shared(),
+                LockMode::Exclusive => file.try_lock(),
+            };
 
-        Ok(())
+            match result {
+                Ok(()) => return Ok(Self { _file: Some(file) }),
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                },
+                Err(TryLockError::WouldBlock) => bail!(
+                    "Timed out after {timeout:?} waiting for a lock on {}; another jiraffe \
+                     session is using the database",
+                    lock_path.display()
+                ),
+                Err(TryLockError::Error(error)) => {
+                    return Err(error).with_context(|| {
+                        format!("Failed to lock file: {}", lock_path.display())
+                    });
+                },
+            }
+        }
     }
 }
 
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
 #[cfg(test)]
 mod tests {
-    use super::test_utils::MockDB;
+    use super::test_utils::{MockDB, test_project};
     use super::*;
 
     #[test]
     fn create_epic_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic.clone());
+        let result = db.create_epic(epic.clone(), project_id);
         assert!(result.is_ok());
 
         let id = result.unwrap();
         let db_state = db.read().unwrap();
-        let expected_id = 1;
+        let expected_id = 2;
 
         assert_eq!(id, expected_id);
         assert_eq!(db_state.last_item_id, expected_id);
         assert_eq!(db_state.epics.get(&id), Some(&epic));
     }
 
+    #[test]
+    fn create_project_should_validate_and_normalise_key() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project = |key: &str| Project::new("".to_string(), key.to_string(), "".to_string());
+
+        let project_id = db.create_project(project(" pay ")).unwrap();
+        assert_eq!(db.get_project(project_id).unwrap().unwrap().key, "PAY");
+        assert_eq!(db.find_project("Pay").unwrap().map(|(id, _)| id), Some(project_id));
+
+        assert!(db.create_project(project("PAY")).is_err());
+        assert!(db.create_project(project("P")).is_err());
+        assert!(db.create_project(project("1PAY")).is_err());
+        assert!(db.create_project(project("PAY-2")).is_err());
+        assert!(db.create_project(project("PAYMENTSAPI")).is_err());
+        assert_eq!(db.list_projects().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn create_epic_should_fail_if_invalid_project_id() {
+        let db = JiraDatabase::from_store(MockDB::new());
+
+        assert!(db.create_epic(Epic::new("".to_string(), "".to_string()), 999).is_err());
+    }
+
+    #[test]
+    fn batch_should_commit_all_changes_at_once() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let revision = db.read().unwrap().revision;
+
+        let story_id = db
+            .batch("test", || {
+                let epic_id =
+                    db.create_epic(Epic::new("".to_string(), "".to_string()), project_id)?;
+                db.create_story(Story::new("".to_string(), "".to_string()), epic_id)
+            })
+            .unwrap();
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.revision, revision + 1);
+        assert!(db_state.stories.contains_key(&story_id));
+    }
+
+    #[test]
+    fn batch_should_roll_back_all_changes_if_one_fails() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let before = db.read().unwrap();
+
+        let result = db.batch("test", || {
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id)?;
+            db.create_story(Story::new("".to_string(), "".to_string()), 999)
+        });
+
+        assert!(result.is_err());
+        assert_eq!(db.read().unwrap(), before);
+        assert!(db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).is_ok());
+    }
+
+    #[test]
+    fn list_queries_should_be_scoped_to_project() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let first = test_project(&db);
+        let second = db
+            .create_project(Project::new("".to_string(), "OPS".to_string(), "".to_string()))
+            .unwrap();
+
+        let first_epic =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), first).unwrap();
+        let second_epic =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), second).unwrap();
+        let story = Story::new("".to_string(), "".to_string());
+        let first_story = db.create_story(story.clone(), first_epic).unwrap();
+        db.create_story(story, second_epic).unwrap();
+
+        let filter = EpicFilter { project_id: Some(first), ..EpicFilter::default() };
+        let ids: Vec<u32> =
+            db.list_epics(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![first_epic]);
+
+        let filter = StoryFilter { project_id: Some(first), ..StoryFilter::default() };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![first_story]);
+
+        assert!(db.delete_epic(first, second_epic).is_err());
+    }
+
+    #[test]
+    fn resolve_issue_key_should_find_items_in_their_project() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        db.create_project(Project::new("".to_string(), "OPS".to_string(), "".to_string()))
+            .unwrap();
+
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+
+        assert_eq!(db.resolve_issue_key(&format!("TEST-{epic_id}")).unwrap(), epic_id);
+        assert_eq!(db.resolve_issue_key(&format!(" test-{story_id} ")).unwrap(), story_id);
+        assert_eq!(db.resolve_issue_key(&story_id.to_string()).unwrap(), story_id);
+
+        assert!(db.resolve_issue_key(&format!("OPS-{epic_id}")).is_err());
+        assert!(db.resolve_issue_key(&format!("NOPE-{epic_id}")).is_err());
+        assert!(db.resolve_issue_key(&format!("TEST-{project_id}")).is_err());
+        assert!(db.resolve_issue_key("999").is_err());
+        assert!(db.resolve_issue_key("TEST").is_err());
+        assert!(db.resolve_issue_key("TEST-x").is_err());
+    }
+
+    #[test]
+    fn issue_keys_should_cover_trashed_items() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story = Story::new("".to_string(), "".to_string());
+        let story_id = db.create_story(story.clone(), epic_id).unwrap();
+        let trashed_story_id = db.create_story(story, epic_id).unwrap();
+        let trashed_epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        db.delete_story(epic_id, trashed_story_id).unwrap();
+        db.delete_epic(project_id, trashed_epic_id).unwrap();
+
+        let keys = db.issue_keys().unwrap();
+        assert_eq!(keys.len(), 4);
+        assert_eq!(keys[&epic_id], format!("TEST-{epic_id}"));
+        assert_eq!(keys[&story_id], format!("TEST-{story_id}"));
+        assert_eq!(keys[&trashed_story_id], format!("TEST-{trashed_story_id}"));
+        assert_eq!(keys[&trashed_epic_id], format!("TEST-{trashed_epic_id}"));
+        assert!(!keys.contains_key(&project_id));
+    }
+
+    #[test]
+    fn issue_keys_should_survive_import_into_another_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = JiraDatabase::open(Backend::Json, dir.path().join("db.json")).unwrap();
+        let project_id = test_project(&json);
+
+        let epic_id =
+            json.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        json.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let keys = json.issue_keys().unwrap();
+
+        let sqlite = JiraDatabase::open(Backend::Sqlite, dir.path().join("db.sqlite")).unwrap();
+        assert_eq!(sqlite.issue_keys().unwrap(), keys);
+
+        let events =
+            JiraDatabase::open(Backend::EventLog, dir.path().join("db.jsonl")).unwrap();
+        assert_eq!(events.issue_keys().unwrap(), keys);
+    }
+
     #[test]
     fn create_story_should_fail_if_invalid_epic_id() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
         let story = Story::new("".to_string(), "".to_string());
         let non_existent_epic_id = 999;
 
@@ -175,11 +1322,12 @@ mod tests {
 
     #[test]
     fn create_story_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -188,7 +1336,7 @@ mod tests {
 
         let id = result.unwrap();
         let db_state = db.read().unwrap();
-        let expected_id = 2;
+        let expected_id = 3;
 
         assert_eq!(id, expected_id);
         assert_eq!(db_state.last_item_id, expected_id);
@@ -198,20 +1346,22 @@ mod tests {
 
     #[test]
     fn delete_epic_should_fail_if_invalid_epic_id() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let non_existent_epic_id = 999;
 
-        let result = db.delete_epic(non_existent_epic_id);
+        let result = db.delete_epic(project_id, non_existent_epic_id);
         assert!(result.is_err());
     }
 
     #[test]
     fn delete_epic_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -219,11 +1369,11 @@ mod tests {
         assert!(result.is_ok());
 
         let story_id = result.unwrap();
-        let result = db.delete_epic(epic_id);
+        let result = db.delete_epic(project_id, epic_id);
         assert!(result.is_ok());
 
         let db_state = db.read().unwrap();
-        let expected_last_id = 2;
+        let expected_last_id = 3;
         assert_eq!(db_state.last_item_id, expected_last_id);
         assert_eq!(db_state.epics.get(&epic_id), None);
         assert_eq!(db_state.stories.get(&story_id), None);
@@ -231,11 +1381,12 @@ mod tests {
 
     #[test]
     fn delete_story_should_fail_if_invalid_epic_id() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -250,11 +1401,12 @@ mod tests {
 
     #[test]
     fn delete_story_should_fail_if_story_not_found_in_epic() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -266,13 +1418,30 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn delete_story_should_fail_without_changes_for_another_epics_story() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let other_epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let before = db.read().unwrap();
+
+        assert!(db.delete_story(other_epic_id, story_id).is_err());
+        assert_eq!(db.read().unwrap(), before);
+    }
+
     #[test]
     fn delete_story_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -284,7 +1453,7 @@ mod tests {
         assert!(result.is_ok());
 
         let db_state = db.read().unwrap();
-        let expected_last_id = 2;
+        let expected_last_id = 3;
         assert_eq!(db_state.last_item_id, expected_last_id);
         assert!(!db_state.epics.get(&epic_id).unwrap().stories.contains(&story_id));
         assert_eq!(db_state.stories.get(&story_id), None);
@@ -292,7 +1461,7 @@ mod tests {
 
     #[test]
     fn update_epic_status_should_fail_if_invalid_epic_id() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
         let non_existent_epic_id = 999;
 
         let result = db.update_epic_status(non_existent_epic_id, Status::Closed);
@@ -301,10 +1470,11 @@ mod tests {
 
     #[test]
     fn update_epic_status_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         assert!(result.is_ok());
 
         let epic_id = result.unwrap();
@@ -320,7 +1490,7 @@ mod tests {
 
     #[test]
     fn update_story_status_should_fail_if_invalid_story_id() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
 
         let non_existent_story_id = 999;
 
@@ -330,11 +1500,12 @@ mod tests {
 
     #[test]
     fn update_story_status_should_pass() {
-        let db = JiraDatabase { database: Box::new(MockDB::new()) };
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
         let epic = Epic::new("".to_string(), "".to_string());
         let story = Story::new("".to_string(), "".to_string());
 
-        let result = db.create_epic(epic);
+        let result = db.create_epic(epic, project_id);
         let epic_id = result.unwrap();
 
         let result = db.create_story(story, epic_id);
@@ -347,110 +1518,423 @@ mod tests {
         assert_eq!(db_state.stories.get(&story_id).unwrap().status, Status::Closed);
     }
 
-    mod database {
-        use std::collections::HashMap;
-        use std::io::Write;
+    #[test]
+    fn update_details_should_return_previous_details() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id = db
+            .create_epic(Epic::new("Chekout".to_string(), "".to_string()), project_id)
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("Cart".to_string(), "old".to_string()), epic_id)
+            .unwrap();
+
+        let details = |name: &str, description: &str| Details {
+            name:        name.to_string(),
+            description: description.to_string(),
+            priority:    Priority::Medium,
+        };
+
+        let previous =
+            db.update_epic_details(epic_id, details("Checkout", "One page")).unwrap();
+        assert_eq!(previous, details("Chekout", ""));
+        let previous = db.update_story_details(story_id, details("Cart", "new")).unwrap();
+        assert_eq!(previous, details("Cart", "old"));
 
-        use super::*;
+        let db_state = db.read().unwrap();
+        assert_eq!(Details::from(&db_state.epics[&epic_id]), details("Checkout", "One page"));
+        assert_eq!(db_state.epics[&epic_id].stories, vec![story_id]);
+        assert_eq!(db_state.stories[&story_id].description, "new");
 
-        #[test]
-        fn read_from_db_should_fail_with_invalid_path() {
-            let db = JSONFileDatabase { file_path: "INVALID_PATH".into() };
-            assert!(db.read().is_err());
-        }
+        assert!(db.update_epic_details(story_id, details("", "")).is_err());
+        assert!(db.update_story_details(999, details("", "")).is_err());
+    }
 
-        #[test]
-        fn read_from_db_should_fail_with_invalid_json() {
-            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+    #[test]
+    fn assign_should_require_known_users_and_return_previous_assignee() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+
+        db.create_user("ana", User { name: "Ana Lima".to_string() }).unwrap();
+        assert!(db.create_user("ana", User { name: "".to_string() }).is_err());
+        assert!(db.create_user("not a name", User { name: "".to_string() }).is_err());
+
+        assert_eq!(db.assign_story(story_id, Some("ana".to_string())).unwrap(), None);
+        assert!(db.assign_story(story_id, Some("bob".to_string())).is_err());
+        assert_eq!(db.assign_story(story_id, None).unwrap(), Some("ana".to_string()));
+        assert_eq!(db.assign_epic(epic_id, Some("ana".to_string())).unwrap(), None);
+
+        let filter = EpicFilter { owner: Some("ana".to_string()), ..EpicFilter::default() };
+        assert_eq!(db.list_epics(&filter).unwrap().len(), 1);
+        let filter =
+            StoryFilter { assignee: Some("ana".to_string()), ..StoryFilter::default() };
+        assert!(db.list_stories(&filter).unwrap().is_empty());
+        assert_eq!(db.list_users().unwrap(), vec![("ana".to_string(), User {
+            name: "Ana Lima".to_string(),
+        })]);
+    }
 
-            let file_contents = r#"{ "last_item_id": 0 epics: {} stories {} }"#;
-            write!(tmpfile, "{file_contents}").unwrap();
+    #[test]
+    fn labels_should_be_normalised_and_renamed_everywhere() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story = || Story::new("".to_string(), "".to_string());
+        let story_id = db.create_story(story(), epic_id).unwrap();
+        let trashed_id = db.create_story(story(), epic_id).unwrap();
+
+        assert_eq!(
+            db.add_label(story_id, " Tech  Debt ").unwrap(),
+            Some("tech-debt".to_string())
+        );
+        assert_eq!(db.add_label(story_id, "tech_debt").unwrap(), None);
+        assert_eq!(db.add_label(story_id, "UI").unwrap(), Some("ui".to_string()));
+        assert!(db.add_label(story_id, "a/b").is_err());
+        assert!(db.add_label(999, "ui").is_err());
+        db.add_label(epic_id, "ui").unwrap();
+        db.add_label(trashed_id, "tech-debt").unwrap();
+        db.delete_story(epic_id, trashed_id).unwrap();
+
+        assert_eq!(db.get_story(story_id).unwrap().unwrap().labels, vec!["tech-debt", "ui"]);
+        assert_eq!(db.list_labels().unwrap(), vec!["tech-debt", "ui"]);
+
+        assert_eq!(db.rename_label("tech-debt", "UI").unwrap(), 2);
+        assert_eq!(db.get_story(story_id).unwrap().unwrap().labels, vec!["ui"]);
+        let Trashed::Story { story, .. } = db.list_trash().unwrap().remove(0).1.item else {
+            panic!("expected a trashed story");
+        };
+        assert_eq!(story.labels, vec!["ui"]);
+        assert!(db.rename_label("ui", "Ui").is_err());
+
+        let filter = StoryFilter { label: Some("ui".to_string()), ..StoryFilter::default() };
+        assert_eq!(db.list_stories(&filter).unwrap().len(), 1);
+        assert!(db.remove_label(story_id, "UI").unwrap());
+        assert!(!db.remove_label(story_id, "ui").unwrap());
+        assert!(db.list_stories(&filter).unwrap().is_empty());
+    }
 
-            let db = JSONFileDatabase { file_path: tmpfile.path().to_path_buf() };
+    #[test]
+    fn schedule_should_set_dates_and_find_overdue_items() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let date = |s: &str| s.parse::<Date>().ok();
+        let schedule = Schedule { start: date("2026-10-01"), due: date("2026-10-15") };
+
+        assert_eq!(db.schedule(story_id, schedule).unwrap(), Schedule::default());
+        let epic_schedule = Schedule { due: date("2026-10-20"), ..schedule };
+        assert_eq!(db.schedule(epic_id, epic_schedule).unwrap(), Schedule::default());
+        assert!(
+            db.schedule(story_id, Schedule { start: date("2026-10-16"), ..schedule }).is_err()
+        );
+        assert!(db.schedule(999, schedule).is_err());
+        assert_eq!(Schedule::from(&db.get_story(story_id).unwrap().unwrap()), schedule);
+
+        let overdue_on =
+            |today: &str| StoryFilter { overdue_on: date(today), ..StoryFilter::default() };
+        assert!(db.list_stories(&overdue_on("2026-10-15")).unwrap().is_empty());
+        assert_eq!(db.list_stories(&overdue_on("2026-10-16")).unwrap().len(), 1);
+        let filter = EpicFilter { overdue_on: date("2026-10-16"), ..EpicFilter::default() };
+        assert!(db.list_epics(&filter).unwrap().is_empty());
+
+        db.update_story_status(story_id, Status::Resolved).unwrap();
+        assert!(db.list_stories(&overdue_on("2026-10-16")).unwrap().is_empty());
+    }
 
-            let result = db.read();
+    #[test]
+    fn move_story_should_keep_order_in_both_epics() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let from =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let to = db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story = || Story::new("".to_string(), "".to_string());
+        let a = db.create_story(story(), from).unwrap();
+        let b = db.create_story(story(), from).unwrap();
+        let c = db.create_story(story(), from).unwrap();
+        let d = db.create_story(story(), to).unwrap();
+
+        assert_eq!(db.move_story(b, from, to).unwrap(), 1);
+        assert_eq!(db.get_epic(from).unwrap().unwrap().stories, vec![a, c]);
+        assert_eq!(db.get_epic(to).unwrap().unwrap().stories, vec![d, b]);
+        assert_eq!(db.find_story_epic(b).unwrap(), Some(to));
+
+        assert_eq!(db.move_story_to(b, to, from, 1).unwrap(), 1);
+        assert_eq!(db.get_epic(from).unwrap().unwrap().stories, vec![a, b, c]);
+        assert_eq!(db.get_epic(to).unwrap().unwrap().stories, vec![d]);
+    }
 
-            assert!(result.is_err());
-        }
+    #[test]
+    fn rank_story_should_reorder_within_epic() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story = || Story::new("".to_string(), "".to_string());
+        let a = db.create_story(story(), epic_id).unwrap();
+        let b = db.create_story(story(), epic_id).unwrap();
+        let c = db.create_story(story(), epic_id).unwrap();
+
+        assert_eq!(db.rank_story(epic_id, c, 0).unwrap(), 2);
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().stories, vec![c, a, b]);
+        assert_eq!(db.rank_story(epic_id, c, 99).unwrap(), 0);
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().stories, vec![a, b, c]);
+
+        let filter = StoryFilter { epic_id: Some(epic_id), ..StoryFilter::default() };
+        db.rank_story(epic_id, b, 0).unwrap();
+        let ids =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![b, a, c]);
+
+        assert!(db.rank_story(epic_id, 999, 0).is_err());
+    }
 
-        #[test]
-        fn read_from_db_should_parse_json_file() {
-            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+    #[test]
+    fn move_story_should_fail_without_changes_for_bad_ids() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let from =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let to = db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), from).unwrap();
+        let before = db.read().unwrap();
+
+        assert!(db.move_story(story_id, from, 999).is_err());
+        assert!(db.move_story(story_id, to, from).is_err());
+        assert!(db.move_story(999, from, to).is_err());
+        assert_eq!(db.read().unwrap(), before);
+    }
 
-            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
-            write!(tmpfile, "{file_contents}").unwrap();
+    #[test]
+    fn mutations_should_bump_revision() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
 
-            let db = JSONFileDatabase { file_path: tmpfile.path().to_path_buf() };
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.update_epic_status(epic_id, Status::Closed).unwrap();
 
-            let result = db.read();
-            assert!(result.is_ok());
-        }
+        assert_eq!(db.read().unwrap().revision, 3);
+    }
 
-        #[test]
-        fn write_to_db_should_pass() {
-            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+    #[test]
+    fn mutation_should_fail_if_state_changed_since_last_read() {
+        let store = MockDB::new();
+        let db = JiraDatabase::from_store(store.clone());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
-            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
-            write!(tmpfile, "{file_contents}").unwrap();
+        let mut db_state = db.read().unwrap();
+        db_state.revision += 1;
+        store.write(&db_state).unwrap();
 
-            let db = JSONFileDatabase { file_path: tmpfile.path().to_path_buf() };
-            let story = Story {
-                name:        "epic 1".to_string(),
-                description: "epic 1".to_string(),
-                status:      Status::Open,
-            };
-            let epic = Epic {
-                name:        "epic 1".to_string(),
-                description: "epic 1".to_string(),
-                status:      Status::Open,
-                stories:     vec![2],
-            };
+        let result = db.update_epic_status(epic_id, Status::Closed);
+        let conflict = result.unwrap_err().downcast::<RevisionConflict>().unwrap();
+        assert_eq!(conflict, RevisionConflict { expected: 2, actual: 3 });
 
-            let mut stories = HashMap::with_capacity(1);
-            stories.insert(2, story);
+        db.read().unwrap();
+        assert!(db.update_epic_status(epic_id, Status::Closed).is_ok());
+    }
 
-            let mut epics = HashMap::with_capacity(1);
-            epics.insert(1, epic);
+    #[test]
+    fn open_sqlite_should_import_neighbouring_json_once() {
+        let dir = tempfile::tempdir().unwrap();
+        JSONFileDatabase::new(dir.path().join("db.json")).write(&DBState::new()).unwrap();
+        let json_db = JiraDatabase::open(Backend::Json, dir.path().join("db.json")).unwrap();
+        let project_id = test_project(&json_db);
+        let epic_id =
+            json_db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        let sqlite_path = dir.path().join("db.sqlite");
+        let db = JiraDatabase::open(Backend::Sqlite, &sqlite_path).unwrap();
+        assert!(db.read().unwrap().epics.contains_key(&epic_id));
+
+        db.delete_epic(project_id, epic_id).unwrap();
+        json_db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        let db = JiraDatabase::open(Backend::Sqlite, &sqlite_path).unwrap();
+        assert!(db.read().unwrap().epics.is_empty());
+    }
 
-            let state = DBState { last_item_id: 2, epics, stories };
+    #[test]
+    fn failed_mutation_should_leave_state_untouched() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let before = db.read().unwrap();
+
+        assert!(db.delete_story(epic_id, 999).is_err());
+        assert_eq!(db.read().unwrap(), before);
+    }
 
-            let write_result = db.write(&state);
-            let read_result = db.read().unwrap();
+    #[test]
+    fn delete_should_move_items_to_trash() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let first =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let second =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+
+        db.delete_story(epic_id, first).unwrap();
+        db.delete_epic(project_id, epic_id).unwrap();
+
+        let trash = db.read().unwrap().trash;
+        assert_eq!(trash.len(), 2);
+        assert!(matches!(
+            &trash[&first].item,
+            Trashed::Story { epic_id: id, position: 0, .. } if *id == epic_id
+        ));
+        assert!(matches!(
+            &trash[&epic_id].item,
+            Trashed::Epic { stories, .. } if stories.keys().eq([&second])
+        ));
+    }
 
-            assert!(write_result.is_ok());
-            assert_eq!(read_result, state);
-        }
+    #[test]
+    fn restore_should_bring_back_original_ids_and_membership() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let first =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let second =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let before = db.read().unwrap();
+
+        db.delete_story(epic_id, first).unwrap();
+        db.delete_epic(project_id, epic_id).unwrap();
+
+        assert!(db.restore(first).is_err());
+        db.restore(epic_id).unwrap();
+        db.restore(first).unwrap();
+
+        let after = db.read().unwrap();
+        assert_eq!(after.epics, before.epics);
+        assert_eq!(after.stories, before.stories);
+        assert_eq!(after.epics[&epic_id].stories, vec![first, second]);
+        assert_eq!(after.projects, before.projects);
+        assert!(after.trash.is_empty());
+        assert!(db.restore(epic_id).is_err());
+    }
+
+    #[test]
+    fn purge_should_only_remove_trashed_items() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let first =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let second =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        assert!(db.purge(first).is_err());
+
+        db.delete_epic(project_id, first).unwrap();
+        db.delete_epic(project_id, second).unwrap();
+        db.purge(first).unwrap();
+        assert!(db.restore(first).is_err());
+
+        assert_eq!(db.empty_trash().unwrap(), 1);
+        assert!(db.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn purge_expired_should_keep_recent_items() {
+        let store = MockDB::new();
+        let db = JiraDatabase::from_store(store.clone());
+        let project_id = test_project(&db);
+        let old =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let recent =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.delete_epic(project_id, old).unwrap();
+        db.delete_epic(project_id, recent).unwrap();
+
+        let mut db_state = db.read().unwrap();
+        db_state.trash.get_mut(&old).unwrap().deleted_at = 0;
+        store.write(&db_state).unwrap();
+        let revision = db.read().unwrap().revision;
+
+        assert_eq!(db.purge_expired(DEFAULT_TRASH_RETENTION).unwrap(), 1);
+        assert_eq!(db.purge_expired(DEFAULT_TRASH_RETENTION).unwrap(), 0);
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.trash.keys().collect::<Vec<_>>(), vec![&recent]);
+        assert_eq!(db_state.revision, revision + 1);
+    }
+
+    #[test]
+    fn list_stories_should_filter_by_epic_and_status() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let first =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let second =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        let open = db.create_story(Story::new("".to_string(), "".to_string()), first).unwrap();
+        let closed =
+            db.create_story(Story::new("".to_string(), "".to_string()), first).unwrap();
+        db.create_story(Story::new("".to_string(), "".to_string()), second).unwrap();
+        db.update_story_status(closed, Status::Closed).unwrap();
+
+        let filter = StoryFilter { epic_id: Some(first), ..StoryFilter::default() };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![open, closed]);
+
+        let filter = StoryFilter {
+            epic_id: Some(first),
+            status: Some(Status::Open),
+            ..StoryFilter::default()
+        };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![open]);
+
+        assert_eq!(db.list_stories(&StoryFilter::default()).unwrap().len(), 3);
     }
 }
 
 #[cfg(test)]
 pub mod test_utils {
     use std::cell::RefCell;
-    use std::collections::HashMap;
+    use std::rc::Rc;
 
     use super::*;
 
+    /// An in-memory document store. Clones share the same state, so tests can
+    /// keep a handle to simulate writes from another session.
+    #[derive(Clone, Default)]
     pub struct MockDB {
-        last_written_state: RefCell<DBState>,
+        last_written_state: Rc<RefCell<DBState>>,
     }
 
     impl MockDB {
-        pub fn new() -> Self {
-            Self {
-                last_written_state: RefCell::new(DBState {
-                    last_item_id: 0,
-                    epics:        HashMap::with_capacity(2),
-                    stories:      HashMap::with_capacity(2),
-                }),
-            }
-        }
+        pub fn new() -> Self { Self::default() }
     }
 
-    impl Default for MockDB {
-        fn default() -> Self { Self::new() }
+    /// Creates the project that a test's epics go into.
+    pub fn test_project(db: &JiraDatabase) -> u32 {
+        let project = Project::new("test".to_string(), "TEST".to_string(), "".to_string());
+        db.create_project(project).unwrap()
     }
 
-    impl Database for MockDB {
+    impl DocumentStore for MockDB {
         fn read(&self) -> Result<DBState> {
             let state = self.last_written_state.borrow().clone();
             Ok(state)
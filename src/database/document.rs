@@ -0,0 +1,261 @@
+use std::cell::RefCell;
+
+use anyhow::{Result, anyhow, bail};
+
+use super::state::{self, Change};
+use super::{Database, DatabaseLock, EpicFilter, LockMode, RevisionConflict, StoryFilter};
+use crate::models::{DBState, Epic, Project, Story, TrashItem, User};
+
+/// Storage that can only load and save the whole board as one document.
+pub trait DocumentStore {
+    fn read(&self) -> Result<DBState>;
+    fn write(&self, db_state: &DBState) -> Result<()>;
+
+    fn lock(&self, _mode: LockMode) -> Result<DatabaseLock> { Ok(DatabaseLock::default()) }
+
+    /// Writes `db_state` only if the stored revision still equals `expected`.
+    /// The default relies on the caller holding an exclusive lock.
+    fn write_if_revision(&self, expected: u64, db_state: &DBState) -> Result<()> {
+        let actual = self.read()?.revision;
+        if actual != expected {
+            return Err(RevisionConflict { expected, actual }.into());
+        }
+
+        self.write(db_state)
+    }
+}
+
+/// Adapts a `DocumentStore` to the entity-level `Database` trait. A
+/// transaction loads the document once, edits it in memory and writes it back
+/// on commit.
+pub struct DocumentDatabase<S> {
+    store:  S,
+    staged: RefCell<Option<DBState>>,
+}
+
+impl<S: DocumentStore> DocumentDatabase<S> {
+    pub fn new(store: S) -> Self { Self { store, staged: RefCell::new(None) } }
+
+    fn with_state<T>(&self, read: impl FnOnce(&DBState) -> T) -> Result<T> {
+        match self.staged.borrow().as_ref() {
+            Some(db_state) => Ok(read(db_state)),
+            None => Ok(read(&self.store.read()?)),
+        }
+    }
+
+    fn with_staged<T>(&self, change: impl FnOnce(&mut DBState) -> Result<T>) -> Result<T> {
+        let mut staged = self.staged.borrow_mut();
+        let db_state = staged.as_mut().ok_or_else(|| anyhow!("No transaction in progress"))?;
+        change(db_state)
+    }
+
+    fn stage(&self, change: Change) -> Result<()> {
+        self.with_staged(|db_state| change.apply(db_state))
+    }
+}
+
+impl<S: DocumentStore> Database for DocumentDatabase<S> {
+    fn lock(&self, mode: LockMode) -> Result<DatabaseLock> { self.store.lock(mode) }
+
+    fn begin(&self, _operation: &str) -> Result<()> {
+        if self.staged.borrow().is_some() {
+            bail!("A transaction is already in progress");
+        }
+
+        let db_state = self.store.read()?;
+        *self.staged.borrow_mut() = Some(db_state);
+        Ok(())
+    }
+
+    fn commit(&self) -> Result<u64> {
+        let mut db_state =
+            self.staged.take().ok_or_else(|| anyhow!("No transaction in progress"))?;
+
+        let revision = db_state.revision;
+        db_state.revision += 1;
+        self.store.write_if_revision(revision, &db_state)?;
+        Ok(db_state.revision)
+    }
+
+    fn rollback(&self) -> Result<()> {
+        self.staged.take();
+        Ok(())
+    }
+
+    fn revision(&self) -> Result<u64> { self.with_state(|db_state| db_state.revision) }
+
+    fn next_id(&self) -> Result<u32> {
+        self.with_staged(|db_state| {
+            let id = db_state.last_item_id + 1;
+            Change::AllocateId { id }.apply(db_state)?;
+            Ok(id)
+        })
+    }
+
+    fn reserve_ids(&self, last_item_id: u32) -> Result<()> {
+        self.stage(Change::AllocateId { id: last_item_id })
+    }
+
+    fn snapshot(&self) -> Result<DBState> { self.with_state(DBState::clone) }
+
+    fn get_project(&self, project_id: u32) -> Result<Option<Project>> {
+        self.with_state(|db_state| db_state.projects.get(&project_id).cloned())
+    }
+
+    fn list_projects(&self) -> Result<Vec<(u32, Project)>> {
+        self.with_state(state::list_projects)
+    }
+
+    fn insert_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        self.stage(Change::InsertProject { id: project_id, project: project.clone() })
+    }
+
+    fn update_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        self.stage(Change::UpdateProject { id: project_id, project: project.clone() })
+    }
+
+    fn delete_project(&self, project_id: u32) -> Result<()> {
+        self.stage(Change::DeleteProject { id: project_id })
+    }
+
+    fn get_epic(&self, epic_id: u32) -> Result<Option<Epic>> {
+        self.with_state(|db_state| db_state.epics.get(&epic_id).cloned())
+    }
+
+    fn list_epics(&self, filter: &EpicFilter) -> Result<Vec<(u32, Epic)>> {
+        self.with_state(|db_state| state::list_epics(db_state, filter))
+    }
+
+    fn insert_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        self.stage(Change::InsertEpic { id: epic_id, epic: epic.clone() })
+    }
+
+    fn update_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        self.stage(Change::UpdateEpic { id: epic_id, epic: epic.clone() })
+    }
+
+    fn delete_epic(&self, epic_id: u32) -> Result<()> {
+        self.stage(Change::DeleteEpic { id: epic_id })
+    }
+
+    fn get_story(&self, story_id: u32) -> Result<Option<Story>> {
+        self.with_state(|db_state| db_state.stories.get(&story_id).cloned())
+    }
+
+    fn list_stories(&self, filter: &StoryFilter) -> Result<Vec<(u32, Story)>> {
+        self.with_state(|db_state| state::list_stories(db_state, filter))
+    }
+
+    fn insert_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        self.stage(Change::InsertStory { id: story_id, story: story.clone() })
+    }
+
+    fn update_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        self.stage(Change::UpdateStory { id: story_id, story: story.clone() })
+    }
+
+    fn delete_story(&self, story_id: u32) -> Result<()> {
+        self.stage(Change::DeleteStory { id: story_id })
+    }
+
+    fn get_trash_item(&self, item_id: u32) -> Result<Option<TrashItem>> {
+        self.with_state(|db_state| db_state.trash.get(&item_id).cloned())
+    }
+
+    fn list_trash(&self) -> Result<Vec<(u32, TrashItem)>> { self.with_state(state::list_trash) }
+
+    fn insert_trash_item(&self, item_id: u32, item: &TrashItem) -> Result<()> {
+        self.stage(Change::InsertTrashItem { id: item_id, item: item.clone() })
+    }
+
+    fn delete_trash_item(&self, item_id: u32) -> Result<()> {
+        self.stage(Change::DeleteTrashItem { id: item_id })
+    }
+
+    fn get_user(&self, username: &str) -> Result<Option<User>> {
+        self.with_state(|db_state| db_state.users.get(username).cloned())
+    }
+
+    fn list_users(&self) -> Result<Vec<(String, User)>> { self.with_state(state::list_users) }
+
+    fn insert_user(&self, username: &str, user: &User) -> Result<()> {
+        self.stage(Change::InsertUser {
+            username: username.to_string(),
+            user:     user.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::MockDB;
+
+    #[test]
+    fn write_if_revision_should_fail_on_stale_revision() {
+        let db = MockDB::new();
+        let db_state = DBState { revision: 1, ..DBState::new() };
+
+        assert!(db.write_if_revision(1, &db_state).is_err());
+        assert!(db.write_if_revision(0, &db_state).is_ok());
+        assert_eq!(db.read().unwrap().revision, 1);
+    }
+
+    #[test]
+    fn mutations_should_fail_outside_transaction() {
+        let db = DocumentDatabase::new(MockDB::new());
+
+        assert!(db.next_id().is_err());
+        assert!(db.insert_epic(1, &Epic::new("".to_string(), "".to_string())).is_err());
+    }
+
+    #[test]
+    fn commit_should_write_staged_changes_and_bump_revision() {
+        let store = MockDB::new();
+        let db = DocumentDatabase::new(store.clone());
+        let epic = Epic::new("".to_string(), "".to_string());
+
+        db.begin("test").unwrap();
+        let epic_id = db.next_id().unwrap();
+        db.insert_epic(epic_id, &epic).unwrap();
+
+        assert!(store.read().unwrap().epics.is_empty());
+        assert_eq!(db.get_epic(epic_id).unwrap(), Some(epic.clone()));
+
+        assert_eq!(db.commit().unwrap(), 1);
+        assert_eq!(store.read().unwrap().epics.get(&epic_id), Some(&epic));
+    }
+
+    #[test]
+    fn rollback_should_discard_staged_changes() {
+        let store = MockDB::new();
+        let db = DocumentDatabase::new(store.clone());
+
+        db.begin("test").unwrap();
+        let epic_id = db.next_id().unwrap();
+        db.insert_epic(epic_id, &Epic::new("".to_string(), "".to_string())).unwrap();
+        db.rollback().unwrap();
+
+        assert_eq!(db.get_epic(epic_id).unwrap(), None);
+        assert_eq!(store.read().unwrap(), DBState::new());
+    }
+
+    #[test]
+    fn insert_should_fail_for_existing_id() {
+        let db = DocumentDatabase::new(MockDB::new());
+        let story = Story::new("".to_string(), "".to_string());
+
+        db.begin("test").unwrap();
+        db.insert_story(1, &story).unwrap();
+        assert!(db.insert_story(1, &story).is_err());
+    }
+
+    #[test]
+    fn update_should_fail_for_missing_id() {
+        let db = DocumentDatabase::new(MockDB::new());
+
+        db.begin("test").unwrap();
+        assert!(db.update_epic(1, &Epic::new("".to_string(), "".to_string())).is_err());
+        assert!(db.update_story(1, &Story::new("".to_string(), "".to_string())).is_err());
+    }
+}
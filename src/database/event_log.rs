@@ -0,0 +1,584 @@
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use super::json::{JSONFileDatabase, sibling_path};
+use super::state::{self, Change};
+use super::{
+    Database,
+    DatabaseLock,
+    DocumentStore,
+    EpicFilter,
+    LOCK_TIMEOUT,
+    LockMode,
+    StoryFilter,
+    unix_timestamp,
+};
+use crate::models::{DBState, Epic, Project, Story, TrashItem, User};
+
+/// How many events pile up in the log before it is folded into a snapshot.
+const COMPACT_EVERY: u64 = 100;
+
+/// One committed `JiraDatabase` operation, stored as a line of the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub revision:  u64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub operation: String,
+    pub changes:   Vec<Change>,
+}
+
+/// Stores the board as an append-only JSONL log of events. The current state
+/// is rebuilt by replaying the log on top of the latest snapshot; compacted
+/// events are moved to an archive so the full history is kept.
+pub struct EventLogDatabase {
+    pub file_path:     PathBuf,
+    pub lock_timeout:  Duration,
+    pub compact_every: u64,
+    replayed:          RefCell<Replayed>,
+    staged:            RefCell<Option<Staged>>,
+}
+
+#[derive(Default)]
+struct Replayed {
+    db_state: DBState,
+    /// Byte offset just past the last complete event read from the log.
+    offset:   u64,
+    /// Number of events in the log since the snapshot was taken.
+    events:   u64,
+}
+
+struct Staged {
+    operation: String,
+    db_state:  DBState,
+    changes:   Vec<Change>,
+}
+
+impl EventLogDatabase {
+    pub fn open(file_path: PathBuf) -> Result<Self> {
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let database = Self {
+            file_path,
+            lock_timeout: LOCK_TIMEOUT,
+            compact_every: COMPACT_EVERY,
+            replayed: RefCell::new(Replayed::default()),
+            staged: RefCell::new(None),
+        };
+
+        let _lock = database.lock(LockMode::Shared)?;
+        database.reload()?;
+        Ok(database)
+    }
+
+    /// A log that has never been written to has no snapshot and no events.
+    pub fn is_empty(&self) -> Result<bool> {
+        self.refresh()?;
+        let replayed = self.replayed.borrow();
+        Ok(replayed.offset == 0 && !self.snapshot_path().exists())
+    }
+
+    /// Seeds the log with the whole state of a `db.json` file as its snapshot.
+    pub fn import_json(&self, json_path: &Path) -> Result<()> {
+        let db_state = JSONFileDatabase::new(json_path.to_path_buf())
+            .read()
+            .with_context(|| format!("Failed to import: {}", json_path.display()))?;
+
+        let _lock = self.lock(LockMode::Exclusive)?;
+        self.snapshot_store().write(&db_state)?;
+        self.reload()
+    }
+
+    /// Every event ever committed, oldest first, including compacted ones.
+    pub fn history(&self) -> Result<Vec<Event>> {
+        let _lock = self.lock(LockMode::Shared)?;
+
+        let mut events = Vec::new();
+        for path in [self.archive_path(), self.file_path.clone()] {
+            match File::open(&path) {
+                Ok(file) => {
+                    events.extend(read_events(file)?.into_iter().map(|(event, _)| event))
+                },
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+                Err(error) => {
+                    return Err(error)
+                        .with_context(|| format!("Failed to read file: {}", path.display()));
+                },
+            }
+        }
+
+        // Events archived by an interrupted compaction may still be in the log,
+        // after the archive's copy of them.
+        let mut last_revision = 0;
+        events.retain(|event| {
+            let unseen = event.revision > last_revision;
+            last_revision = last_revision.max(event.revision);
+            unseen
+        });
+        Ok(events)
+    }
+
+    fn snapshot_path(&self) -> PathBuf { sibling_path(&self.file_path, ".snapshot") }
+
+    fn archive_path(&self) -> PathBuf { sibling_path(&self.file_path, ".archive") }
+
+    fn lock_path(&self) -> PathBuf { sibling_path(&self.file_path, ".lock") }
+
+    fn snapshot_store(&self) -> JSONFileDatabase { JSONFileDatabase::new(self.snapshot_path()) }
+
+    /// Rebuilds the state from scratch: the snapshot, then the whole log.
+    fn reload(&self) -> Result<()> {
+        let snapshot = self.snapshot_store();
+        let db_state =
+            if snapshot.file_path.exists() { snapshot.read()? } else { DBState::new() };
+
+        let mut replayed = Replayed { db_state, offset: 0, events: 0 };
+        self.replay(&mut replayed)?;
+
+        *self.replayed.borrow_mut() = replayed;
+        Ok(())
+    }
+
+    /// Catches up with events appended by other sessions, starting over if
+    /// the log was compacted in the meantime.
+    fn refresh(&self) -> Result<()> {
+        let caught_up = self.replay(&mut self.replayed.borrow_mut());
+        if caught_up.is_err() {
+            return self.reload();
+        }
+        Ok(())
+    }
+
+    fn replay(&self, replayed: &mut Replayed) -> Result<()> {
+        let mut file = match File::open(&self.file_path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound && replayed.offset == 0 => {
+                return Ok(());
+            },
+            Err(error) => return Err(error.into()),
+        };
+
+        if file.metadata()?.len() < replayed.offset {
+            bail!("Event log {} was truncated", self.file_path.display());
+        }
+        file.seek(SeekFrom::Start(replayed.offset))?;
+
+        for (event, length) in read_events(file)? {
+            let revision = replayed.db_state.revision;
+
+            // Events already folded into the snapshot are skipped.
+            if event.revision > revision {
+                if event.revision != revision + 1 {
+                    bail!(
+                        "Event log {} jumps from revision {revision} to {}",
+                        self.file_path.display(),
+                        event.revision
+                    );
+                }
+
+                for change in &event.changes {
+                    change.apply(&mut replayed.db_state)?;
+                }
+                replayed.db_state.revision = event.revision;
+            }
+
+            replayed.offset += length;
+            replayed.events += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Folds the log into a new snapshot and moves its events to the archive.
+    fn compact(&self) -> Result<()> {
+        let mut replayed = self.replayed.borrow_mut();
+        self.snapshot_store().write(&replayed.db_state)?;
+
+        let mut events = fs::read(&self.file_path)?;
+        events.truncate(replayed.offset.try_into()?);
+
+        let mut archive =
+            OpenOptions::new().create(true).append(true).open(self.archive_path())?;
+        archive.write_all(&events)?;
+        archive.sync_all()?;
+
+        let log = OpenOptions::new().write(true).open(&self.file_path)?;
+        log.set_len(0)?;
+        log.sync_all()?;
+
+        replayed.offset = 0;
+        replayed.events = 0;
+        Ok(())
+    }
+
+    fn with_state<T>(&self, read: impl FnOnce(&DBState) -> T) -> Result<T> {
+        if let Some(staged) = self.staged.borrow().as_ref() {
+            return Ok(read(&staged.db_state));
+        }
+
+        self.refresh()?;
+        Ok(read(&self.replayed.borrow().db_state))
+    }
+
+    fn stage(&self, change: Change) -> Result<()> {
+        let mut staged = self.staged.borrow_mut();
+        let staged = staged.as_mut().ok_or_else(|| anyhow!("No transaction in progress"))?;
+
+        change.apply(&mut staged.db_state)?;
+        staged.changes.push(change);
+        Ok(())
+    }
+}
+
+/// Reads complete events and their length in bytes. A final line without a
+/// newline is an append cut short by a crash, so it is left out.
+fn read_events(file: File) -> Result<Vec<(Event, u64)>> {
+    let mut reader = BufReader::new(file);
+    let mut events = Vec::new();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let length = reader.read_line(&mut line)?;
+        if length == 0 || !line.ends_with('\n') {
+            break;
+        }
+
+        let event = serde_json::from_str(&line).context("Failed to parse event")?;
+        events.push((event, length.try_into()?));
+    }
+
+    Ok(events)
+}
+
+impl Database for EventLogDatabase {
+    fn lock(&self, mode: LockMode) -> Result<DatabaseLock> {
+        DatabaseLock::acquire(&self.lock_path(), mode, self.lock_timeout)
+    }
+
+    fn begin(&self, operation: &str) -> Result<()> {
+        if self.staged.borrow().is_some() {
+            bail!("A transaction is already in progress");
+        }
+
+        self.refresh()?;
+        let db_state = self.replayed.borrow().db_state.clone();
+        *self.staged.borrow_mut() =
+            Some(Staged { operation: operation.to_string(), db_state, changes: Vec::new() });
+        Ok(())
+    }
+
+    fn commit(&self) -> Result<u64> {
+        let staged = self.staged.take().ok_or_else(|| anyhow!("No transaction in progress"))?;
+
+        let event = Event {
+            revision:  staged.db_state.revision + 1,
+            timestamp: unix_timestamp(),
+            operation: staged.operation,
+            changes:   staged.changes,
+        };
+        let mut line = serde_json::to_string(&event).context("Failed to serialize event")?;
+        line.push('\n');
+
+        {
+            let mut replayed = self.replayed.borrow_mut();
+
+            // Anything past the last complete event is a torn append; drop it.
+            let mut log = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&self.file_path)?;
+            log.set_len(replayed.offset)?;
+            log.seek(SeekFrom::Start(replayed.offset))?;
+            log.write_all(line.as_bytes())?;
+            log.sync_all().with_context(|| {
+                format!("Failed to write to file: {}", self.file_path.display())
+            })?;
+
+            replayed.db_state = staged.db_state;
+            replayed.db_state.revision = event.revision;
+            replayed.offset += u64::try_from(line.len())?;
+            replayed.events += 1;
+        }
+
+        // The event is durable at this point, so a failed compaction only
+        // means a longer replay next time.
+        if self.replayed.borrow().events >= self.compact_every
+            && let Err(error) = self.compact()
+        {
+            eprintln!("Warning: failed to compact {}: {error:#}", self.file_path.display());
+        }
+
+        Ok(event.revision)
+    }
+
+    fn rollback(&self) -> Result<()> {
+        self.staged.take();
+        Ok(())
+    }
+
+    fn revision(&self) -> Result<u64> { self.with_state(|db_state| db_state.revision) }
+
+    fn next_id(&self) -> Result<u32> {
+        let id = self.with_state(|db_state| db_state.last_item_id + 1)?;
+        self.stage(Change::AllocateId { id })?;
+        Ok(id)
+    }
+
+    fn reserve_ids(&self, last_item_id: u32) -> Result<()> {
+        self.stage(Change::AllocateId { id: last_item_id })
+    }
+
+    fn snapshot(&self) -> Result<DBState> { self.with_state(DBState::clone) }
+
+    fn get_project(&self, project_id: u32) -> Result<Option<Project>> {
+        self.with_state(|db_state| db_state.projects.get(&project_id).cloned())
+    }
+
+    fn list_projects(&self) -> Result<Vec<(u32, Project)>> {
+        self.with_state(state::list_projects)
+    }
+
+    fn insert_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        self.stage(Change::InsertProject { id: project_id, project: project.clone() })
+    }
+
+    fn update_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        self.stage(Change::UpdateProject { id: project_id, project: project.clone() })
+    }
+
+    fn delete_project(&self, project_id: u32) -> Result<()> {
+        self.stage(Change::DeleteProject { id: project_id })
+    }
+
+    fn get_epic(&self, epic_id: u32) -> Result<Option<Epic>> {
+        self.with_state(|db_state| db_state.epics.get(&epic_id).cloned())
+    }
+
+    fn list_epics(&self, filter: &EpicFilter) -> Result<Vec<(u32, Epic)>> {
+        self.with_state(|db_state| state::list_epics(db_state, filter))
+    }
+
+    fn insert_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        self.stage(Change::InsertEpic { id: epic_id, epic: epic.clone() })
+    }
+
+    fn update_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        self.stage(Change::UpdateEpic { id: epic_id, epic: epic.clone() })
+    }
+
+    fn delete_epic(&self, epic_id: u32) -> Result<()> {
+        self.stage(Change::DeleteEpic { id: epic_id })
+    }
+
+    fn get_story(&self, story_id: u32) -> Result<Option<Story>> {
+        self.with_state(|db_state| db_state.stories.get(&story_id).cloned())
+    }
+
+    fn list_stories(&self, filter: &StoryFilter) -> Result<Vec<(u32, Story)>> {
+        self.with_state(|db_state| state::list_stories(db_state, filter))
+    }
+
+    fn insert_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        self.stage(Change::InsertStory { id: story_id, story: story.clone() })
+    }
+
+    fn update_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        self.stage(Change::UpdateStory { id: story_id, story: story.clone() })
+    }
+
+    fn delete_story(&self, story_id: u32) -> Result<()> {
+        self.stage(Change::DeleteStory { id: story_id })
+    }
+
+    fn get_trash_item(&self, item_id: u32) -> Result<Option<TrashItem>> {
+        self.with_state(|db_state| db_state.trash.get(&item_id).cloned())
+    }
+
+    fn list_trash(&self) -> Result<Vec<(u32, TrashItem)>> { self.with_state(state::list_trash) }
+
+    fn insert_trash_item(&self, item_id: u32, item: &TrashItem) -> Result<()> {
+        self.stage(Change::InsertTrashItem { id: item_id, item: item.clone() })
+    }
+
+    fn delete_trash_item(&self, item_id: u32) -> Result<()> {
+        self.stage(Change::DeleteTrashItem { id: item_id })
+    }
+
+    fn get_user(&self, username: &str) -> Result<Option<User>> {
+        self.with_state(|db_state| db_state.users.get(username).cloned())
+    }
+
+    fn list_users(&self) -> Result<Vec<(String, User)>> { self.with_state(state::list_users) }
+
+    fn insert_user(&self, username: &str, user: &User) -> Result<()> {
+        self.stage(Change::InsertUser {
+            username: username.to_string(),
+            user:     user.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::JiraDatabase;
+    use crate::database::test_utils::test_project;
+    use crate::models::{Priority, Status};
+
+    fn open(path: &Path) -> JiraDatabase {
+        JiraDatabase::from_database(Box::new(
+            EventLogDatabase::open(path.to_path_buf()).unwrap(),
+        ))
+    }
+
+    fn populate(db: &JiraDatabase) {
+        let project_id = test_project(db);
+        let epic_id =
+            db.create_epic(Epic::new("epic".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("story".to_string(), "".to_string()), epic_id).unwrap();
+        db.update_story_status(story_id, Status::Resolved).unwrap();
+    }
+
+    #[test]
+    fn replay_should_rebuild_state_after_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+
+        let db = open(&path);
+        populate(&db);
+
+        assert_eq!(open(&path).read().unwrap(), db.read().unwrap());
+    }
+
+    #[test]
+    fn history_should_record_each_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+        populate(&open(&path));
+
+        let history = EventLogDatabase::open(path).unwrap().history().unwrap();
+        let operations: Vec<&str> = history.iter().map(|e| e.operation.as_str()).collect();
+
+        assert_eq!(operations, vec![
+            "create_project",
+            "create_epic",
+            "create_story",
+            "update_story_status"
+        ]);
+        assert_eq!(history[3].changes, vec![Change::UpdateStory {
+            id:    3,
+            story: Story {
+                name:        "story".to_string(),
+                description: "".to_string(),
+                priority:    Priority::Medium,
+                status:      Status::Resolved,
+                assignee:    None,
+                reporter:    None,
+                labels:      Vec::new(),
+                start_date:  None,
+                due_date:    None,
+            },
+        }]);
+    }
+
+    #[test]
+    fn compaction_should_snapshot_and_archive_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+
+        let mut database = EventLogDatabase::open(path.clone()).unwrap();
+        database.compact_every = 3;
+        let db = JiraDatabase::from_database(Box::new(database));
+        populate(&db);
+
+        let reopened = EventLogDatabase::open(path.clone()).unwrap();
+        assert!(reopened.snapshot_path().exists());
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert_eq!(reopened.history().unwrap().len(), 4);
+        assert_eq!(open(&path).read().unwrap(), db.read().unwrap());
+    }
+
+    #[test]
+    fn history_should_skip_events_left_by_interrupted_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+        populate(&open(&path));
+
+        // The log was archived but not truncated yet.
+        let database = EventLogDatabase::open(path.clone()).unwrap();
+        fs::copy(&path, database.archive_path()).unwrap();
+        open(&path).update_story_status(3, Status::Closed).unwrap();
+
+        let revisions: Vec<u64> =
+            database.history().unwrap().iter().map(|event| event.revision).collect();
+        assert_eq!(revisions, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn replay_should_ignore_and_overwrite_torn_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+        populate(&open(&path));
+
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(log, r#"{{"revision":5,"timestamp":0,"oper"#).unwrap();
+
+        let db = open(&path);
+        assert_eq!(db.read().unwrap().revision, 4);
+
+        let project_id = db.list_projects().unwrap()[0].0;
+        db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        assert_eq!(open(&path).read().unwrap().revision, 5);
+    }
+
+    #[test]
+    fn reads_should_pick_up_events_from_other_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+
+        let first = open(&path);
+
+        let project_id = test_project(&first);
+        let second = open(&path);
+
+        let epic_id =
+            first.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        assert!(second.get_epic(epic_id).unwrap().is_some());
+    }
+
+    #[test]
+    fn rollback_should_not_append_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.jsonl");
+        let db = open(&path);
+
+        assert!(db.create_story(Story::new("".to_string(), "".to_string()), 999).is_err());
+
+        assert!(EventLogDatabase::open(path).unwrap().is_empty().unwrap());
+    }
+
+    #[test]
+    fn import_json_should_seed_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("db.json");
+        let db_state = DBState { revision: 5, last_item_id: 9, ..DBState::new() };
+        JSONFileDatabase::new(json_path.clone()).write(&db_state).unwrap();
+
+        let database = EventLogDatabase::open(dir.path().join("db.jsonl")).unwrap();
+        database.import_json(&json_path).unwrap();
+
+        assert!(!database.is_empty().unwrap());
+        assert_eq!(database.snapshot().unwrap(), db_state);
+    }
+}
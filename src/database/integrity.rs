@@ -0,0 +1,644 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use anyhow::Result;
+use itertools::Itertools;
+
+use super::Database;
+use crate::models::{DBState, Epic, Project, Trashed};
+
+/// Name of the epic that stories without one are moved into on repair.
+const RECOVERED_EPIC_NAME: &str = "Recovered stories";
+/// Key of the project that epics without one are moved into on repair.
+const RECOVERED_PROJECT_KEY: &str = "RECOVERED";
+
+/// A broken invariant of a `DBState`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    /// The same id is used by a project, an epic or a story, or by a live
+    /// item and a trashed one.
+    IdCollision { id: u32 },
+    /// A project lists an epic that doesn't exist.
+    DanglingEpic { project_id: u32, epic_id: u32 },
+    /// An epic is listed more than once, in one project or across several.
+    DuplicateEpicMembership { epic_id: u32, project_ids: Vec<u32> },
+    /// An epic that no project lists.
+    OrphanedEpic { epic_id: u32 },
+    /// An epic lists a story that doesn't exist.
+    DanglingStory { epic_id: u32, story_id: u32 },
+    /// A story is listed more than once, in one epic or across several.
+    DuplicateMembership { story_id: u32, epic_ids: Vec<u32> },
+    /// A story that no epic lists.
+    OrphanedStory { story_id: u32 },
+    /// `last_item_id` is below an id already in use, so it would be reused.
+    StaleLastItemId { last_item_id: u32, max_id: u32 },
+    /// An epic or story refers to a user that isn't in the directory.
+    UnknownUser { item_id: u32, username: String },
+    /// A trashed epic's project, or a trashed story's epic, is gone, so the
+    /// item can't be restored.
+    UnrestorableTrashItem { item_id: u32, parent_id: u32 },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::IdCollision { id } => {
+                write!(f, "Id {id} is used by more than one project, epic, story or trash item")
+            },
+            Issue::DanglingEpic { project_id, epic_id } => {
+                write!(f, "Project {project_id} refers to missing epic {epic_id}")
+            },
+            Issue::DuplicateEpicMembership { epic_id, project_ids } => {
+                write!(
+                    f,
+                    "Epic {epic_id} is listed more than once, by projects {project_ids:?}"
+                )
+            },
+            Issue::OrphanedEpic { epic_id } => {
+                write!(f, "Epic {epic_id} doesn't belong to any project")
+            },
+            Issue::DanglingStory { epic_id, story_id } => {
+                write!(f, "Epic {epic_id} refers to missing story {story_id}")
+            },
+            Issue::DuplicateMembership { story_id, epic_ids } => {
+                write!(f, "Story {story_id} is listed more than once, by epics {epic_ids:?}")
+            },
+            Issue::OrphanedStory { story_id } => {
+                write!(f, "Story {story_id} doesn't belong to any epic")
+            },
+            Issue::StaleLastItemId { last_item_id, max_id } => {
+                write!(f, "last_item_id is {last_item_id} but id {max_id} is already in use")
+            },
+            Issue::UnknownUser { item_id, username } => {
+                write!(f, "Item {item_id} refers to unknown user {username}")
+            },
+            Issue::UnrestorableTrashItem { item_id, parent_id } => {
+                write!(f, "Trash item {item_id} belongs to missing project or epic {parent_id}")
+            },
+        }
+    }
+}
+
+/// Lists every issue in `db_state`, in a stable order.
+pub fn check(db_state: &DBState) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let colliding_ids = colliding_project_ids(db_state)
+        .into_iter()
+        .chain(colliding_story_ids(db_state))
+        .chain(colliding_trash_ids(db_state));
+    for id in colliding_ids {
+        issues.push(Issue::IdCollision { id });
+    }
+
+    let mut epic_memberships: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for (project_id, project) in db_state.projects.iter().sorted_by_key(|(id, _)| **id) {
+        for epic_id in &project.epics {
+            if db_state.epics.contains_key(epic_id) {
+                epic_memberships.entry(*epic_id).or_default().push(*project_id);
+            } else {
+                let project_id = *project_id;
+                issues.push(Issue::DanglingEpic { project_id, epic_id: *epic_id });
+            }
+        }
+    }
+
+    for (epic_id, project_ids) in &epic_memberships {
+        if project_ids.len() > 1 {
+            let project_ids = project_ids.clone();
+            issues.push(Issue::DuplicateEpicMembership { epic_id: *epic_id, project_ids });
+        }
+    }
+
+    for epic_id in db_state.epics.keys().sorted() {
+        if !epic_memberships.contains_key(epic_id) {
+            issues.push(Issue::OrphanedEpic { epic_id: *epic_id });
+        }
+    }
+
+    let mut memberships: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for (epic_id, epic) in db_state.epics.iter().sorted_by_key(|(id, _)| **id) {
+        for story_id in &epic.stories {
+            if db_state.stories.contains_key(story_id) {
+                memberships.entry(*story_id).or_default().push(*epic_id);
+            } else {
+                issues.push(Issue::DanglingStory { epic_id: *epic_id, story_id: *story_id });
+            }
+        }
+    }
+
+    for (story_id, epic_ids) in &memberships {
+        if epic_ids.len() > 1 {
+            let epic_ids = epic_ids.clone();
+            issues.push(Issue::DuplicateMembership { story_id: *story_id, epic_ids });
+        }
+    }
+
+    for story_id in db_state.stories.keys().sorted() {
+        if !memberships.contains_key(story_id) {
+            issues.push(Issue::OrphanedStory { story_id: *story_id });
+        }
+    }
+
+    let max_id = max_id(db_state);
+    if db_state.last_item_id < max_id {
+        issues.push(Issue::StaleLastItemId { last_item_id: db_state.last_item_id, max_id });
+    }
+
+    let epic_users = db_state.epics.iter().map(|(id, epic)| (*id, vec![&epic.owner]));
+    let story_users = db_state
+        .stories
+        .iter()
+        .map(|(id, story)| (*id, vec![&story.assignee, &story.reporter]));
+    for (item_id, usernames) in epic_users.chain(story_users).sorted_by_key(|(id, _)| *id) {
+        for username in usernames.into_iter().flatten().dedup() {
+            if !db_state.users.contains_key(username) {
+                issues.push(Issue::UnknownUser { item_id, username: username.clone() });
+            }
+        }
+    }
+
+    for (item_id, trash_item) in db_state.trash.iter().sorted_by_key(|(id, _)| **id) {
+        if let Some(parent_id) = missing_parent(db_state, &trash_item.item) {
+            issues.push(Issue::UnrestorableTrashItem { item_id: *item_id, parent_id });
+        }
+    }
+
+    issues
+}
+
+/// Returns a copy of `db_state` with every issue `check` reports fixed:
+/// colliding projects, stories and trash items get fresh ids, dangling and
+/// duplicate references are dropped, orphaned stories are gathered into a new
+/// epic and orphaned epics into a new project. Trashed stories and epics whose
+/// parent is gone are restored into those instead. References to unknown users
+/// are cleared.
+pub fn repair(db_state: &DBState) -> DBState {
+    let mut repaired = db_state.clone();
+
+    let forget_unknown = |username: &mut Option<String>| {
+        if username.as_ref().is_some_and(|username| !db_state.users.contains_key(username)) {
+            *username = None;
+        }
+    };
+    repaired.epics.values_mut().for_each(|epic| forget_unknown(&mut epic.owner));
+    for story in repaired.stories.values_mut() {
+        forget_unknown(&mut story.assignee);
+        forget_unknown(&mut story.reporter);
+    }
+
+    let mut last_item_id = db_state.last_item_id.max(max_id(db_state));
+
+    for project_id in colliding_project_ids(db_state) {
+        last_item_id += 1;
+
+        let project = repaired.projects.remove(&project_id).expect("colliding project exists");
+        repaired.projects.insert(last_item_id, project);
+        for trash_item in repaired.trash.values_mut() {
+            if let Trashed::Epic { project_id: id, .. } = &mut trash_item.item
+                && *id == project_id
+            {
+                *id = last_item_id;
+            }
+        }
+    }
+
+    for story_id in colliding_story_ids(db_state) {
+        last_item_id += 1;
+
+        let story = repaired.stories.remove(&story_id).expect("colliding story exists");
+        repaired.stories.insert(last_item_id, story);
+        for epic in repaired.epics.values_mut() {
+            for id in epic.stories.iter_mut().filter(|id| **id == story_id) {
+                *id = last_item_id;
+            }
+        }
+    }
+
+    for item_id in colliding_trash_ids(db_state) {
+        last_item_id += 1;
+
+        let item = repaired.trash.remove(&item_id).expect("colliding trash item exists");
+        repaired.trash.insert(last_item_id, item);
+    }
+
+    let mut seen = HashSet::new();
+    let epic_ids: Vec<u32> = repaired.epics.keys().copied().sorted().collect();
+    for epic_id in epic_ids {
+        let epic = repaired.epics.get_mut(&epic_id).expect("epic exists");
+        epic.stories.retain(|id| repaired.stories.contains_key(id) && seen.insert(*id));
+    }
+
+    let orphans: Vec<u32> =
+        repaired.stories.keys().filter(|id| !seen.contains(id)).copied().sorted().collect();
+    let mut unrestorable: Vec<u32> = repaired
+        .trash
+        .iter()
+        .filter(|(_, trash_item)| missing_parent(&repaired, &trash_item.item).is_some())
+        .map(|(id, _)| *id)
+        .sorted()
+        .collect();
+
+    // Dangling epics are dropped before the recovery epic below is given an
+    // id that one of them might have referred to.
+    let mut seen_epics = HashSet::new();
+    let project_ids: Vec<u32> = repaired.projects.keys().copied().sorted().collect();
+    for project_id in project_ids {
+        let project = repaired.projects.get_mut(&project_id).expect("project exists");
+        project.epics.retain(|id| repaired.epics.contains_key(id) && seen_epics.insert(*id));
+    }
+    let trashed_stories = take_trashed(&mut unrestorable, &repaired, |item| {
+        matches!(item, Trashed::Story { .. })
+    });
+    if !orphans.is_empty() || !trashed_stories.is_empty() {
+        last_item_id += 1;
+
+        let mut epic = Epic::new(
+            RECOVERED_EPIC_NAME.to_string(),
+            "Stories that didn't belong to any epic".to_string(),
+        );
+        epic.stories = orphans;
+        repaired.epics.insert(last_item_id, epic);
+        reparent_trashed(&mut repaired, &trashed_stories, last_item_id);
+    }
+
+    let orphans: Vec<u32> =
+        repaired.epics.keys().filter(|id| !seen_epics.contains(id)).copied().sorted().collect();
+    if !orphans.is_empty() || !unrestorable.is_empty() {
+        last_item_id += 1;
+
+        let mut project = Project::new(
+            "Recovered epics".to_string(),
+            unused_project_key(&repaired),
+            "Epics that didn't belong to any project".to_string(),
+        );
+        project.epics = orphans;
+        repaired.projects.insert(last_item_id, project);
+        reparent_trashed(&mut repaired, &unrestorable, last_item_id);
+    }
+
+    repaired.last_item_id = last_item_id;
+    repaired
+}
+
+/// Writes the difference between `from` and `to` through `db`, within the
+/// caller's transaction.
+pub(super) fn write_changes(db: &dyn Database, from: &DBState, to: &DBState) -> Result<()> {
+    // Stories come first so that epics never link to one that isn't there yet.
+    for (story_id, story) in to.stories.iter().sorted_by_key(|(id, _)| **id) {
+        match from.stories.get(story_id) {
+            None => db.insert_story(*story_id, story)?,
+            Some(existing) if existing != story => db.update_story(*story_id, story)?,
+            Some(_) => {},
+        }
+    }
+
+    for story_id in from.stories.keys().filter(|id| !to.stories.contains_key(id)).sorted() {
+        db.delete_story(*story_id)?;
+    }
+
+    for project_id in from.projects.keys().filter(|id| !to.projects.contains_key(id)).sorted() {
+        db.delete_project(*project_id)?;
+    }
+
+    for epic_id in from.epics.keys().filter(|id| !to.epics.contains_key(id)).sorted() {
+        db.delete_epic(*epic_id)?;
+    }
+
+    for (epic_id, epic) in to.epics.iter().sorted_by_key(|(id, _)| **id) {
+        match from.epics.get(epic_id) {
+            None => db.insert_epic(*epic_id, epic)?,
+            Some(existing) if existing != epic => db.update_epic(*epic_id, epic)?,
+            Some(_) => {},
+        }
+    }
+
+    for (project_id, project) in to.projects.iter().sorted_by_key(|(id, _)| **id) {
+        match from.projects.get(project_id) {
+            None => db.insert_project(*project_id, project)?,
+            Some(existing) if existing != project => db.update_project(*project_id, project)?,
+            Some(_) => {},
+        }
+    }
+
+    for item_id in from.trash.keys().filter(|id| !to.trash.contains_key(id)).sorted() {
+        db.delete_trash_item(*item_id)?;
+    }
+
+    for (item_id, item) in to.trash.iter().sorted_by_key(|(id, _)| **id) {
+        if from.trash.get(item_id) != Some(item) {
+            db.delete_trash_item(*item_id)?;
+            db.insert_trash_item(*item_id, item)?;
+        }
+    }
+
+    db.reserve_ids(to.last_item_id)
+}
+
+/// The project of a trashed epic, or the epic of a trashed story, if it is
+/// neither live nor, for an epic, in the trash itself.
+fn missing_parent(db_state: &DBState, item: &Trashed) -> Option<u32> {
+    match item {
+        Trashed::Epic { project_id, .. } => {
+            (!db_state.projects.contains_key(project_id)).then_some(*project_id)
+        },
+        Trashed::Story { epic_id, .. } => {
+            let trashed = db_state
+                .trash
+                .get(epic_id)
+                .is_some_and(|trash_item| matches!(trash_item.item, Trashed::Epic { .. }));
+            (!db_state.epics.contains_key(epic_id) && !trashed).then_some(*epic_id)
+        },
+    }
+}
+
+/// Takes the trash items that `pick` accepts out of `item_ids`.
+fn take_trashed(
+    item_ids: &mut Vec<u32>,
+    db_state: &DBState,
+    pick: impl Fn(&Trashed) -> bool,
+) -> Vec<u32> {
+    let (picked, rest) = item_ids.iter().partition(|id| pick(&db_state.trash[*id].item));
+    *item_ids = rest;
+    picked
+}
+
+/// Points trash items at a new project or epic to be restored into.
+fn reparent_trashed(db_state: &mut DBState, item_ids: &[u32], parent_id: u32) {
+    for item_id in item_ids {
+        match &mut db_state.trash.get_mut(item_id).expect("trash item exists").item {
+            Trashed::Epic { project_id, .. } => *project_id = parent_id,
+            Trashed::Story { epic_id, .. } => *epic_id = parent_id,
+        }
+    }
+}
+
+/// Projects whose id is also taken by an epic or a story.
+fn colliding_project_ids(db_state: &DBState) -> Vec<u32> {
+    db_state
+        .projects
+        .keys()
+        .filter(|id| db_state.epics.contains_key(id) || db_state.stories.contains_key(id))
+        .copied()
+        .sorted()
+        .collect()
+}
+
+/// Stories whose id is also taken by an epic.
+fn colliding_story_ids(db_state: &DBState) -> Vec<u32> {
+    db_state
+        .stories
+        .keys()
+        .filter(|id| db_state.epics.contains_key(id))
+        .copied()
+        .sorted()
+        .collect()
+}
+
+/// Trash items whose id is also taken by a live project, epic or story.
+fn colliding_trash_ids(db_state: &DBState) -> Vec<u32> {
+    db_state
+        .trash
+        .keys()
+        .filter(|id| {
+            db_state.projects.contains_key(id)
+                || db_state.epics.contains_key(id)
+                || db_state.stories.contains_key(id)
+        })
+        .copied()
+        .sorted()
+        .collect()
+}
+
+/// The highest id in use anywhere, including stories inside trashed epics.
+fn max_id(db_state: &DBState) -> u32 {
+    let trashed_story_ids =
+        db_state.trash.values().flat_map(|trash_item| match &trash_item.item {
+            Trashed::Epic { stories, .. } => stories.keys().copied().collect(),
+            Trashed::Story { .. } => Vec::new(),
+        });
+
+    db_state
+        .projects
+        .keys()
+        .chain(db_state.epics.keys())
+        .chain(db_state.stories.keys())
+        .chain(db_state.trash.keys())
+        .copied()
+        .chain(trashed_story_ids)
+        .max()
+        .unwrap_or(0)
+}
+
+/// `RECOVERED`, or `RECOVERED2` and so on if a project already uses it.
+fn unused_project_key(db_state: &DBState) -> String {
+    let taken: HashSet<&str> = db_state.projects.values().map(|p| p.key.as_str()).collect();
+
+    (1..)
+        .map(|n| match n {
+            1 => RECOVERED_PROJECT_KEY.to_string(),
+            n => format!("{RECOVERED_PROJECT_KEY}{n}"),
+        })
+        .find(|key| !taken.contains(key.as_str()))
+        .expect("some key is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::database::test_utils::{MockDB, test_project};
+    use crate::database::{DocumentStore, JSONFileDatabase, JiraDatabase, SQLiteDatabase};
+    use crate::models::{Story, TrashItem, User};
+
+    fn epic_with(stories: Vec<u32>) -> Epic {
+        let mut epic = Epic::new("".to_string(), "".to_string());
+        epic.stories = stories;
+        epic
+    }
+
+    fn story() -> Story { Story::new("".to_string(), "".to_string()) }
+
+    fn project_with(epics: Vec<u32>) -> Project {
+        let mut project = Project::new("".to_string(), "P".to_string(), "".to_string());
+        project.epics = epics;
+        project
+    }
+
+    fn broken_state() -> DBState {
+        let mut db_state = DBState { last_item_id: 3, ..DBState::new() };
+
+        // Project 6 lists missing epic 9 and leaves epic 5 orphaned. Story 2 is
+        // listed twice by epic 1 and once by epic 3, epic 3 also lists missing
+        // story 9, story 4 is orphaned and story 5 collides with epic 5.
+        db_state.projects.insert(6, project_with(vec![1, 3, 9]));
+        db_state.epics.insert(1, epic_with(vec![2, 2]));
+        db_state.epics.insert(3, epic_with(vec![2, 9]));
+        db_state.epics.insert(5, epic_with(vec![5]));
+        db_state.stories.insert(2, story());
+        db_state.stories.insert(4, story());
+        db_state.stories.insert(5, story());
+
+        let item = Trashed::Story { epic_id: 1, position: 0, story: story() };
+        db_state.trash.insert(4, TrashItem { deleted_at: 0, item });
+        db_state
+    }
+
+    #[test]
+    fn check_should_pass_for_consistent_state() {
+        let db = JiraDatabase::from_store(MockDB::new());
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id = db.create_story(story(), epic_id).unwrap();
+        db.create_story(story(), epic_id).unwrap();
+        db.delete_story(epic_id, story_id).unwrap();
+
+        assert_eq!(check(&db.read().unwrap()), vec![]);
+    }
+
+    #[test]
+    fn check_should_report_every_issue() {
+        assert_eq!(check(&broken_state()), vec![
+            Issue::IdCollision { id: 5 },
+            Issue::IdCollision { id: 4 },
+            Issue::DanglingEpic { project_id: 6, epic_id: 9 },
+            Issue::OrphanedEpic { epic_id: 5 },
+            Issue::DanglingStory { epic_id: 3, story_id: 9 },
+            Issue::DuplicateMembership { story_id: 2, epic_ids: vec![1, 1, 3] },
+            Issue::OrphanedStory { story_id: 4 },
+            Issue::StaleLastItemId { last_item_id: 3, max_id: 6 },
+        ]);
+    }
+
+    #[test]
+    fn repair_should_fix_every_issue() {
+        let repaired = repair(&broken_state());
+
+        assert_eq!(check(&repaired), vec![]);
+        assert_eq!(repaired.epics[&1].stories, vec![2]);
+        assert_eq!(repaired.epics[&3].stories, Vec::<u32>::new());
+        assert_eq!(repaired.epics[&5].stories, vec![7]);
+        assert!(repaired.trash.contains_key(&8));
+        assert_eq!(repaired.epics[&9].name, RECOVERED_EPIC_NAME);
+        assert_eq!(repaired.epics[&9].stories, vec![4]);
+        assert_eq!(repaired.projects[&6].epics, vec![1, 3]);
+        assert_eq!(repaired.projects[&10].key, RECOVERED_PROJECT_KEY);
+        assert_eq!(repaired.projects[&10].epics, vec![5, 9]);
+        assert_eq!(repaired.last_item_id, 10);
+    }
+
+    #[test]
+    fn repair_should_renumber_colliding_projects() {
+        let mut db_state = DBState { last_item_id: 2, ..DBState::new() };
+        db_state.projects.insert(1, project_with(vec![2]));
+        db_state.projects.insert(2, project_with(vec![]));
+        db_state.epics.insert(1, epic_with(vec![]));
+        db_state.epics.insert(2, epic_with(vec![]));
+
+        let mut repaired = repair(&db_state);
+        assert_eq!(check(&repaired), vec![]);
+        assert_eq!(repaired.projects.remove(&3), Some(project_with(vec![2])));
+        assert_eq!(repaired.projects.remove(&4), Some(project_with(vec![])));
+    }
+
+    #[test]
+    fn repair_integrity_should_write_repaired_state() {
+        let store = MockDB::new();
+        store.write(&broken_state()).unwrap();
+        let db = JiraDatabase::from_store(store);
+
+        assert_eq!(db.repair_integrity().unwrap(), check(&broken_state()));
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state, DBState { revision: 1, ..repair(&broken_state()) });
+        assert_eq!(db.repair_integrity().unwrap(), vec![]);
+        assert_eq!(db.read().unwrap().revision, 1);
+    }
+
+    #[test]
+    fn repair_integrity_should_work_on_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("db.json");
+        JSONFileDatabase::new(json_path.clone()).write(&broken_state()).unwrap();
+
+        let sqlite = SQLiteDatabase::open(dir.path().join("db.sqlite")).unwrap();
+        sqlite.import_json(&json_path).unwrap();
+        let db = JiraDatabase::from_database(Box::new(sqlite));
+
+        assert!(!db.check_integrity().unwrap().is_empty());
+        db.repair_integrity().unwrap();
+        assert_eq!(db.check_integrity().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn repair_should_leave_consistent_state_untouched() {
+        let mut db_state = DBState { last_item_id: 3, ..DBState::new() };
+        db_state.projects.insert(3, project_with(vec![1]));
+        db_state.epics.insert(1, epic_with(vec![2]));
+        db_state.stories.insert(2, story());
+
+        assert_eq!(repair(&db_state), db_state);
+    }
+
+    #[test]
+    fn repair_should_reattach_unrestorable_trash_items() {
+        let mut db_state = DBState { last_item_id: 5, ..DBState::new() };
+        db_state.projects.insert(3, project_with(vec![1]));
+        db_state.epics.insert(1, epic_with(vec![]));
+        // Story 2 sat in missing epic 9, story 4 in trashed epic 5, and epic 5
+        // in missing project 8.
+        let stories = HashMap::new();
+        let epic =
+            Trashed::Epic { project_id: 8, position: 0, epic: epic_with(vec![]), stories };
+        db_state.trash.insert(5, TrashItem { deleted_at: 0, item: epic });
+        for (story_id, epic_id) in [(2, 9), (4, 5)] {
+            let item = Trashed::Story { epic_id, position: 0, story: story() };
+            db_state.trash.insert(story_id, TrashItem { deleted_at: 0, item });
+        }
+
+        assert_eq!(check(&db_state), vec![
+            Issue::UnrestorableTrashItem { item_id: 2, parent_id: 9 },
+            Issue::UnrestorableTrashItem { item_id: 5, parent_id: 8 },
+        ]);
+
+        let repaired = repair(&db_state);
+        assert_eq!(check(&repaired), vec![]);
+        assert_eq!(repaired.epics[&6].name, RECOVERED_EPIC_NAME);
+        assert_eq!(repaired.projects[&7].epics, vec![6]);
+        assert!(matches!(repaired.trash[&2].item, Trashed::Story { epic_id: 6, .. }));
+        assert!(matches!(repaired.trash[&4].item, Trashed::Story { epic_id: 5, .. }));
+        assert!(matches!(repaired.trash[&5].item, Trashed::Epic { project_id: 7, .. }));
+
+        let store = MockDB::new();
+        store.write(&repaired).unwrap();
+        let db = JiraDatabase::from_store(store);
+        db.restore(5).unwrap();
+        db.restore(4).unwrap();
+        db.restore(2).unwrap();
+        assert_eq!(db.read().unwrap().epics[&5].stories, vec![4]);
+        assert_eq!(db.read().unwrap().epics[&6].stories, vec![2]);
+    }
+
+    #[test]
+    fn repair_should_clear_unknown_users() {
+        let mut db_state = DBState { last_item_id: 3, ..DBState::new() };
+        db_state.users.insert("ana".to_string(), User { name: "Ana".to_string() });
+        db_state.projects.insert(3, project_with(vec![1]));
+        db_state.epics.insert(1, Epic { owner: Some("bo".to_string()), ..epic_with(vec![2]) });
+        let assignee = Some("ana".to_string());
+        let reporter = Some("bo".to_string());
+        db_state.stories.insert(2, Story { assignee, reporter, ..story() });
+
+        assert_eq!(check(&db_state), vec![
+            Issue::UnknownUser { item_id: 1, username: "bo".to_string() },
+            Issue::UnknownUser { item_id: 2, username: "bo".to_string() },
+        ]);
+        let repaired = repair(&db_state);
+        assert_eq!(check(&repaired), vec![]);
+        assert_eq!(repaired.epics[&1].owner, None);
+        assert_eq!(repaired.stories[&2].assignee.as_deref(), Some("ana"));
+        assert_eq!(repaired.stories[&2].reporter, None);
+    }
+}
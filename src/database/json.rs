@@ -0,0 +1,378 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use super::migrations::{from_json, to_json};
+use super::{DatabaseLock, DocumentStore, LOCK_TIMEOUT, LockMode};
+use crate::models::DBState;
+
+pub struct JSONFileDatabase {
+    pub file_path:    PathBuf,
+    pub lock_timeout: Duration,
+}
+
+impl JSONFileDatabase {
+    pub fn new(file_path: PathBuf) -> Self { Self { file_path, lock_timeout: LOCK_TIMEOUT } }
+
+    fn backup_path(&self) -> PathBuf { sibling_path(&self.file_path, ".bak") }
+
+    fn temp_path(&self) -> PathBuf { sibling_path(&self.file_path, ".tmp") }
+
+    // The data file itself is replaced on every write, so locks are taken on a
+    // sidecar file whose inode stays put.
+    fn lock_path(&self) -> PathBuf { sibling_path(&self.file_path, ".lock") }
+}
+
+impl DocumentStore for JSONFileDatabase {
+    fn read(&self) -> Result<DBState> {
+        // A board that doesn't exist yet starts out empty. It is written by
+        // the first change, under the exclusive lock, not here.
+        let exists = self
+            .file_path
+            .try_exists()
+            .with_context(|| format!("Failed to read file: {}", self.file_path.display()))?;
+        if !exists {
+            return Ok(DBState::new());
+        }
+
+        let error = match read_state(&self.file_path) {
+            Ok(state) => return Ok(state),
+            Err(error) if error.is::<serde_json::Error>() => error,
+            Err(error) => return Err(error),
+        };
+
+        // The primary file is corrupt (e.g. truncated by a crash mid-write), so
+        // fall back to the last good copy kept alongside it.
+        let backup_path = self.backup_path();
+        let state = read_state(&backup_path).map_err(|_| error)?;
+        eprintln!(
+            "Warning: {} is corrupt, recovered from backup {}",
+            self.file_path.display(),
+            backup_path.display()
+        );
+        Ok(state)
+    }
+
+    fn write(&self, data: &DBState) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let json_data = to_json(data)?;
+
+        let temp_path = self.temp_path();
+        write_synced(&temp_path, &json_data)
+            .with_context(|| format!("Failed to write to file: {}", temp_path.display()))?;
+
+        // Only a primary file that still parses is worth keeping as a backup,
+        // otherwise a corrupt file would clobber the last good copy.
+        if read_state(&self.file_path).is_ok() {
+            let backup_path = self.backup_path();
+            fs::copy(&self.file_path, &backup_path).with_context(|| {
+                format!("Failed to back up file to: {}", backup_path.display())
+            })?;
+        }
+
+        fs::rename(&temp_path, &self.file_path).with_context(|| {
+            format!("Failed to write to file: {}", self.file_path.display())
+        })?;
+        sync_parent_dir(&self.file_path)
+    }
+
+    fn lock(&self, mode: LockMode) -> Result<DatabaseLock> {
+        DatabaseLock::acquire(&self.lock_path(), mode, self.lock_timeout)
+    }
+}
+
+fn read_state(path: &Path) -> Result<DBState> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+
+    from_json(&content)
+        .with_context(|| format!("Failed to parse JSON from file: {}", path.display()))
+}
+
+pub(super) fn write_synced(path: &Path, data: &[u8]) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Flushes the directory entry so a completed rename survives a crash.
+#[cfg(unix)]
+pub(super) fn sync_parent_dir(path: &Path) -> Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync directory: {}", parent.display()))
+}
+
+#[cfg(not(unix))]
+pub(super) fn sync_parent_dir(_path: &Path) -> Result<()> { Ok(()) }
+
+pub(super) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    use super::*;
+    use crate::models::{Epic, Priority, Status, Story, TrashItem, Trashed};
+
+    #[test]
+    fn read_from_db_should_fail_with_invalid_path() {
+        let tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        let db = JSONFileDatabase::new(tmpfile.path().join("db.json"));
+        assert!(db.read().is_err());
+    }
+
+    #[test]
+    fn read_from_db_should_start_missing_file_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("boards").join("db.json"));
+
+        assert_eq!(db.read().unwrap(), DBState::new());
+        assert!(!db.file_path.exists());
+
+        db.write(&DBState::new()).unwrap();
+        assert_eq!(read_state(&db.file_path).unwrap(), DBState::new());
+    }
+
+    #[test]
+    fn read_from_db_should_fail_with_invalid_json() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        let file_contents = r#"{ "last_item_id": 0 epics: {} stories {} }"#;
+        write!(tmpfile, "{file_contents}").unwrap();
+
+        let db = JSONFileDatabase::new(tmpfile.path().to_path_buf());
+
+        let result = db.read();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn read_from_db_should_parse_json_file() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+        write!(tmpfile, "{file_contents}").unwrap();
+
+        let db = JSONFileDatabase::new(tmpfile.path().to_path_buf());
+
+        let result = db.read();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn read_from_db_should_default_missing_priorities() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        let file_contents = r#"{
+            "last_item_id": 1,
+            "epics": {},
+            "stories": { "1": { "name": "", "description": "", "status": "Open" } }
+        }"#;
+        write!(tmpfile, "{file_contents}").unwrap();
+
+        let db = JSONFileDatabase::new(tmpfile.path().to_path_buf());
+
+        assert_eq!(db.read().unwrap().stories[&1].priority, Priority::Medium);
+    }
+
+    #[test]
+    fn write_to_db_should_pass() {
+        let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+        let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+        write!(tmpfile, "{file_contents}").unwrap();
+
+        let db = JSONFileDatabase::new(tmpfile.path().to_path_buf());
+        let story = Story {
+            name:        "epic 1".to_string(),
+            description: "epic 1".to_string(),
+            priority:    Priority::Highest,
+            status:      Status::Open,
+            assignee:    None,
+            reporter:    None,
+            labels:      Vec::new(),
+            start_date:  None,
+            due_date:    None,
+        };
+        let epic = Epic {
+            name:        "epic 1".to_string(),
+            description: "epic 1".to_string(),
+            priority:    Priority::Medium,
+            status:      Status::Open,
+            stories:     vec![2],
+            owner:       None,
+            labels:      Vec::new(),
+            start_date:  None,
+            due_date:    None,
+        };
+
+        let mut stories = HashMap::with_capacity(1);
+        stories.insert(2, story);
+
+        let mut epics = HashMap::with_capacity(1);
+        epics.insert(1, epic);
+
+        let state = DBState {
+            revision: 0,
+            last_item_id: 2,
+            projects: HashMap::new(),
+            epics,
+            stories,
+            trash: HashMap::new(),
+            users: HashMap::new(),
+        };
+
+        let write_result = db.write(&state);
+        let read_result = db.read().unwrap();
+
+        assert!(write_result.is_ok());
+        assert_eq!(read_result, state);
+    }
+
+    #[test]
+    fn write_to_db_should_round_trip_trashed_epic_with_stories() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        let story = Story {
+            name:        "story 3".to_string(),
+            description: "".to_string(),
+            priority:    Priority::Medium,
+            status:      Status::Open,
+            assignee:    None,
+            reporter:    None,
+            labels:      Vec::new(),
+            start_date:  None,
+            due_date:    None,
+        };
+        let epic = Epic {
+            name:        "epic 2".to_string(),
+            description: "".to_string(),
+            priority:    Priority::Medium,
+            status:      Status::Open,
+            stories:     vec![3],
+            owner:       None,
+            labels:      Vec::new(),
+            start_date:  None,
+            due_date:    None,
+        };
+        let item = Trashed::Epic {
+            project_id: 1,
+            position: 0,
+            epic,
+            stories: HashMap::from([(3, story)]),
+        };
+
+        let state = DBState {
+            last_item_id: 3,
+            trash: HashMap::from([(2, TrashItem { deleted_at: 1, item })]),
+            ..DBState::new()
+        };
+        db.write(&state).unwrap();
+
+        assert_eq!(read_state(&db.file_path).unwrap(), state);
+    }
+
+    #[test]
+    fn write_to_db_should_not_leave_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        db.write(&DBState::new()).unwrap();
+
+        assert!(db.file_path.exists());
+        assert!(!db.temp_path().exists());
+    }
+
+    #[test]
+    fn write_to_db_should_keep_previous_state_as_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        let previous = DBState { last_item_id: 1, ..DBState::new() };
+        db.write(&previous).unwrap();
+        db.write(&DBState { last_item_id: 2, ..DBState::new() }).unwrap();
+
+        assert_eq!(read_state(&db.backup_path()).unwrap(), previous);
+    }
+
+    #[test]
+    fn read_from_db_should_fall_back_to_backup_if_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        let previous = DBState { last_item_id: 1, ..DBState::new() };
+        db.write(&previous).unwrap();
+        db.write(&DBState { last_item_id: 2, ..DBState::new() }).unwrap();
+
+        fs::write(&db.file_path, r#"{ "last_item_id": 2, "epi"#).unwrap();
+
+        assert_eq!(db.read().unwrap(), previous);
+    }
+
+    #[test]
+    fn write_to_db_should_not_back_up_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        let previous = DBState { last_item_id: 1, ..DBState::new() };
+        db.write(&previous).unwrap();
+        db.write(&DBState { last_item_id: 2, ..DBState::new() }).unwrap();
+
+        fs::write(&db.file_path, r#"{ "last_item_id": 2, "epi"#).unwrap();
+        db.write(&DBState { last_item_id: 3, ..DBState::new() }).unwrap();
+
+        assert_eq!(read_state(&db.backup_path()).unwrap(), previous);
+    }
+
+    #[test]
+    fn shared_locks_should_not_block_each_other() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = JSONFileDatabase::new(dir.path().join("db.json"));
+
+        let _first = db.lock(LockMode::Shared).unwrap();
+        assert!(db.lock(LockMode::Shared).is_ok());
+    }
+
+    #[test]
+    fn lock_should_time_out_while_held_exclusively() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = JSONFileDatabase::new(dir.path().join("db.json"));
+        db.lock_timeout = Duration::from_millis(100);
+
+        let _held = db.lock(LockMode::Exclusive).unwrap();
+        assert!(db.lock(LockMode::Shared).is_err());
+        assert!(db.lock(LockMode::Exclusive).is_err());
+    }
+
+    #[test]
+    fn lock_should_be_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = JSONFileDatabase::new(dir.path().join("db.json"));
+        db.lock_timeout = Duration::from_millis(100);
+
+        drop(db.lock(LockMode::Exclusive).unwrap());
+        assert!(db.lock(LockMode::Exclusive).is_ok());
+    }
+}
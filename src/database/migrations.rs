@@ -0,0 +1,339 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::models::DBState;
+
+/// The `schema_version` written into every new document.
+pub const SCHEMA_VERSION: u64 = 8;
+
+/// Key of the project that boards from before projects existed are moved into.
+const DEFAULT_PROJECT_KEY: &str = "MAIN";
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// `MIGRATIONS[n]` upgrades a document from version `n + 1` to `n + 2`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize - 1] =
+    [add_revision, add_trash, add_projects, add_priorities, add_users, add_labels, add_dates];
+
+#[derive(Serialize)]
+struct VersionedDocument<'a> {
+    schema_version: u64,
+    #[serde(flatten)]
+    db_state:       &'a DBState,
+}
+
+pub fn to_json(db_state: &DBState) -> Result<Vec<u8>> {
+    let document = VersionedDocument { schema_version: SCHEMA_VERSION, db_state };
+    serde_json::to_vec_pretty(&document).context("Failed to serialize data to JSON")
+}
+
+/// Parses a document of any known version, upgrading it step by step.
+pub fn from_json(content: &str) -> Result<DBState> {
+    let mut document: Value = serde_json::from_str(content)?;
+    let fields = document
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Expected a JSON object at the top level"))?;
+
+    // Documents written before versioning was introduced have no version.
+    let version = match fields.get("schema_version") {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| anyhow!("Invalid schema_version: {version}"))?,
+    };
+
+    if version > SCHEMA_VERSION {
+        bail!(
+            "Database schema version {version} is newer than the supported version \
+             {SCHEMA_VERSION}; please upgrade jiraffe"
+        );
+    }
+
+    for (step, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        migrate(fields).with_context(|| {
+            format!("Failed to migrate database to schema version {}", step + 2)
+        })?;
+    }
+
+    Ok(serde_json::from_value(document)?)
+}
+
+/// v1 -> v2: documents gained a revision counter for optimistic concurrency.
+fn add_revision(fields: &mut Map<String, Value>) -> Result<()> {
+    fields.entry("revision").or_insert(Value::from(0));
+    Ok(())
+}
+
+/// v2 -> v3: deleted items are kept in a trash area instead of dropped.
+fn add_trash(fields: &mut Map<String, Value>) -> Result<()> {
+    fields.entry("trash").or_insert(Value::Object(Map::new()));
+    Ok(())
+}
+
+/// v3 -> v4: epics belong to a project. Existing epics, including trashed
+/// ones, are gathered into a single default project under a fresh id.
+fn add_projects(fields: &mut Map<String, Value>) -> Result<()> {
+    if fields.contains_key("projects") {
+        return Ok(());
+    }
+
+    let mut epic_ids: Vec<u64> = match fields.get("epics") {
+        Some(Value::Object(epics)) => epics
+            .keys()
+            .map(|id| id.parse().with_context(|| format!("Invalid epic id: {id}")))
+            .collect::<Result<_>>()?,
+        _ => Vec::new(),
+    };
+    epic_ids.sort_unstable();
+
+    let has_trashed_epics = match fields.get("trash") {
+        Some(Value::Object(trash)) => trash.values().any(|item| item["kind"] == "epic"),
+        _ => false,
+    };
+
+    let mut projects = Map::new();
+    if !epic_ids.is_empty() || has_trashed_epics {
+        let last_item_id = fields.get("last_item_id").and_then(Value::as_u64).unwrap_or(0);
+        let project_id = last_item_id + 1;
+        fields.insert("last_item_id".to_string(), Value::from(project_id));
+
+        if let Some(Value::Object(trash)) = fields.get_mut("trash") {
+            for item in trash.values_mut().filter(|item| item["kind"] == "epic") {
+                if let Value::Object(item) = item {
+                    item.insert("project_id".to_string(), Value::from(project_id));
+                    item.insert("position".to_string(), Value::from(epic_ids.len()));
+                }
+            }
+        }
+
+        projects.insert(
+            project_id.to_string(),
+            serde_json::json!({
+                "name": "Main",
+                "key": DEFAULT_PROJECT_KEY,
+                "description": "Epics created before projects were introduced",
+                "epics": epic_ids,
+            }),
+        );
+    }
+
+    fields.insert("projects".to_string(), Value::Object(projects));
+    Ok(())
+}
+
+/// v4 -> v5: epics and stories have a priority. Older items read as medium
+/// priority, so there is nothing to rewrite; the bump keeps older builds from
+/// dropping priorities they don't know about.
+fn add_priorities(_fields: &mut Map<String, Value>) -> Result<()> { Ok(()) }
+
+/// v5 -> v6: boards keep a directory of users to assign work to.
+fn add_users(fields: &mut Map<String, Value>) -> Result<()> {
+    fields.entry("users").or_insert(Value::Object(Map::new()));
+    Ok(())
+}
+
+/// v6 -> v7: epics and stories have labels, which older items read as none.
+fn add_labels(_fields: &mut Map<String, Value>) -> Result<()> { Ok(()) }
+
+/// v7 -> v8: epics and stories have optional start and due dates, which older
+/// items read as unset.
+fn add_dates(_fields: &mut Map<String, Value>) -> Result<()> { Ok(()) }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Priority, Status, Trashed};
+
+    #[test]
+    fn from_json_should_upgrade_v1_document() {
+        let content = r#"{
+            "last_item_id": 2,
+            "epics": { "1": { "name": "e", "description": "", "status": "Open", "stories": [2] } },
+            "stories": { "2": { "name": "s", "description": "", "status": "Closed" } }
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+
+        assert_eq!(db_state.revision, 0);
+        assert_eq!(db_state.last_item_id, 3);
+        assert_eq!(db_state.projects[&3].epics, vec![1]);
+        assert_eq!(db_state.epics[&1].stories, vec![2]);
+        assert_eq!(db_state.stories[&2].status, Status::Closed);
+    }
+
+    #[test]
+    fn from_json_should_read_v4_document() {
+        let content = r#"{
+            "schema_version": 4,
+            "revision": 7,
+            "last_item_id": 0,
+            "projects": {},
+            "epics": {},
+            "stories": {},
+            "trash": {}
+        }"#;
+
+        assert_eq!(from_json(content).unwrap(), DBState { revision: 7, ..DBState::new() });
+    }
+
+    #[test]
+    fn from_json_should_give_v4_items_medium_priority() {
+        let content = r#"{
+            "schema_version": 4,
+            "revision": 1,
+            "last_item_id": 3,
+            "projects": { "3": { "name": "p", "key": "P", "description": "", "epics": [1] } },
+            "epics": { "1": { "name": "e", "description": "", "status": "Open", "stories": [2] } },
+            "stories": { "2": { "name": "s", "description": "", "status": "Open" } },
+            "trash": {}
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+        assert_eq!(db_state.epics[&1].priority, Priority::Medium);
+        assert_eq!(db_state.stories[&2].priority, Priority::Medium);
+    }
+
+    #[test]
+    fn from_json_should_read_v6_items_without_labels() {
+        let content = r#"{
+            "schema_version": 6,
+            "revision": 1,
+            "last_item_id": 3,
+            "projects": { "3": { "name": "p", "key": "P", "description": "", "epics": [1] } },
+            "epics": {
+                "1": {
+                    "name": "e",
+                    "description": "",
+                    "status": "Open",
+                    "priority": "High",
+                    "stories": [2]
+                }
+            },
+            "stories": {
+                "2": { "name": "s", "description": "", "status": "Open", "priority": "Low" }
+            },
+            "trash": {},
+            "users": {}
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+        assert_eq!(db_state.epics[&1].priority, Priority::High);
+        assert!(db_state.epics[&1].labels.is_empty());
+        assert!(db_state.stories[&2].labels.is_empty());
+    }
+
+    #[test]
+    fn from_json_should_read_v7_items_without_dates() {
+        let content = r#"{
+            "schema_version": 7,
+            "revision": 1,
+            "last_item_id": 3,
+            "projects": { "3": { "name": "p", "key": "P", "description": "", "epics": [1] } },
+            "epics": {
+                "1": {
+                    "name": "e",
+                    "description": "",
+                    "status": "Open",
+                    "labels": ["ui"],
+                    "stories": [2]
+                }
+            },
+            "stories": { "2": { "name": "s", "description": "", "status": "Open" } },
+            "trash": {},
+            "users": {}
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+        assert_eq!(db_state.epics[&1].labels, vec!["ui"]);
+        assert_eq!(db_state.epics[&1].due_date, None);
+        assert_eq!(db_state.stories[&2].start_date, None);
+    }
+
+    #[test]
+    fn from_json_should_upgrade_v2_document_with_empty_trash() {
+        let content = r#"{
+            "schema_version": 2,
+            "revision": 1,
+            "last_item_id": 0,
+            "epics": {},
+            "stories": {}
+        }"#;
+
+        assert!(from_json(content).unwrap().trash.is_empty());
+    }
+
+    #[test]
+    fn from_json_should_move_v3_epics_into_default_project() {
+        let content = r#"{
+            "schema_version": 3,
+            "revision": 1,
+            "last_item_id": 4,
+            "epics": {
+                "3": { "name": "b", "description": "", "status": "Open", "stories": [] },
+                "1": { "name": "a", "description": "", "status": "Open", "stories": [] }
+            },
+            "stories": {},
+            "trash": {
+                "4": {
+                    "deleted_at": 0,
+                    "kind": "epic",
+                    "epic": { "name": "c", "description": "", "status": "Open", "stories": [] },
+                    "stories": {}
+                }
+            }
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+
+        assert_eq!(db_state.last_item_id, 5);
+        assert_eq!(db_state.projects[&5].key, DEFAULT_PROJECT_KEY);
+        assert_eq!(db_state.projects[&5].epics, vec![1, 3]);
+        assert!(matches!(db_state.trash[&4].item, Trashed::Epic {
+            project_id: 5,
+            position: 2,
+            ..
+        }));
+    }
+
+    #[test]
+    fn from_json_should_not_add_default_project_to_empty_board() {
+        let content = r#"{
+            "schema_version": 3,
+            "revision": 0,
+            "last_item_id": 0,
+            "epics": {},
+            "stories": {},
+            "trash": {}
+        }"#;
+
+        let db_state = from_json(content).unwrap();
+        assert!(db_state.projects.is_empty());
+        assert_eq!(db_state.last_item_id, 0);
+    }
+
+    #[test]
+    fn from_json_should_reject_newer_schema_version() {
+        let content = r#"{ "schema_version": 99, "revision": 0, "last_item_id": 0 }"#;
+
+        let error = from_json(content).unwrap_err();
+        assert!(!error.is::<serde_json::Error>());
+    }
+
+    #[test]
+    fn from_json_should_reject_invalid_schema_version() {
+        let content = r#"{ "schema_version": "two", "last_item_id": 0 }"#;
+        assert!(from_json(content).is_err());
+    }
+
+    #[test]
+    fn to_json_should_round_trip_with_current_version() {
+        let db_state = DBState { revision: 3, last_item_id: 1, ..DBState::new() };
+        let content = String::from_utf8(to_json(&db_state).unwrap()).unwrap();
+
+        let document: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(document["schema_version"], SCHEMA_VERSION);
+        assert_eq!(from_json(&content).unwrap(), db_state);
+    }
+}
@@ -0,0 +1,1030 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row, ToSql, params};
+
+use super::{Database, DocumentStore, EpicFilter, LOCK_TIMEOUT, StoryFilter};
+use crate::models::{DBState, Date, Epic, Priority, Project, Status, Story, TrashItem, User};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key   TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS projects (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT NOT NULL,
+        key         TEXT NOT NULL,
+        description TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS epics (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        priority    TEXT NOT NULL DEFAULT 'MEDIUM',
+        owner       TEXT,
+        start_date  TEXT,
+        due_date    TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS stories (
+        id          INTEGER PRIMARY KEY,
+        name        TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        priority    TEXT NOT NULL DEFAULT 'MEDIUM',
+        assignee    TEXT,
+        reporter    TEXT,
+        start_date  TEXT,
+        due_date    TEXT
+    );
+
+    -- Epics and stories refer to users by username. Unknown usernames are
+    -- reported by the integrity check rather than rejected here, as with the
+    -- other backends.
+    CREATE TABLE IF NOT EXISTS users (
+        username TEXT PRIMARY KEY,
+        name     TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS project_epics (
+        project_id INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+        epic_id    INTEGER NOT NULL UNIQUE REFERENCES epics (id) ON DELETE CASCADE,
+        position   INTEGER NOT NULL,
+        PRIMARY KEY (project_id, epic_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS epic_stories (
+        epic_id  INTEGER NOT NULL REFERENCES epics (id) ON DELETE CASCADE,
+        story_id INTEGER NOT NULL UNIQUE REFERENCES stories (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (epic_id, story_id)
+    );
+
+    CREATE TABLE IF NOT EXISTS epic_labels (
+        epic_id INTEGER NOT NULL REFERENCES epics (id) ON DELETE CASCADE,
+        label   TEXT NOT NULL,
+        PRIMARY KEY (epic_id, label)
+    );
+
+    CREATE TABLE IF NOT EXISTS story_labels (
+        story_id INTEGER NOT NULL REFERENCES stories (id) ON DELETE CASCADE,
+        label    TEXT NOT NULL,
+        PRIMARY KEY (story_id, label)
+    );
+
+    -- Trashed items are only ever restored or purged as a whole, so they are
+    -- kept as JSON rather than spread across the tables above.
+    CREATE TABLE IF NOT EXISTS trash (
+        id         INTEGER PRIMARY KEY,
+        deleted_at INTEGER NOT NULL,
+        item       TEXT NOT NULL
+    );
+";
+
+pub struct SQLiteDatabase {
+    pub file_path: PathBuf,
+    connection:    Connection,
+}
+
+impl SQLiteDatabase {
+    pub fn open(file_path: PathBuf) -> Result<Self> {
+        if let Some(parent) = file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let connection = Connection::open(&file_path)
+            .with_context(|| format!("Failed to open database: {}", file_path.display()))?;
+        let database = Self { file_path, connection };
+        database.init()?;
+        Ok(database)
+    }
+
+    /// Opens an existing database without creating or upgrading its schema.
+    pub fn open_read_only(file_path: PathBuf) -> Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let connection = Connection::open_with_flags(&file_path, flags)
+            .with_context(|| format!("Failed to open database: {}", file_path.display()))?;
+        connection.busy_timeout(LOCK_TIMEOUT)?;
+        Ok(Self { file_path, connection })
+    }
+
+    fn init(&self) -> Result<()> {
+        self.connection.busy_timeout(LOCK_TIMEOUT)?;
+        self.connection.pragma_update(None, "foreign_keys", true)?;
+        self.connection.execute_batch(SCHEMA).with_context(|| {
+            format!("Failed to create schema in: {}", self.file_path.display())
+        })?;
+
+        // Tables created by older versions don't get new columns from
+        // `CREATE TABLE IF NOT EXISTS`.
+        for table in ["epics", "stories"] {
+            self.add_missing_column(table, "priority", "TEXT NOT NULL DEFAULT 'MEDIUM'")?;
+        }
+        self.add_missing_column("epics", "owner", "TEXT")?;
+        self.add_missing_column("stories", "assignee", "TEXT")?;
+        self.add_missing_column("stories", "reporter", "TEXT")?;
+        for table in ["epics", "stories"] {
+            self.add_missing_column(table, "start_date", "TEXT")?;
+            self.add_missing_column(table, "due_date", "TEXT")?;
+        }
+        Ok(())
+    }
+
+    fn add_missing_column(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = self.connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            self.connection
+                .execute(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"), [])
+                .with_context(|| format!("Failed to add {table}.{column}"))?;
+        }
+        Ok(())
+    }
+
+    /// A database that has never been written to has no revision recorded.
+    pub fn is_empty(&self) -> Result<bool> { Ok(self.get_meta("revision")?.is_none()) }
+
+    /// Copies the whole state of a `db.json` file into this database.
+    pub fn import_json(&self, json_path: &Path) -> Result<()> {
+        let db_state = super::JSONFileDatabase::new(json_path.to_path_buf())
+            .read()
+            .with_context(|| format!("Failed to import: {}", json_path.display()))?;
+
+        let transaction = self.connection.unchecked_transaction()?;
+        self.replace_all(&db_state)?;
+        transaction.commit().context("Failed to commit to database")
+    }
+
+    fn get_meta(&self, key: &str) -> Result<Option<i64>> {
+        let value = self
+            .connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_meta(&self, key: &str, value: i64) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    fn last_item_id(&self) -> Result<u32> {
+        Ok(self.get_meta("last_item_id")?.unwrap_or_default().try_into()?)
+    }
+
+    fn epic_ids(&self, project_id: u32) -> Result<Vec<u32>> {
+        let epic_ids = self
+            .connection
+            .prepare(
+                "SELECT epic_id FROM project_epics WHERE project_id = ?1 ORDER BY position",
+            )?
+            .query_map([project_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(epic_ids)
+    }
+
+    fn link_epics(&self, project_id: u32, epic_ids: &[u32]) -> Result<()> {
+        self.connection
+            .execute("DELETE FROM project_epics WHERE project_id = ?1", [project_id])?;
+
+        // Same as for stories: dangling and already linked epics are skipped.
+        let mut link = self.connection.prepare(
+            "INSERT OR IGNORE INTO project_epics (project_id, epic_id, position)
+             SELECT ?1, id, ?3 FROM epics WHERE id = ?2",
+        )?;
+        for (position, epic_id) in epic_ids.iter().enumerate() {
+            link.execute(params![project_id, epic_id, i64::try_from(position)?])?;
+        }
+
+        Ok(())
+    }
+
+    fn story_ids(&self, epic_id: u32) -> Result<Vec<u32>> {
+        let story_ids = self
+            .connection
+            .prepare("SELECT story_id FROM epic_stories WHERE epic_id = ?1 ORDER BY position")?
+            .query_map([epic_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(story_ids)
+    }
+
+    fn project_story_ids(&self, project_id: u32) -> Result<Vec<u32>> {
+        let story_ids = self
+            .connection
+            .prepare(
+                "SELECT es.story_id FROM epic_stories es
+                 JOIN project_epics pe ON pe.epic_id = es.epic_id
+                 WHERE pe.project_id = ?1",
+            )?
+            .query_map([project_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(story_ids)
+    }
+
+    fn link_stories(&self, epic_id: u32, story_ids: &[u32]) -> Result<()> {
+        self.connection.execute("DELETE FROM epic_stories WHERE epic_id = ?1", [epic_id])?;
+
+        // Dangling story ids have nothing to link to and a story can only be
+        // linked once, so both are skipped rather than failing the write.
+        let mut link = self.connection.prepare(
+            "INSERT OR IGNORE INTO epic_stories (epic_id, story_id, position)
+             SELECT ?1, id, ?3 FROM stories WHERE id = ?2",
+        )?;
+        for (position, story_id) in story_ids.iter().enumerate() {
+            link.execute(params![epic_id, story_id, i64::try_from(position)?])?;
+        }
+
+        Ok(())
+    }
+
+    /// The labels of an epic or story, `kind` naming which, in order.
+    fn labels(&self, kind: &str, item_id: u32) -> Result<Vec<String>> {
+        let labels = self
+            .connection
+            .prepare(&format!(
+                "SELECT label FROM {kind}_labels WHERE {kind}_id = ?1 ORDER BY label"
+            ))?
+            .query_map([item_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(labels)
+    }
+
+    fn set_labels(&self, kind: &str, item_id: u32, labels: &[String]) -> Result<()> {
+        self.connection
+            .execute(&format!("DELETE FROM {kind}_labels WHERE {kind}_id = ?1"), [item_id])?;
+
+        let mut insert = self.connection.prepare(&format!(
+            "INSERT OR IGNORE INTO {kind}_labels ({kind}_id, label) VALUES (?1, ?2)"
+        ))?;
+        for label in labels {
+            insert.execute(params![item_id, label])?;
+        }
+
+        Ok(())
+    }
+
+    fn replace_all(&self, db_state: &DBState) -> Result<()> {
+        self.connection.execute_batch(
+            "DELETE FROM epic_labels;
+             DELETE FROM story_labels;
+             DELETE FROM epic_stories;
+             DELETE FROM project_epics;
+             DELETE FROM stories;
+             DELETE FROM epics;
+             DELETE FROM projects;
+             DELETE FROM trash;
+             DELETE FROM users;
+             DELETE FROM meta;",
+        )?;
+
+        self.set_meta("revision", i64::try_from(db_state.revision)?)?;
+        self.set_meta("last_item_id", db_state.last_item_id.into())?;
+
+        for (id, story) in &db_state.stories {
+            self.insert_story(*id, story)?;
+        }
+
+        for (id, epic) in &db_state.epics {
+            self.insert_epic(*id, epic)?;
+        }
+
+        for (id, project) in &db_state.projects {
+            self.insert_project(*id, project)?;
+        }
+
+        for (id, item) in &db_state.trash {
+            self.insert_trash_item(*id, item)?;
+        }
+
+        for (username, user) in &db_state.users {
+            self.insert_user(username, user)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn project_from_row(row: &Row<'_>) -> rusqlite::Result<(u32, Project)> {
+    let project = Project {
+        name:        row.get(1)?,
+        key:         row.get(2)?,
+        description: row.get(3)?,
+        epics:       Vec::new(),
+    };
+    Ok((row.get(0)?, project))
+}
+
+fn epic_from_row(row: &Row<'_>) -> rusqlite::Result<(u32, Epic)> {
+    let epic = Epic {
+        name:        row.get(1)?,
+        description: row.get(2)?,
+        priority:    row.get(4)?,
+        status:      row.get(3)?,
+        stories:     Vec::new(),
+        owner:       row.get(5)?,
+        labels:      Vec::new(),
+        start_date:  row.get(6)?,
+        due_date:    row.get(7)?,
+    };
+    Ok((row.get(0)?, epic))
+}
+
+fn story_from_row(row: &Row<'_>) -> rusqlite::Result<(u32, Story)> {
+    let story = Story {
+        name:        row.get(1)?,
+        description: row.get(2)?,
+        priority:    row.get(4)?,
+        status:      row.get(3)?,
+        assignee:    row.get(5)?,
+        reporter:    row.get(6)?,
+        labels:      Vec::new(),
+        start_date:  row.get(7)?,
+        due_date:    row.get(8)?,
+    };
+    Ok((row.get(0)?, story))
+}
+
+type TrashRow = (u32, i64, String);
+
+fn trash_row(row: &Row<'_>) -> rusqlite::Result<TrashRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+}
+
+fn trash_item_from_row((id, deleted_at, item): TrashRow) -> Result<(u32, TrashItem)> {
+    let item = serde_json::from_str(&item)
+        .with_context(|| format!("Failed to parse trash item with id {id}"))?;
+    Ok((id, TrashItem { deleted_at: deleted_at.try_into()?, item }))
+}
+
+impl Database for SQLiteDatabase {
+    fn begin(&self, _operation: &str) -> Result<()> {
+        // Taking the write lock up front keeps the revision check and the
+        // writes that follow it atomic across processes.
+        self.connection.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(())
+    }
+
+    fn commit(&self) -> Result<u64> {
+        let revision = self.revision()? + 1;
+        self.set_meta("revision", i64::try_from(revision)?)?;
+
+        self.connection.execute_batch("COMMIT").context("Failed to commit to database")?;
+        Ok(revision)
+    }
+
+    fn rollback(&self) -> Result<()> {
+        if !self.connection.is_autocommit() {
+            self.connection.execute_batch("ROLLBACK")?;
+        }
+        Ok(())
+    }
+
+    fn revision(&self) -> Result<u64> {
+        Ok(self.get_meta("revision")?.unwrap_or_default().try_into()?)
+    }
+
+    fn next_id(&self) -> Result<u32> {
+        let id = self.last_item_id()? + 1;
+        self.set_meta("last_item_id", id.into())?;
+        Ok(id)
+    }
+
+    fn reserve_ids(&self, last_item_id: u32) -> Result<()> {
+        let last_item_id = last_item_id.max(self.last_item_id()?);
+        self.set_meta("last_item_id", last_item_id.into())
+    }
+
+    fn snapshot(&self) -> Result<DBState> {
+        let projects = self.list_projects()?.into_iter().collect();
+        let epics = self.list_epics(&EpicFilter::default())?.into_iter().collect();
+        let stories = self.list_stories(&StoryFilter::default())?.into_iter().collect();
+
+        let trash = self.list_trash()?.into_iter().collect();
+        let users = self.list_users()?.into_iter().collect();
+
+        Ok(DBState {
+            revision: self.revision()?,
+            last_item_id: self.last_item_id()?,
+            projects,
+            epics,
+            stories,
+            trash,
+            users,
+        })
+    }
+
+    fn get_project(&self, project_id: u32) -> Result<Option<Project>> {
+        let project = self
+            .connection
+            .query_row(
+                "SELECT id, name, key, description FROM projects WHERE id = ?1",
+                [project_id],
+                project_from_row,
+            )
+            .optional()?;
+
+        let Some((_, mut project)) = project else { return Ok(None) };
+        project.epics = self.epic_ids(project_id)?;
+        Ok(Some(project))
+    }
+
+    fn list_projects(&self) -> Result<Vec<(u32, Project)>> {
+        let mut projects = self
+            .connection
+            .prepare("SELECT id, name, key, description FROM projects ORDER BY id")?
+            .query_map([], project_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        for (project_id, project) in &mut projects {
+            project.epics = self.epic_ids(*project_id)?;
+        }
+
+        Ok(projects)
+    }
+
+    fn insert_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO projects (id, name, key, description) VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, project.name, project.key, project.description],
+        )?;
+        self.link_epics(project_id, &project.epics)
+    }
+
+    fn update_project(&self, project_id: u32, project: &Project) -> Result<()> {
+        let updated = self.connection.execute(
+            "UPDATE projects SET name = ?2, key = ?3, description = ?4 WHERE id = ?1",
+            params![project_id, project.name, project.key, project.description],
+        )?;
+        if updated == 0 {
+            bail!("Project with id {project_id} not found!");
+        }
+
+        self.link_epics(project_id, &project.epics)
+    }
+
+    fn delete_project(&self, project_id: u32) -> Result<()> {
+        self.connection.execute("DELETE FROM projects WHERE id = ?1", [project_id])?;
+        Ok(())
+    }
+
+    fn get_epic(&self, epic_id: u32) -> Result<Option<Epic>> {
+        let epic = self
+            .connection
+            .query_row(
+                "SELECT id, name, description, status, priority, owner, start_date, due_date
+                 FROM epics WHERE id = ?1",
+                [epic_id],
+                epic_from_row,
+            )
+            .optional()?;
+
+        let Some((_, mut epic)) = epic else { return Ok(None) };
+        epic.stories = self.story_ids(epic_id)?;
+        epic.labels = self.labels("epic", epic_id)?;
+        Ok(Some(epic))
+    }
+
+    fn list_epics(&self, filter: &EpicFilter) -> Result<Vec<(u32, Epic)>> {
+        let mut epics = match filter.project_id {
+            Some(project_id) => self
+                .connection
+                .prepare(
+                    "SELECT e.id, e.name, e.description, e.status, e.priority, e.owner,
+                         e.start_date, e.due_date
+                     FROM epics e JOIN project_epics pe ON pe.epic_id = e.id
+                     WHERE pe.project_id = ?1 ORDER BY e.id",
+                )?
+                .query_map([project_id], epic_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+            None => self
+                .connection
+                .prepare(
+                    "SELECT id, name, description, status, priority, owner, start_date, \
+                     due_date
+                     FROM epics ORDER BY id",
+                )?
+                .query_map([], epic_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+        };
+
+        for (epic_id, epic) in &mut epics {
+            epic.labels = self.labels("epic", *epic_id)?;
+        }
+        epics.retain(|(_, epic)| filter.matches(epic));
+        for (epic_id, epic) in &mut epics {
+            epic.stories = self.story_ids(*epic_id)?;
+        }
+
+        Ok(epics)
+    }
+
+    fn insert_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO epics (id, name, description, status, priority, owner, start_date,
+                 due_date)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                epic_id,
+                epic.name,
+                epic.description,
+                epic.status,
+                epic.priority,
+                epic.owner,
+                epic.start_date,
+                epic.due_date
+            ],
+        )?;
+        self.set_labels("epic", epic_id, &epic.labels)?;
+        self.link_stories(epic_id, &epic.stories)
+    }
+
+    fn update_epic(&self, epic_id: u32, epic: &Epic) -> Result<()> {
+        let updated = self.connection.execute(
+            "UPDATE epics SET name = ?2, description = ?3, status = ?4, priority = ?5,
+                 owner = ?6, start_date = ?7, due_date = ?8
+             WHERE id = ?1",
+            params![
+                epic_id,
+                epic.name,
+                epic.description,
+                epic.status,
+                epic.priority,
+                epic.owner,
+                epic.start_date,
+                epic.due_date
+            ],
+        )?;
+        if updated == 0 {
+            bail!("Epic with id {epic_id} not found!");
+        }
+
+        self.set_labels("epic", epic_id, &epic.labels)?;
+        self.link_stories(epic_id, &epic.stories)
+    }
+
+    fn delete_epic(&self, epic_id: u32) -> Result<()> {
+        self.connection.execute("DELETE FROM epics WHERE id = ?1", [epic_id])?;
+        Ok(())
+    }
+
+    fn get_story(&self, story_id: u32) -> Result<Option<Story>> {
+        let story = self
+            .connection
+            .query_row(
+                "SELECT id, name, description, status, priority, assignee, reporter,
+                     start_date, due_date
+                 FROM stories WHERE id = ?1",
+                [story_id],
+                story_from_row,
+            )
+            .optional()?;
+
+        let Some((_, mut story)) = story else { return Ok(None) };
+        story.labels = self.labels("story", story_id)?;
+        Ok(Some(story))
+    }
+
+    fn list_stories(&self, filter: &StoryFilter) -> Result<Vec<(u32, Story)>> {
+        let mut stories = match filter.epic_id {
+            Some(epic_id) => self
+                .connection
+                .prepare(
+                    "SELECT s.id, s.name, s.description, s.status, s.priority, s.assignee,
+                         s.reporter, s.start_date, s.due_date
+                     FROM stories s JOIN epic_stories es ON es.story_id = s.id
+                     WHERE es.epic_id = ?1 ORDER BY es.position",
+                )?
+                .query_map([epic_id], story_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+            None => self
+                .connection
+                .prepare(
+                    "SELECT id, name, description, status, priority, assignee, reporter,
+                         start_date, due_date
+                     FROM stories ORDER BY id",
+                )?
+                .query_map([], story_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+        };
+
+        if let Some(project_id) = filter.project_id {
+            let story_ids = self.project_story_ids(project_id)?;
+            stories.retain(|(id, _)| story_ids.contains(id));
+        }
+
+        for (story_id, story) in &mut stories {
+            story.labels = self.labels("story", *story_id)?;
+        }
+        stories.retain(|(_, story)| filter.matches(story));
+        Ok(stories)
+    }
+
+    fn insert_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO stories (id, name, description, status, priority, assignee, reporter,
+                 start_date, due_date)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                story_id,
+                story.name,
+                story.description,
+                story.status,
+                story.priority,
+                story.assignee,
+                story.reporter,
+                story.start_date,
+                story.due_date
+            ],
+        )?;
+        self.set_labels("story", story_id, &story.labels)
+    }
+
+    fn update_story(&self, story_id: u32, story: &Story) -> Result<()> {
+        let updated = self.connection.execute(
+            "UPDATE stories SET name = ?2, description = ?3, status = ?4, priority = ?5,
+                 assignee = ?6, reporter = ?7, start_date = ?8, due_date = ?9
+             WHERE id = ?1",
+            params![
+                story_id,
+                story.name,
+                story.description,
+                story.status,
+                story.priority,
+                story.assignee,
+                story.reporter,
+                story.start_date,
+                story.due_date
+            ],
+        )?;
+        if updated == 0 {
+            bail!("Story with id {story_id} not found!");
+        }
+        self.set_labels("story", story_id, &story.labels)
+    }
+
+    fn delete_story(&self, story_id: u32) -> Result<()> {
+        self.connection.execute("DELETE FROM stories WHERE id = ?1", [story_id])?;
+        Ok(())
+    }
+
+    fn get_trash_item(&self, item_id: u32) -> Result<Option<TrashItem>> {
+        let item = self
+            .connection
+            .query_row(
+                "SELECT id, deleted_at, item FROM trash WHERE id = ?1",
+                [item_id],
+                trash_row,
+            )
+            .optional()?;
+
+        let Some(row) = item else { return Ok(None) };
+        Ok(Some(trash_item_from_row(row)?.1))
+    }
+
+    fn list_trash(&self) -> Result<Vec<(u32, TrashItem)>> {
+        self.connection
+            .prepare("SELECT id, deleted_at, item FROM trash ORDER BY deleted_at DESC, id")?
+            .query_map([], trash_row)?
+            .map(|row| trash_item_from_row(row?))
+            .collect()
+    }
+
+    fn insert_trash_item(&self, item_id: u32, item: &TrashItem) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO trash (id, deleted_at, item) VALUES (?1, ?2, ?3)",
+            params![
+                item_id,
+                i64::try_from(item.deleted_at)?,
+                serde_json::to_string(&item.item)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_trash_item(&self, item_id: u32) -> Result<()> {
+        self.connection.execute("DELETE FROM trash WHERE id = ?1", [item_id])?;
+        Ok(())
+    }
+
+    fn get_user(&self, username: &str) -> Result<Option<User>> {
+        let user = self
+            .connection
+            .query_row("SELECT name FROM users WHERE username = ?1", [username], |row| {
+                Ok(User { name: row.get(0)? })
+            })
+            .optional()?;
+        Ok(user)
+    }
+
+    fn list_users(&self) -> Result<Vec<(String, User)>> {
+        let users = self
+            .connection
+            .prepare("SELECT username, name FROM users ORDER BY username")?
+            .query_map([], |row| Ok((row.get(0)?, User { name: row.get(1)? })))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(users)
+    }
+
+    fn insert_user(&self, username: &str, user: &User) -> Result<()> {
+        self.connection
+            .execute("INSERT INTO users (username, name) VALUES (?1, ?2)", params![
+                username, user.name
+            ])?;
+        Ok(())
+    }
+}
+
+impl ToSql for Status {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let status: &'static str = self.into();
+        Ok(status.into())
+    }
+}
+
+impl FromSql for Status {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "OPEN" => Ok(Status::Open),
+            "IN PROGRESS" => Ok(Status::InProgress),
+            "RESOLVED" => Ok(Status::Resolved),
+            "CLOSED" => Ok(Status::Closed),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for Priority {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let priority: &'static str = self.into();
+        Ok(priority.into())
+    }
+}
+
+impl FromSql for Priority {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
+impl ToSql for Date {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> { Ok(self.to_string().into()) }
+}
+
+impl FromSql for Date {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Trashed;
+
+    fn sample_state() -> DBState {
+        let mut epic = Epic::new("epic 1".to_string(), "epic 1".to_string());
+        epic.stories = vec![3, 2];
+        epic.labels = vec!["backend".to_string()];
+        epic.start_date = "2026-10-01".parse().ok();
+
+        let mut story = Story::new("story 2".to_string(), "story 2".to_string());
+        story.status = Status::InProgress;
+        story.priority = Priority::Highest;
+        story.assignee = Some("ana".to_string());
+        story.labels = vec!["tech-debt".to_string(), "ui".to_string()];
+        story.due_date = "2026-10-16".parse().ok();
+
+        let mut project =
+            Project::new("project 5".to_string(), "PRJ".to_string(), "".to_string());
+        project.epics = vec![1];
+
+        let mut db_state = DBState { revision: 4, last_item_id: 5, ..DBState::new() };
+        db_state.users.insert("ana".to_string(), User { name: "Ana Lima".to_string() });
+        db_state.projects.insert(5, project);
+        db_state.epics.insert(1, epic);
+        db_state.stories.insert(2, story);
+        db_state.stories.insert(3, Story::new("story 3".to_string(), "".to_string()));
+
+        let story = Story::new("story 4".to_string(), "".to_string());
+        let item = Trashed::Story { epic_id: 1, position: 2, story };
+        db_state.trash.insert(4, TrashItem { deleted_at: 1_700_000_000, item });
+        db_state
+    }
+
+    fn open_with_sample_state(dir: &Path) -> SQLiteDatabase {
+        let json_path = dir.join("db.json");
+        std::fs::write(&json_path, serde_json::to_vec(&sample_state()).unwrap()).unwrap();
+
+        let db = SQLiteDatabase::open(dir.join("db.sqlite")).unwrap();
+        db.import_json(&json_path).unwrap();
+        db
+    }
+
+    #[test]
+    fn open_should_start_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SQLiteDatabase::open(dir.path().join("db.sqlite")).unwrap();
+
+        assert!(db.is_empty().unwrap());
+        assert_eq!(db.snapshot().unwrap(), DBState::new());
+    }
+
+    #[test]
+    fn import_json_should_copy_state_and_story_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        assert!(!db.is_empty().unwrap());
+        assert_eq!(db.snapshot().unwrap(), sample_state());
+    }
+
+    #[test]
+    fn commit_should_persist_across_connections() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        db.begin("test").unwrap();
+        db.update_story(3, &Story::new("renamed".to_string(), "".to_string())).unwrap();
+        assert_eq!(db.commit().unwrap(), 5);
+
+        let db = SQLiteDatabase::open(dir.path().join("db.sqlite")).unwrap();
+        assert_eq!(db.get_story(3).unwrap().unwrap().name, "renamed");
+        assert_eq!(db.revision().unwrap(), 5);
+    }
+
+    #[test]
+    fn rollback_should_discard_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        db.begin("test").unwrap();
+        assert_eq!(db.next_id().unwrap(), 6);
+        db.delete_epic(1).unwrap();
+        db.rollback().unwrap();
+
+        assert_eq!(db.snapshot().unwrap(), sample_state());
+    }
+
+    #[test]
+    fn delete_epic_should_unlink_it_from_its_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        db.begin("test").unwrap();
+        db.delete_epic(1).unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(db.get_project(5).unwrap().unwrap().epics, Vec::<u32>::new());
+    }
+
+    #[test]
+    fn delete_story_should_unlink_it_from_its_epic() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        db.begin("test").unwrap();
+        db.delete_story(3).unwrap();
+        db.commit().unwrap();
+
+        assert_eq!(db.get_epic(1).unwrap().unwrap().stories, vec![2]);
+    }
+
+    #[test]
+    fn list_stories_should_follow_epic_order_and_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_with_sample_state(dir.path());
+
+        let filter = StoryFilter { epic_id: Some(1), ..StoryFilter::default() };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![3, 2]);
+
+        let filter = StoryFilter { status: Some(Status::InProgress), ..StoryFilter::default() };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![2]);
+
+        let filter = StoryFilter { project_id: Some(5), ..StoryFilter::default() };
+        let ids: Vec<u32> =
+            db.list_stories(&filter).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        let filter = EpicFilter { project_id: Some(1), ..EpicFilter::default() };
+        assert!(db.list_epics(&filter).unwrap().is_empty());
+    }
+
+    #[test]
+    fn open_should_add_priorities_to_older_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("db.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE stories (
+                     id          INTEGER PRIMARY KEY,
+                     name        TEXT NOT NULL,
+                     description TEXT NOT NULL,
+                     status      TEXT NOT NULL
+                 );
+                 INSERT INTO stories VALUES (1, 'story 1', '', 'OPEN');",
+            )
+            .unwrap();
+
+        let db = SQLiteDatabase::open(db_path).unwrap();
+        assert_eq!(db.get_story(1).unwrap().unwrap().priority, Priority::Medium);
+    }
+
+    #[test]
+    fn open_should_add_users_to_older_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("db.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE epics (
+                     id          INTEGER PRIMARY KEY,
+                     name        TEXT NOT NULL,
+                     description TEXT NOT NULL,
+                     status      TEXT NOT NULL,
+                     priority    TEXT NOT NULL DEFAULT 'MEDIUM'
+                 );
+                 CREATE TABLE stories (
+                     id          INTEGER PRIMARY KEY,
+                     name        TEXT NOT NULL,
+                     description TEXT NOT NULL,
+                     status      TEXT NOT NULL,
+                     priority    TEXT NOT NULL DEFAULT 'MEDIUM'
+                 );
+                 INSERT INTO epics VALUES (1, 'epic 1', '', 'OPEN', 'HIGH');
+                 INSERT INTO stories VALUES (2, 'story 2', '', 'OPEN', 'LOW');",
+            )
+            .unwrap();
+
+        let db = SQLiteDatabase::open(db_path).unwrap();
+        let epic = db.get_epic(1).unwrap().unwrap();
+        assert_eq!((epic.priority, epic.owner), (Priority::High, None));
+        let story = db.get_story(2).unwrap().unwrap();
+        assert_eq!((story.assignee, story.reporter), (None, None));
+    }
+
+    #[test]
+    fn open_should_add_dates_to_older_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("db.sqlite");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE epics (
+                     id          INTEGER PRIMARY KEY,
+                     name        TEXT NOT NULL,
+                     description TEXT NOT NULL,
+                     status      TEXT NOT NULL,
+                     priority    TEXT NOT NULL DEFAULT 'MEDIUM',
+                     owner       TEXT
+                 );
+                 CREATE TABLE stories (
+                     id          INTEGER PRIMARY KEY,
+                     name        TEXT NOT NULL,
+                     description TEXT NOT NULL,
+                     status      TEXT NOT NULL,
+                     priority    TEXT NOT NULL DEFAULT 'MEDIUM',
+                     assignee    TEXT,
+                     reporter    TEXT
+                 );
+                 INSERT INTO epics VALUES (1, 'epic 1', '', 'OPEN', 'MEDIUM', 'ana');
+                 INSERT INTO stories VALUES (2, 'story 2', '', 'OPEN', 'MEDIUM', 'ana', NULL);",
+            )
+            .unwrap();
+
+        let db = SQLiteDatabase::open(db_path).unwrap();
+        let epic = db.get_epic(1).unwrap().unwrap();
+        assert_eq!(epic.owner.as_deref(), Some("ana"));
+        assert_eq!((epic.start_date, epic.due_date), (None, None));
+        let story = db.get_story(2).unwrap().unwrap();
+        assert_eq!(story.assignee.as_deref(), Some("ana"));
+        assert_eq!((story.start_date, story.due_date), (None, None));
+    }
+
+    #[test]
+    fn update_should_fail_for_missing_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = SQLiteDatabase::open(dir.path().join("db.sqlite")).unwrap();
+
+        db.begin("test").unwrap();
+        assert!(db.update_epic(1, &Epic::new("".to_string(), "".to_string())).is_err());
+        assert!(db.update_story(1, &Story::new("".to_string(), "".to_string())).is_err());
+        db.rollback().unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp::Reverse;
+
+use anyhow::{Result, anyhow, bail};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{EpicFilter, StoryFilter};
+use crate::models::{DBState, Epic, Project, Story, TrashItem, User};
+
+/// A single entity-level mutation of an in-memory `DBState`, as staged by
+/// the document adapter and recorded by the event log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    AllocateId { id: u32 },
+    InsertProject { id: u32, project: Project },
+    UpdateProject { id: u32, project: Project },
+    DeleteProject { id: u32 },
+    InsertEpic { id: u32, epic: Epic },
+    UpdateEpic { id: u32, epic: Epic },
+    DeleteEpic { id: u32 },
+    InsertStory { id: u32, story: Story },
+    UpdateStory { id: u32, story: Story },
+    DeleteStory { id: u32 },
+    InsertTrashItem { id: u32, item: TrashItem },
+    DeleteTrashItem { id: u32 },
+    InsertUser { username: String, user: User },
+}
+
+impl Change {
+    pub fn apply(&self, db_state: &mut DBState) -> Result<()> {
+        match self {
+            Change::AllocateId { id } => {
+                db_state.last_item_id = db_state.last_item_id.max(*id);
+            },
+            Change::InsertProject { id, project } => {
+                if db_state.projects.contains_key(id) {
+                    bail!("Project with id {id} already exists!");
+                }
+                db_state.projects.insert(*id, project.clone());
+            },
+            Change::UpdateProject { id, project } => {
+                let stored = db_state
+                    .projects
+                    .get_mut(id)
+                    .ok_or_else(|| anyhow!("Project with id {id} not found!"))?;
+                *stored = project.clone();
+            },
+            Change::DeleteProject { id } => {
+                db_state.projects.remove(id);
+            },
+            Change::InsertEpic { id, epic } => {
+                if db_state.epics.contains_key(id) {
+                    bail!("Epic with id {id} already exists!");
+                }
+                db_state.epics.insert(*id, epic.clone());
+            },
+            Change::UpdateEpic { id, epic } => {
+                let stored = db_state
+                    .epics
+                    .get_mut(id)
+                    .ok_or_else(|| anyhow!("Epic with id {id} not found!"))?;
+                *stored = epic.clone();
+            },
+            Change::DeleteEpic { id } => {
+                db_state.epics.remove(id);
+            },
+            Change::InsertStory { id, story } => {
+                if db_state.stories.contains_key(id) {
+                    bail!("Story with id {id} already exists!");
+                }
+                db_state.stories.insert(*id, story.clone());
+            },
+            Change::UpdateStory { id, story } => {
+                let stored = db_state
+                    .stories
+                    .get_mut(id)
+                    .ok_or_else(|| anyhow!("Story with id {id} not found!"))?;
+                *stored = story.clone();
+            },
+            Change::DeleteStory { id } => {
+                db_state.stories.remove(id);
+            },
+            Change::InsertTrashItem { id, item } => {
+                if db_state.trash.contains_key(id) {
+                    bail!("Item with id {id} is already in the trash!");
+                }
+                db_state.trash.insert(*id, item.clone());
+            },
+            Change::DeleteTrashItem { id } => {
+                db_state.trash.remove(id);
+            },
+            Change::InsertUser { username, user } => {
+                if db_state.users.contains_key(username) {
+                    bail!("User {username} already exists!");
+                }
+                db_state.users.insert(username.clone(), user.clone());
+            },
+        }
+
+        Ok(())
+    }
+}
+
+pub fn list_projects(db_state: &DBState) -> Vec<(u32, Project)> {
+    db_state
+        .projects
+        .iter()
+        .sorted_by_key(|(id, _)| **id)
+        .map(|(id, project)| (*id, project.clone()))
+        .collect()
+}
+
+pub fn list_epics(db_state: &DBState, filter: &EpicFilter) -> Vec<(u32, Epic)> {
+    let epic_ids: Vec<u32> = match filter.project_id {
+        Some(project_id) => project_epic_ids(db_state, project_id).sorted().collect(),
+        None => db_state.epics.keys().copied().sorted().collect(),
+    };
+
+    epic_ids
+        .into_iter()
+        .filter_map(|id| db_state.epics.get(&id).map(|epic| (id, epic)))
+        .filter(|(_, epic)| filter.matches(epic))
+        .map(|(id, epic)| (id, epic.clone()))
+        .collect()
+}
+
+pub fn list_stories(db_state: &DBState, filter: &StoryFilter) -> Vec<(u32, Story)> {
+    let story_ids = match filter.epic_id {
+        Some(epic_id) => {
+            db_state.epics.get(&epic_id).map(|epic| epic.stories.clone()).unwrap_or_default()
+        },
+        None => db_state.stories.keys().copied().sorted().collect(),
+    };
+
+    let project_story_ids: Option<Vec<u32>> = filter.project_id.map(|project_id| {
+        project_epic_ids(db_state, project_id)
+            .filter_map(|epic_id| db_state.epics.get(&epic_id))
+            .flat_map(|epic| epic.stories.iter().copied())
+            .collect()
+    });
+
+    story_ids
+        .into_iter()
+        .filter(|id| project_story_ids.as_ref().is_none_or(|ids| ids.contains(id)))
+        .filter_map(|id| db_state.stories.get(&id).map(|story| (id, story)))
+        .filter(|(_, story)| filter.matches(story))
+        .map(|(id, story)| (id, story.clone()))
+        .collect()
+}
+
+pub fn list_trash(db_state: &DBState) -> Vec<(u32, TrashItem)> {
+    db_state
+        .trash
+        .iter()
+        .sorted_by_key(|(id, item)| (Reverse(item.deleted_at), **id))
+        .map(|(id, item)| (*id, item.clone()))
+        .collect()
+}
+
+pub fn list_users(db_state: &DBState) -> Vec<(String, User)> {
+    db_state
+        .users
+        .iter()
+        .sorted_by_key(|(username, _)| *username)
+        .map(|(username, user)| (username.clone(), user.clone()))
+        .collect()
+}
+
+fn project_epic_ids(db_state: &DBState, project_id: u32) -> impl Iterator<Item = u32> + '_ {
+    db_state
+        .projects
+        .get(&project_id)
+        .into_iter()
+        .flat_map(|project| project.epics.iter().copied())
+}
@@ -1,11 +1,23 @@
 use std::any::Any;
+use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, anyhow};
 use itertools::Itertools;
 
-use crate::database::JiraDatabase;
-use crate::models::Action;
+use crate::database::{EpicFilter, JiraDatabase, StoryFilter};
+use crate::models::{
+    Action,
+    Date,
+    IssueKey,
+    Priority,
+    Project,
+    Rank,
+    Schedule,
+    Story,
+    normalize_label,
+};
 
 mod helpers;
 use helpers::*;
@@ -16,77 +28,296 @@ pub trait Page {
     fn handle_input(&self, input: &str) -> Result<Option<Action>>;
 }
 
+const PROJECT_TABLE_HEADER: &str =
+    "---------------------------- PROJECTS ----------------------------";
 const EPIC_TABLE_HEADER: &str =
     "----------------------------- EPICS ------------------------------";
 const STORY_TABLE_HEADER: &str =
-    "---------------------------- STORIES -----------------------------";
+    "----------------------------------- STORIES -----------------------------------";
 const EPIC_DETAIL_HEADER: &str =
     "------------------------------ EPIC ------------------------------";
 const STORY_DETAIL_HEADER: &str =
     "------------------------------ STORY -----------------------------";
+const TRASH_TABLE_HEADER: &str =
+    "----------------------------- TRASH ------------------------------";
+const MY_WORK_TABLE_HEADER: &str =
+    "----------------------------------- MY WORK -----------------------------------";
 
+const PROJECT_COLUMN_HEADER: &str =
+    "    key     |               name               |      epics       ";
 const EPIC_COLUMN_HEADER: &str =
-    "     id     |               name               |      status      ";
+    "     id     |      name      | priority |   labels   |   status   ";
 const STORY_COLUMN_HEADER: &str =
-    "     id     |               name               |      status      ";
+    "     id     |      name      | priority |  assignee  |   labels   |   status   ";
+const TRASH_COLUMN_HEADER: &str =
+    "     id     |           name           |   type   |    deleted    ";
 const DETAIL_COLUMN_HEADER: &str =
-    "  id  |     name     |         description         |    status    ";
+    "     id     |    name    |  description  | priority |    status   ";
 
 fn print_table_row(
-    id: u32,
+    id: &str,
     name: &str,
     status: &str,
     id_width: usize,
     name_width: usize,
     status_width: usize,
 ) {
-    let id_col = get_column_string(&id.to_string(), id_width);
+    let id_col = get_column_string(id, id_width);
     let name_col = get_column_string(name, name_width);
     let status_col = get_column_string(status, status_width);
     println!("{id_col} | {name_col} | {status_col}");
 }
 
-fn print_detail_row(id: u32, name: &str, description: &str, status: &str) {
-    let id_col = get_column_string(&id.to_string(), 5);
-    let name_col = get_column_string(name, 12);
-    let desc_col = get_column_string(description, 27);
-    let status_col = get_column_string(status, 13);
-    println!("{id_col} | {name_col} | {desc_col} | {status_col}");
+/// Prints a row of the epic table.
+fn print_item_row(id: &str, name: &str, priority: Priority, labels: &[String], status: &str) {
+    let id_col = get_column_string(id, 11);
+    let name_col = get_column_string(name, 14);
+    let priority_col = get_column_string(&priority.to_string(), 8);
+    let labels_col = get_column_string(&labels.join(", "), 10);
+    let status_col = get_column_string(status, 11);
+    println!("{id_col} | {name_col} | {priority_col} | {labels_col} | {status_col}");
 }
 
-pub struct HomePage {
+/// Prints a row of the story tables, which also show the assignee.
+fn print_story_row(id: &str, story: &Story) {
+    let id_col = get_column_string(id, 11);
+    let name_col = get_column_string(&story.name, 14);
+    let priority_col = get_column_string(&story.priority.to_string(), 8);
+    let assignee_col = get_column_string(story.assignee.as_deref().unwrap_or("-"), 10);
+    let labels_col = get_column_string(&story.labels.join(", "), 10);
+    let status_col = get_column_string(&story.status.to_string(), 11);
+    println!(
+        "{id_col} | {name_col} | {priority_col} | {assignee_col} | {labels_col} | {status_col}"
+    );
+}
+
+/// Marks the id of an overdue item in the epic and story tables.
+fn flag_overdue(id: String, overdue: bool) -> String {
+    if overdue { format!("{id} !") } else { id }
+}
+
+/// Explains the overdue mark under a table that shows it.
+fn print_overdue_legend(any_overdue: bool) {
+    if any_overdue {
+        println!("\n! overdue");
+    }
+}
+
+/// Prints an item's start and due dates under its detail row.
+fn print_schedule(schedule: Schedule, overdue: bool) {
+    let date =
+        |date: Option<Date>| date.map_or_else(|| "-".to_string(), |date| date.to_string());
+    println!("Start: {}", date(schedule.start));
+    println!("Due: {}{}", date(schedule.due), if overdue { " (overdue)" } else { "" });
+}
+
+/// Prints an item's labels under its detail row.
+fn print_labels(labels: &[String]) {
+    if labels.is_empty() {
+        println!("Labels: -");
+    } else {
+        println!("Labels: {}", labels.join(", "));
+    }
+}
+
+fn print_detail_row(id: &str, name: &str, description: &str, priority: Priority, status: &str) {
+    let id_col = get_column_string(id, 11);
+    let name_col = get_column_string(name, 10);
+    let desc_col = get_column_string(description, 13);
+    let priority_col = get_column_string(&priority.to_string(), 8);
+    let status_col = get_column_string(status, 12);
+    println!("{id_col} | {name_col} | {desc_col} | {priority_col} | {status_col}");
+}
+
+/// Prints the whole description under a detail row when the row's column
+/// can't show it, e.g. because it spans several lines.
+fn print_full_description(description: &str) {
+    if fits_column(description, 13) {
+        return;
+    }
+
+    println!("\nDescription:");
+    for line in description.lines() {
+        println!("  {line}");
+    }
+}
+
+/// How a page lists epics or stories: in the board's own order or by
+/// priority, and either all of them or only those of one priority or label,
+/// or only the overdue ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListView {
+    pub by_priority: bool,
+    pub priority:    Option<Priority>,
+    /// A normalised label the listed items must have.
+    pub label:       Option<String>,
+    pub overdue:     bool,
+}
+
+impl ListView {
+    /// Reads `s`, which toggles sorting by priority, `f :priority:`, which
+    /// shows only that priority, `# :label:`, which shows only that label
+    /// (a bare `f` or `#` shows all again), or `o`, which toggles showing
+    /// only overdue items. Returns `None` for any other input.
+    fn updated(&self, input: &str) -> Option<Self> {
+        let view = self.clone();
+        match input.split_once(' ').unwrap_or((input, "")) {
+            ("s", "") => Some(Self { by_priority: !self.by_priority, ..view }),
+            ("o", "") => Some(Self { overdue: !self.overdue, ..view }),
+            ("f", "") => Some(Self { priority: None, ..view }),
+            ("f", priority) => Some(Self { priority: Some(priority.parse().ok()?), ..view }),
+            ("#", "") => Some(Self { label: None, ..view }),
+            ("#", label) => Some(Self { label: Some(normalize_label(label)?), ..view }),
+            _ => None,
+        }
+    }
+
+    /// Puts the most urgent items first when sorting by priority. The sort is
+    /// stable, so items of equal priority keep the board's order.
+    fn sort<T>(&self, items: &mut [(u32, T)], priority: impl Fn(&T) -> Priority) {
+        if self.by_priority {
+            items.sort_by_key(|(_, item)| priority(item));
+        }
+    }
+
+    /// The day items must be overdue on to be listed, if only overdue ones are.
+    fn overdue_on(&self) -> Option<Date> { self.overdue.then(Date::today) }
+
+    fn print_summary(&self) {
+        let filters = [
+            self.overdue.then(|| "overdue items".to_string()),
+            self.priority.map(|priority| format!("{priority} priority")),
+            self.label.as_ref().map(|label| format!("label {label}")),
+        ]
+        .into_iter()
+        .flatten()
+        .join(" and ");
+
+        match (self.by_priority, filters.is_empty()) {
+            (false, true) => {},
+            (true, true) => println!("\nSorted by priority"),
+            (false, false) => println!("\nShowing {filters} only"),
+            (true, false) => println!("\nSorted by priority, showing {filters} only"),
+        }
+    }
+}
+
+/// Applies a sort or filter input to a page's view, returning whether it was
+/// one.
+fn update_view(view: &RefCell<ListView>, input: &str) -> bool {
+    let updated = view.borrow().updated(input);
+    updated.map(|updated| view.replace(updated)).is_some()
+}
+
+fn get_project(database: &JiraDatabase, project_id: u32) -> Result<Project> {
+    database
+        .get_project(project_id)
+        .context("Failed to read from database")?
+        .ok_or_else(|| anyhow!("Project with id {project_id} not found!"))
+}
+
+pub struct ProjectsPage {
     pub database: Rc<JiraDatabase>,
 }
+
+impl Page for ProjectsPage {
+    fn draw_page(&self) -> Result<()> {
+        println!("{PROJECT_TABLE_HEADER}");
+        println!("{PROJECT_COLUMN_HEADER}");
+
+        let projects = self.database.list_projects().context("Failed to read from database")?;
+
+        projects.iter().sorted_by_key(|(_, project)| &project.key).for_each(|(_, project)| {
+            let epics = project.epics.len().to_string();
+            print_table_row(&project.key, &project.name, &epics, 11, 32, 17);
+        });
+
+        println!(
+            "\n\n[q] quit | [c] create project | [:key:] open project | [w] my work | [t] \
+             trash | [z] undo | [y] redo"
+        );
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>> {
+        match input {
+            "q" => Ok(Some(Action::Exit)),
+            "c" => Ok(Some(Action::CreateProject)),
+            "w" => Ok(Some(Action::NavigateToMyWork)),
+            "t" => Ok(Some(Action::NavigateToTrash)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            key => {
+                let project =
+                    self.database.find_project(key).context("Failed to read from database")?;
+                Ok(project.map(|(project_id, _)| Action::NavigateToProject { project_id }))
+            },
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any { self }
+}
+
+pub struct HomePage {
+    pub project_id: u32,
+    pub database:   Rc<JiraDatabase>,
+    pub view:       RefCell<ListView>,
+}
 impl Page for HomePage {
     fn draw_page(&self) -> Result<()> {
+        let project = get_project(&self.database, self.project_id)?;
+        println!("{}: {}\n", project.key, project.name);
+
         println!("{EPIC_TABLE_HEADER}");
         println!("{EPIC_COLUMN_HEADER}");
 
-        let db_state = self.database.read().context("Failed to read from database")?;
-
-        db_state.epics.iter().sorted_by_key(|(id, _)| *id).for_each(|(id, epic)| {
-            print_table_row(*id, &epic.name, &epic.status.to_string(), 11, 32, 17);
+        let view = self.view.borrow();
+        let filter = EpicFilter {
+            project_id: Some(self.project_id),
+            priority: view.priority,
+            label: view.label.clone(),
+            overdue_on: view.overdue_on(),
+            ..EpicFilter::default()
+        };
+        let mut epics =
+            self.database.list_epics(&filter).context("Failed to read from database")?;
+        view.sort(&mut epics, |epic| epic.priority);
+
+        let today = Date::today();
+        epics.iter().for_each(|(id, epic)| {
+            let status = epic.status.to_string();
+            let key = flag_overdue(project.issue_key(*id), epic.is_overdue(today));
+            print_item_row(&key, &epic.name, epic.priority, &epic.labels, &status);
         });
+        print_overdue_legend(epics.iter().any(|(_, epic)| epic.is_overdue(today)));
+        view.print_summary();
 
-        println!("\n\n[q] quit | [c] create epic | [:id:] navigate to epic");
+        println!(
+            "\n\n[p] projects | [q] quit | [c] create epic | [:key:] navigate to epic | [s] \
+             sort by priority | [f :priority:] filter by priority | [# :label:] filter by \
+             label | [o] overdue only | [t] trash | [z] undo | [y] redo"
+        );
         Ok(())
     }
 
     fn handle_input(&self, input: &str) -> Result<Option<Action>> {
         match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
             "q" => Ok(Some(Action::Exit)),
-            "c" => Ok(Some(Action::CreateEpic)),
-            input => match input.parse::<u32>() {
-                Ok(epic_id) => {
-                    let db_state =
-                        self.database.read().context("Failed to read from database")?;
-                    if db_state.epics.contains_key(&epic_id) {
-                        Ok(Some(Action::NavigateToEpicDetail { epic_id }))
-                    } else {
-                        Ok(None)
-                    }
-                },
-                Err(_) => Ok(None),
+            "c" => Ok(Some(Action::CreateEpic { project_id: self.project_id })),
+            "t" => Ok(Some(Action::NavigateToTrash)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            input if update_view(&self.view, input) => Ok(None),
+            input => {
+                let project = get_project(&self.database, self.project_id)?;
+                match project.parse_issue_key(input) {
+                    Some(epic_id) if project.epics.contains(&epic_id) => {
+                        let project_id = self.project_id;
+                        Ok(Some(Action::NavigateToEpicDetail { project_id, epic_id }))
+                    },
+                    _ => Ok(None),
+                }
             },
         }
     }
@@ -95,38 +326,69 @@ impl Page for HomePage {
 }
 
 pub struct EpicDetail {
-    pub epic_id:  u32,
-    pub database: Rc<JiraDatabase>,
+    pub project_id: u32,
+    pub epic_id:    u32,
+    pub database:   Rc<JiraDatabase>,
+    pub view:       RefCell<ListView>,
 }
 
 impl Page for EpicDetail {
     fn draw_page(&self) -> Result<()> {
-        let db_state = self.database.read().context("Failed to read from database")?;
-        let epic = db_state
-            .epics
-            .get(&self.epic_id)
+        let project = get_project(&self.database, self.project_id)?;
+        let epic = self
+            .database
+            .get_epic(self.epic_id)
+            .context("Failed to read from database")?
             .ok_or_else(|| anyhow!("Epic with id {} not found!", &self.epic_id))?;
 
         println!("{EPIC_DETAIL_HEADER}");
         println!("{DETAIL_COLUMN_HEADER}");
-        print_detail_row(self.epic_id, &epic.name, &epic.description, &epic.status.to_string());
+        print_detail_row(
+            &project.issue_key(self.epic_id),
+            &epic.name,
+            epic.description.lines().next().unwrap_or_default(),
+            epic.priority,
+            &epic.status.to_string(),
+        );
+        print_full_description(&epic.description);
+        println!("\nOwner: {}", epic.owner.as_deref().unwrap_or("-"));
+        print_labels(&epic.labels);
+        let today = Date::today();
+        print_schedule(Schedule::from(&epic), epic.is_overdue(today));
 
         println!();
 
         println!("{STORY_TABLE_HEADER}");
         println!("{STORY_COLUMN_HEADER}");
 
-        epic.stories
-            .iter()
-            .sorted()
-            .filter_map(|id| db_state.stories.get(id).map(|story| (*id, story)))
-            .for_each(|(id, story)| {
-                print_table_row(id, &story.name, &story.status.to_string(), 11, 32, 17);
-            });
+        let view = self.view.borrow();
+        let filter = StoryFilter {
+            epic_id: Some(self.epic_id),
+            priority: view.priority,
+            label: view.label.clone(),
+            overdue_on: view.overdue_on(),
+            ..StoryFilter::default()
+        };
+        let mut stories =
+            self.database.list_stories(&filter).context("Failed to read from database")?;
+        // Stories come in the epic's ranking, top of the backlog first.
+        view.sort(&mut stories, |story| story.priority);
+
+        stories.iter().for_each(|(id, story)| {
+            print_story_row(
+                &flag_overdue(project.issue_key(*id), story.is_overdue(today)),
+                story,
+            )
+        });
+        print_overdue_legend(stories.iter().any(|(_, story)| story.is_overdue(today)));
+        view.print_summary();
 
         println!(
-            "\n\n[p] previous | [u] update epic | [d] delete epic | [c] create story | [:id:] \
-             navigate to story"
+            "\n\n[p] previous | [u] update epic | [e] edit epic | [a] assign owner | [l] add \
+             label | [x] remove label | [n] set dates | [d] delete epic | [c] create story | \
+             [:key:] navigate to story | [+ :key:] rank up | [- :key:] rank down | [^ :key:] \
+             rank top | [s] sort by priority | [f :priority:] filter by priority | [# \
+             :label:] filter by label | [o] overdue only | [z] undo | [y] redo"
         );
         Ok(())
     }
@@ -135,22 +397,37 @@ impl Page for EpicDetail {
         match input {
             "p" => Ok(Some(Action::NavigateToPreviousPage)),
             "u" => Ok(Some(Action::UpdateEpicStatus { epic_id: self.epic_id })),
-            "d" => Ok(Some(Action::DeleteEpic { epic_id: self.epic_id })),
+            "e" => Ok(Some(Action::EditEpic { epic_id: self.epic_id })),
+            "a" => Ok(Some(Action::AssignEpic { epic_id: self.epic_id })),
+            "l" => Ok(Some(Action::AddLabel { item_id: self.epic_id })),
+            "x" => Ok(Some(Action::RemoveLabel { item_id: self.epic_id })),
+            "n" => Ok(Some(Action::ScheduleItem { item_id: self.epic_id })),
+            "d" => Ok(Some(Action::DeleteEpic {
+                project_id: self.project_id,
+                epic_id:    self.epic_id,
+            })),
             "c" => Ok(Some(Action::CreateStory { epic_id: self.epic_id })),
-            input => match input.parse::<u32>() {
-                Ok(story_id) => {
-                    let db_state =
-                        self.database.read().context("Failed to read from database")?;
-                    if db_state.stories.contains_key(&story_id) {
-                        Ok(Some(Action::NavigateToStoryDetail {
-                            epic_id: self.epic_id,
-                            story_id,
-                        }))
-                    } else {
-                        Ok(None)
-                    }
-                },
-                Err(_) => Ok(None),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            input if update_view(&self.view, input) => Ok(None),
+            input => {
+                let (rank, key) = match input.split_once(' ') {
+                    Some(("+", key)) => (Some(Rank::Up), key),
+                    Some(("-", key)) => (Some(Rank::Down), key),
+                    Some(("^", key)) => (Some(Rank::Top), key),
+                    _ => (None, input),
+                };
+                let Some(story_id) = self.epic_story(key)? else { return Ok(None) };
+
+                let epic_id = self.epic_id;
+                Ok(Some(match rank {
+                    Some(rank) => Action::RankStory { epic_id, story_id, rank },
+                    None => Action::NavigateToStoryDetail {
+                        project_id: self.project_id,
+                        epic_id,
+                        story_id,
+                    },
+                }))
             },
         }
     }
@@ -158,30 +435,54 @@ impl Page for EpicDetail {
     fn as_any(&self) -> &dyn Any { self }
 }
 
+impl EpicDetail {
+    /// Reads the key, or bare id, of one of this epic's stories.
+    fn epic_story(&self, input: &str) -> Result<Option<u32>> {
+        let project = get_project(&self.database, self.project_id)?;
+        let Some(story_id) = project.parse_issue_key(input) else { return Ok(None) };
+
+        let epic =
+            self.database.get_epic(self.epic_id).context("Failed to read from database")?;
+        Ok(epic.is_some_and(|epic| epic.stories.contains(&story_id)).then_some(story_id))
+    }
+}
+
 pub struct StoryDetail {
-    pub epic_id:  u32,
-    pub story_id: u32,
-    pub database: Rc<JiraDatabase>,
+    pub project_id: u32,
+    pub epic_id:    u32,
+    pub story_id:   u32,
+    pub database:   Rc<JiraDatabase>,
 }
 
 impl Page for StoryDetail {
     fn draw_page(&self) -> Result<()> {
-        let db_state = self.database.read().context("Failed to read from database")?;
-        let story = db_state
-            .stories
-            .get(&self.story_id)
+        let project = get_project(&self.database, self.project_id)?;
+        let story = self
+            .database
+            .get_story(self.story_id)
+            .context("Failed to read from database")?
             .ok_or_else(|| anyhow!("Story with id {} not found!", self.story_id))?;
 
         println!("{STORY_DETAIL_HEADER}");
         println!("{DETAIL_COLUMN_HEADER}");
         print_detail_row(
-            self.story_id,
+            &project.issue_key(self.story_id),
             &story.name,
-            &story.description,
+            story.description.lines().next().unwrap_or_default(),
+            story.priority,
             &story.status.to_string(),
         );
+        print_full_description(&story.description);
+        println!("\nAssignee: {}", story.assignee.as_deref().unwrap_or("-"));
+        println!("Reporter: {}", story.reporter.as_deref().unwrap_or("-"));
+        print_labels(&story.labels);
+        print_schedule(Schedule::from(&story), story.is_overdue(Date::today()));
 
-        println!("\n\n[p] previous | [u] update story | [d] delete story");
+        println!(
+            "\n\n[p] previous | [u] update story | [e] edit story | [a] assign story | [l] \
+             add label | [x] remove label | [n] set dates | [m] move story | [d] delete story \
+             | [z] undo | [y] redo"
+        );
         Ok(())
     }
 
@@ -189,10 +490,22 @@ impl Page for StoryDetail {
         match input {
             "p" => Ok(Some(Action::NavigateToPreviousPage)),
             "u" => Ok(Some(Action::UpdateStoryStatus { story_id: self.story_id })),
+            "e" => Ok(Some(Action::EditStory { story_id: self.story_id })),
+            "a" => Ok(Some(Action::AssignStory { story_id: self.story_id })),
+            "l" => Ok(Some(Action::AddLabel { item_id: self.story_id })),
+            "x" => Ok(Some(Action::RemoveLabel { item_id: self.story_id })),
+            "n" => Ok(Some(Action::ScheduleItem { item_id: self.story_id })),
+            "m" => Ok(Some(Action::MoveStory {
+                project_id: self.project_id,
+                epic_id:    self.epic_id,
+                story_id:   self.story_id,
+            })),
             "d" => Ok(Some(Action::DeleteStory {
                 epic_id:  self.epic_id,
                 story_id: self.story_id,
             })),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
             _ => Ok(None),
         }
     }
@@ -200,40 +513,249 @@ impl Page for StoryDetail {
     fn as_any(&self) -> &dyn Any { self }
 }
 
+/// A story's issue key, id and the story itself.
+type KeyedStory = (String, u32, Story);
+
+/// The stories assigned to the current user, across every project.
+pub struct MyWorkPage {
+    pub user:     Option<String>,
+    pub database: Rc<JiraDatabase>,
+}
+
+impl MyWorkPage {
+    /// The current user's stories, with their issue keys.
+    fn stories(&self) -> Result<Vec<KeyedStory>> {
+        let Some(user) = &self.user else { return Ok(Vec::new()) };
+
+        let filter = StoryFilter { assignee: Some(user.clone()), ..StoryFilter::default() };
+        let stories =
+            self.database.list_stories(&filter).context("Failed to read from database")?;
+        let keys = self.database.issue_keys().context("Failed to read from database")?;
+        Ok(stories
+            .into_iter()
+            .map(|(id, story)| {
+                (keys.get(&id).cloned().unwrap_or_else(|| id.to_string()), id, story)
+            })
+            .collect())
+    }
+
+    /// Finds the epic and project of a story, to open its detail page.
+    fn story_action(&self, story_id: u32) -> Result<Option<Action>> {
+        let Some(epic_id) = self.database.find_story_epic(story_id)? else { return Ok(None) };
+        let projects = self.database.list_projects().context("Failed to read from database")?;
+        let project =
+            projects.into_iter().find(|(_, project)| project.epics.contains(&epic_id));
+
+        Ok(project.map(|(project_id, _)| Action::NavigateToStoryDetail {
+            project_id,
+            epic_id,
+            story_id,
+        }))
+    }
+}
+
+impl Page for MyWorkPage {
+    fn draw_page(&self) -> Result<()> {
+        println!("{MY_WORK_TABLE_HEADER}");
+
+        match &self.user {
+            Some(user) => println!("Stories assigned to {user}\n"),
+            None => println!(
+                "No current user: set `user` in the config file, JIRAFFE_USER or $USER\n"
+            ),
+        }
+
+        println!("{STORY_COLUMN_HEADER}");
+        for (key, _, story) in self.stories()? {
+            print_story_row(&key, &story);
+        }
+
+        println!("\n\n[p] previous | [:key:] navigate to story | [z] undo | [y] redo");
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>> {
+        match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            input => {
+                let story = self.stories()?.into_iter().find(|(key, id, _)| {
+                    key.eq_ignore_ascii_case(input) || id.to_string() == input
+                });
+                match story {
+                    Some((_, story_id, _)) => self.story_action(story_id),
+                    None => Ok(None),
+                }
+            },
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any { self }
+}
+
+pub struct TrashPage {
+    pub database: Rc<JiraDatabase>,
+}
+
+impl TrashPage {
+    /// Reads the key, or bare id, of an item that is in the trash.
+    fn trashed_item(&self, input: &str) -> Result<Option<u32>> {
+        let item_id = if let Ok(item_id) = input.parse::<u32>() {
+            item_id
+        } else if let Ok(key) = input.parse::<IssueKey>() {
+            let keys = self.database.issue_keys().context("Failed to read from database")?;
+            if keys.get(&key.item_id) != Some(&key.to_string()) {
+                return Ok(None);
+            }
+            key.item_id
+        } else {
+            return Ok(None);
+        };
+
+        let trash = self.database.list_trash().context("Failed to read from database")?;
+        Ok(trash.iter().any(|(id, _)| *id == item_id).then_some(item_id))
+    }
+}
+
+impl Page for TrashPage {
+    fn draw_page(&self) -> Result<()> {
+        println!("{TRASH_TABLE_HEADER}");
+        println!("{TRASH_COLUMN_HEADER}");
+
+        let trash = self.database.list_trash().context("Failed to read from database")?;
+        let keys = self.database.issue_keys().context("Failed to read from database")?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+
+        trash.iter().for_each(|(id, trash_item)| {
+            let key = keys.get(id).cloned().unwrap_or_else(|| id.to_string());
+            let id_col = get_column_string(&key, 11);
+            let name_col = get_column_string(trash_item.item.name(), 24);
+            let kind_col = get_column_string(trash_item.item.kind(), 8);
+            let age = format_age(now.saturating_sub(trash_item.deleted_at));
+            let deleted_col = get_column_string(&age, 14);
+            println!("{id_col} | {name_col} | {kind_col} | {deleted_col}");
+        });
+
+        println!(
+            "\n\n[p] previous | [r :key:] restore | [x :key:] purge | [e] empty trash | [z] \
+             undo | [y] redo"
+        );
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>> {
+        match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
+            "e" => Ok(Some(Action::EmptyTrash)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            input => match input.split_once(' ') {
+                Some(("r", item_id)) => Ok(self
+                    .trashed_item(item_id)?
+                    .map(|item_id| Action::RestoreItem { item_id })),
+                Some(("x", item_id)) => {
+                    Ok(self.trashed_item(item_id)?.map(|item_id| Action::PurgeItem { item_id }))
+                },
+                _ => Ok(None),
+            },
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any { self }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::test_utils::MockDB;
-    use crate::models::{Epic, Story};
+    use crate::database::test_utils::{MockDB, test_project};
+    use crate::models::{Epic, Project, Story, User};
+
+    mod projects_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_fail() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            test_project(&db);
+
+            let page = ProjectsPage { database: db };
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+
+            let page = ProjectsPage { database: db };
+
+            assert_eq!(page.handle_input("q").unwrap(), Some(Action::Exit));
+            assert_eq!(page.handle_input("c").unwrap(), Some(Action::CreateProject));
+            assert_eq!(page.handle_input("w").unwrap(), Some(Action::NavigateToMyWork));
+            assert_eq!(page.handle_input("t").unwrap(), Some(Action::NavigateToTrash));
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
+            assert_eq!(
+                page.handle_input("TEST").unwrap(),
+                Some(Action::NavigateToProject { project_id })
+            );
+            assert_eq!(
+                page.handle_input("test").unwrap(),
+                Some(Action::NavigateToProject { project_id })
+            );
+            assert_eq!(page.handle_input("OTHER").unwrap(), None);
+            assert_eq!(page.handle_input(&project_id.to_string()).unwrap(), None);
+            assert_eq!(page.handle_input("").unwrap(), None);
+        }
+    }
 
     mod home_page {
         use super::*;
 
         #[test]
         fn draw_page_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let page = HomePage { database: db };
+            let page = HomePage { project_id, database: db, view: RefCell::default() };
             assert!(page.draw_page().is_ok());
         }
 
+        #[test]
+        fn draw_page_should_fail_for_invalid_project_id() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+
+            let page =
+                HomePage { project_id: 999, database: db, view: RefCell::default() };
+            assert!(page.draw_page().is_err());
+        }
+
         #[test]
         fn handle_input_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let page = HomePage { database: db };
+            let page = HomePage { project_id, database: db, view: RefCell::default() };
             assert!(page.handle_input("").is_ok());
         }
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
             let epic = Epic::new("".to_string(), "".to_string());
 
-            let epic_id = db.create_epic(epic).unwrap();
+            let epic_id = db.create_epic(epic, project_id).unwrap();
+
+            let other_project =
+                Project::new("".to_string(), "OTHER".to_string(), "".to_string());
+            let other_project_id = db.create_project(other_project).unwrap();
+            let other_epic = Epic::new("".to_string(), "".to_string());
+            let other_epic_id = db.create_epic(other_epic, other_project_id).unwrap();
 
-            let page = HomePage { database: db };
+            let page = HomePage { project_id, database: db, view: RefCell::default() };
 
             let q = "q";
             let c = "c";
@@ -243,12 +765,27 @@ mod tests {
             let junk_input_with_valid_prefix = "q983f2j";
             let input_with_trailing_white_spaces = "q\n";
 
+            assert_eq!(page.handle_input("p").unwrap(), Some(Action::NavigateToPreviousPage));
             assert_eq!(page.handle_input(q).unwrap(), Some(Action::Exit));
-            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateEpic));
+            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateEpic { project_id }));
+            assert_eq!(page.handle_input("t").unwrap(), Some(Action::NavigateToTrash));
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
             assert_eq!(
                 page.handle_input(&valid_epic_id).unwrap(),
-                Some(Action::NavigateToEpicDetail { epic_id: 1 })
+                Some(Action::NavigateToEpicDetail { project_id, epic_id })
             );
+            assert_eq!(
+                page.handle_input(&format!("TEST-{epic_id}")).unwrap(),
+                Some(Action::NavigateToEpicDetail { project_id, epic_id })
+            );
+            assert_eq!(
+                page.handle_input(&format!("test-{epic_id}")).unwrap(),
+                Some(Action::NavigateToEpicDetail { project_id, epic_id })
+            );
+            assert_eq!(page.handle_input(&format!("OTHER-{epic_id}")).unwrap(), None);
+            assert_eq!(page.handle_input(&other_epic_id.to_string()).unwrap(), None);
+            assert_eq!(page.handle_input(&format!("OTHER-{other_epic_id}")).unwrap(), None);
             assert_eq!(page.handle_input(invalid_epic_id).unwrap(), None);
             assert_eq!(page.handle_input(junk_input).unwrap(), None);
             assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
@@ -261,39 +798,50 @@ mod tests {
 
         #[test]
         fn draw_page_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
-            let page = EpicDetail { epic_id, database: db };
+            let page =
+                EpicDetail { project_id, epic_id, database: db, view: RefCell::default() };
             assert!(page.draw_page().is_ok());
         }
 
         #[test]
         fn handle_input_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
-            let page = EpicDetail { epic_id, database: db };
+            let page =
+                EpicDetail { project_id, epic_id, database: db, view: RefCell::default() };
             assert!(page.handle_input("").is_ok());
         }
 
         #[test]
         fn draw_page_should_fail_for_invalid_epic_id() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let page = EpicDetail { epic_id: 999, database: db };
+            let page =
+                EpicDetail { project_id, epic_id: 999, database: db, view: RefCell::default() };
             assert!(page.draw_page().is_err());
         }
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
             let story_id =
                 db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
-            let page = EpicDetail { epic_id, database: db };
+            let page =
+                EpicDetail { project_id, epic_id, database: db, view: RefCell::default() };
 
             let p = "p";
             let u = "u";
@@ -307,14 +855,52 @@ mod tests {
             assert_eq!(page.handle_input(p).unwrap(), Some(Action::NavigateToPreviousPage));
             assert_eq!(
                 page.handle_input(u).unwrap(),
-                Some(Action::UpdateEpicStatus { epic_id: 1 })
+                Some(Action::UpdateEpicStatus { epic_id })
+            );
+            assert_eq!(page.handle_input("e").unwrap(), Some(Action::EditEpic { epic_id }));
+            assert_eq!(page.handle_input("a").unwrap(), Some(Action::AssignEpic { epic_id }));
+            assert_eq!(
+                page.handle_input("l").unwrap(),
+                Some(Action::AddLabel { item_id: epic_id })
+            );
+            assert_eq!(
+                page.handle_input("x").unwrap(),
+                Some(Action::RemoveLabel { item_id: epic_id })
+            );
+            assert_eq!(
+                page.handle_input("n").unwrap(),
+                Some(Action::ScheduleItem { item_id: epic_id })
+            );
+            assert_eq!(page.handle_input("t").unwrap(), None);
+            assert_eq!(
+                page.handle_input(d).unwrap(),
+                Some(Action::DeleteEpic { project_id, epic_id })
             );
-            assert_eq!(page.handle_input(d).unwrap(), Some(Action::DeleteEpic { epic_id: 1 }));
-            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateStory { epic_id: 1 }));
+            assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateStory { epic_id }));
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
             assert_eq!(
                 page.handle_input(&story_id.to_string()).unwrap(),
-                Some(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 })
+                Some(Action::NavigateToStoryDetail { project_id, epic_id, story_id })
+            );
+            assert_eq!(
+                page.handle_input(&format!("TEST-{story_id}")).unwrap(),
+                Some(Action::NavigateToStoryDetail { project_id, epic_id, story_id })
             );
+            assert_eq!(
+                page.handle_input(&format!("+ TEST-{story_id}")).unwrap(),
+                Some(Action::RankStory { epic_id, story_id, rank: Rank::Up })
+            );
+            assert_eq!(
+                page.handle_input(&format!("- {story_id}")).unwrap(),
+                Some(Action::RankStory { epic_id, story_id, rank: Rank::Down })
+            );
+            assert_eq!(
+                page.handle_input(&format!("^ TEST-{story_id}")).unwrap(),
+                Some(Action::RankStory { epic_id, story_id, rank: Rank::Top })
+            );
+            assert_eq!(page.handle_input("^ 999").unwrap(), None);
+            assert_eq!(page.handle_input(&format!("TEST-{epic_id}")).unwrap(), None);
             assert_eq!(page.handle_input(invalid_story_id).unwrap(), None);
             assert_eq!(page.handle_input(junk_input).unwrap(), None);
             assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
@@ -322,54 +908,142 @@ mod tests {
         }
     }
 
+    #[test]
+    fn list_view_should_sort_and_filter_by_priority() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+
+        let page = EpicDetail { project_id, epic_id, database: db, view: RefCell::default() };
+
+        assert_eq!(page.handle_input("s").unwrap(), None);
+        assert_eq!(page.handle_input("f high").unwrap(), None);
+        assert_eq!(*page.view.borrow(), ListView {
+            by_priority: true,
+            priority:    Some(Priority::High),
+            label:       None,
+            overdue:     false,
+        });
+        assert!(page.draw_page().is_ok());
+
+        assert_eq!(page.handle_input("f urgent").unwrap(), None);
+        assert_eq!(page.view.borrow().priority, Some(Priority::High));
+        assert_eq!(page.handle_input("f").unwrap(), None);
+        assert_eq!(page.handle_input("s").unwrap(), None);
+        assert_eq!(*page.view.borrow(), ListView::default());
+        assert_eq!(
+            page.handle_input(&story_id.to_string()).unwrap(),
+            Some(Action::NavigateToStoryDetail { project_id, epic_id, story_id })
+        );
+
+        let mut items = vec![(1, Priority::Low), (2, Priority::Highest), (3, Priority::Low)];
+        let view = ListView { by_priority: true, ..ListView::default() };
+        view.sort(&mut items, |priority| *priority);
+        assert_eq!(items, vec![(2, Priority::Highest), (1, Priority::Low), (3, Priority::Low)]);
+    }
+
+    #[test]
+    fn list_view_should_filter_by_label() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.add_label(epic_id, "ui").unwrap();
+
+        let page = HomePage { project_id, database: db, view: RefCell::default() };
+
+        assert_eq!(page.handle_input("# Tech Debt").unwrap(), None);
+        assert_eq!(page.view.borrow().label.as_deref(), Some("tech-debt"));
+        assert!(page.draw_page().is_ok());
+
+        assert_eq!(page.handle_input("# a/b").unwrap(), None);
+        assert_eq!(page.view.borrow().label.as_deref(), Some("tech-debt"));
+        assert_eq!(page.handle_input("#").unwrap(), None);
+        assert_eq!(*page.view.borrow(), ListView::default());
+    }
+
+    #[test]
+    fn list_view_should_show_overdue_items_only() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let due = Some(Date::today().add_days(-1));
+        db.schedule(story_id, Schedule { start: None, due }).unwrap();
+
+        let page = EpicDetail { project_id, epic_id, database: db, view: RefCell::default() };
+
+        assert_eq!(page.handle_input("o").unwrap(), None);
+        assert!(page.view.borrow().overdue);
+        assert_eq!(page.view.borrow().overdue_on(), Some(Date::today()));
+        assert!(page.draw_page().is_ok());
+
+        assert_eq!(page.handle_input("o").unwrap(), None);
+        assert_eq!(*page.view.borrow(), ListView::default());
+        assert_eq!(page.view.borrow().overdue_on(), None);
+    }
+
     mod story_detail_page {
         use super::*;
 
         #[test]
         fn draw_page_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
             let story_id =
                 db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
-            let page = StoryDetail { epic_id, story_id, database: db };
+            let page = StoryDetail { project_id, epic_id, story_id, database: db };
             assert!(page.draw_page().is_ok());
         }
 
         #[test]
         fn handle_input_should_not_fail() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
             let story_id =
                 db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
-            let page = StoryDetail { epic_id, story_id, database: db };
+            let page = StoryDetail { project_id, epic_id, story_id, database: db };
             assert!(page.handle_input("").is_ok());
         }
 
         #[test]
         fn draw_page_should_fail_for_invalid_story_id() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
             let _ =
                 db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
-            let page = StoryDetail { epic_id, story_id: 999, database: db };
+            let page = StoryDetail { project_id, epic_id, story_id: 999, database: db };
             assert!(page.draw_page().is_err());
         }
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
 
-            let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
             let story_id =
                 db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
-            let page = StoryDetail { epic_id, story_id, database: db };
+            let page = StoryDetail { project_id, epic_id, story_id, database: db };
 
             let p = "p";
             let u = "u";
@@ -384,14 +1058,128 @@ mod tests {
                 page.handle_input(u).unwrap(),
                 Some(Action::UpdateStoryStatus { story_id })
             );
+            assert_eq!(page.handle_input("e").unwrap(), Some(Action::EditStory { story_id }));
+            assert_eq!(page.handle_input("a").unwrap(), Some(Action::AssignStory { story_id }));
+            assert_eq!(
+                page.handle_input("l").unwrap(),
+                Some(Action::AddLabel { item_id: story_id })
+            );
+            assert_eq!(
+                page.handle_input("x").unwrap(),
+                Some(Action::RemoveLabel { item_id: story_id })
+            );
+            assert_eq!(
+                page.handle_input("n").unwrap(),
+                Some(Action::ScheduleItem { item_id: story_id })
+            );
+            assert_eq!(page.handle_input("t").unwrap(), None);
+            assert_eq!(
+                page.handle_input("m").unwrap(),
+                Some(Action::MoveStory { project_id, epic_id, story_id })
+            );
             assert_eq!(
                 page.handle_input(d).unwrap(),
                 Some(Action::DeleteStory { epic_id, story_id })
             );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
             assert_eq!(page.handle_input(some_number).unwrap(), None);
             assert_eq!(page.handle_input(junk_input).unwrap(), None);
             assert_eq!(page.handle_input(junk_input_with_valid_prefix).unwrap(), None);
             assert_eq!(page.handle_input(input_with_trailing_white_spaces).unwrap(), None);
         }
     }
+
+    mod my_work_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_fail_without_a_user() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+
+            let page = MyWorkPage { user: None, database: db };
+            assert!(page.draw_page().is_ok());
+            assert_eq!(page.handle_input("1").unwrap(), None);
+        }
+
+        #[test]
+        fn handle_input_should_open_assigned_stories_only() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+            let story_id =
+                db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+            let other_id =
+                db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+            db.create_user("ana", User { name: "Ana".to_string() }).unwrap();
+            db.assign_story(story_id, Some("ana".to_string())).unwrap();
+
+            let page = MyWorkPage { user: Some("ana".to_string()), database: db };
+            assert!(page.draw_page().is_ok());
+
+            assert_eq!(page.handle_input("p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(
+                page.handle_input(&format!("test-{story_id}")).unwrap(),
+                Some(Action::NavigateToStoryDetail { project_id, epic_id, story_id })
+            );
+            assert_eq!(
+                page.handle_input(&story_id.to_string()).unwrap(),
+                Some(Action::NavigateToStoryDetail { project_id, epic_id, story_id })
+            );
+            assert_eq!(page.handle_input(&format!("TEST-{other_id}")).unwrap(), None);
+        }
+    }
+
+    mod trash_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_fail() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+            db.delete_epic(project_id, epic_id).unwrap();
+
+            let page = TrashPage { database: db };
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+            let project_id = test_project(&db);
+            let epic_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+            let live_id =
+                db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+            db.delete_epic(project_id, epic_id).unwrap();
+
+            let page = TrashPage { database: db };
+
+            assert_eq!(page.handle_input("p").unwrap(), Some(Action::NavigateToPreviousPage));
+            assert_eq!(page.handle_input("e").unwrap(), Some(Action::EmptyTrash));
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
+            assert_eq!(
+                page.handle_input(&format!("r {epic_id}")).unwrap(),
+                Some(Action::RestoreItem { item_id: epic_id })
+            );
+            assert_eq!(
+                page.handle_input(&format!("x {epic_id}")).unwrap(),
+                Some(Action::PurgeItem { item_id: epic_id })
+            );
+            assert_eq!(
+                page.handle_input(&format!("r TEST-{epic_id}")).unwrap(),
+                Some(Action::RestoreItem { item_id: epic_id })
+            );
+            assert_eq!(page.handle_input(&format!("r OTHER-{epic_id}")).unwrap(), None);
+            assert_eq!(page.handle_input(&format!("r {live_id}")).unwrap(), None);
+            assert_eq!(page.handle_input(&format!("r TEST-{live_id}")).unwrap(), None);
+            assert_eq!(page.handle_input("r 999").unwrap(), None);
+            assert_eq!(page.handle_input("r").unwrap(), None);
+            assert_eq!(page.handle_input("j983f2j").unwrap(), None);
+        }
+    }
 }
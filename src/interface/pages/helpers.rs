@@ -8,6 +8,20 @@ pub fn get_column_string(text: &str, width: usize) -> String {
     }
 }
 
+/// Whether `text` shows in full in a column `width` characters wide.
+pub fn fits_column(text: &str, width: usize) -> bool {
+    !text.contains('\n') && text.len() <= width
+}
+
+/// Describes how long ago something happened, to the day.
+pub fn format_age(seconds: u64) -> String {
+    match seconds / (24 * 60 * 60) {
+        0 => "today".to_string(),
+        1 => "1 day ago".to_string(),
+        days => format!("{days} days ago"),
+    }
+}
+
 fn truncate_with_ellipsis(text: &str, width: usize) -> String {
     match width {
         0 => "".to_string(),
@@ -48,4 +62,20 @@ mod tests {
         assert_eq!(get_column_string(text3, width), "testme".to_string());
         assert_eq!(get_column_string(text4, width), "tes...".to_string());
     }
+
+    #[test]
+    fn test_fits_column() {
+        assert!(fits_column("", 6));
+        assert!(fits_column("testme", 6));
+        assert!(!fits_column("testmetest", 6));
+        assert!(!fits_column("test\nme", 6));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(0), "today");
+        assert_eq!(format_age(24 * 60 * 60 - 1), "today");
+        assert_eq!(format_age(24 * 60 * 60), "1 day ago");
+        assert_eq!(format_age(3 * 24 * 60 * 60 + 5), "3 days ago");
+    }
 }
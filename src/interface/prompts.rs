@@ -1,22 +1,71 @@
-use crate::io::read_line;
-use crate::models::{Epic, Status, Story};
+use crate::io::{edit_in_editor, read_line};
+use crate::models::{
+    Date,
+    Details,
+    Epic,
+    Priority,
+    Project,
+    Schedule,
+    Status,
+    Story,
+    User,
+    normalize_label,
+};
+
+/// Asks for new details given the current ones.
+type EditPrompt = Box<dyn Fn(&Details) -> Details>;
+
+/// Asks which of the project's epics to pick, returning its id.
+type PickEpicPrompt = Box<dyn Fn(&Project, &[(u32, Epic)]) -> Option<u32>>;
+
+/// Asks which user to assign, returning `Some(None)` to unassign and `None`
+/// to cancel.
+type PickUserPrompt = Box<dyn Fn(&[(String, User)]) -> Option<Option<String>>>;
+
+/// Asks which of an item's labels to remove.
+type PickLabelPrompt = Box<dyn Fn(&[String]) -> Option<String>>;
+
+/// Asks for new dates given the current ones, returning `None` to cancel.
+type SchedulePrompt = Box<dyn Fn(&Schedule) -> Option<Schedule>>;
 
 pub struct Prompts {
-    pub create_epic:   Box<dyn Fn() -> Epic>,
-    pub create_story:  Box<dyn Fn() -> Story>,
-    pub delete_epic:   Box<dyn Fn() -> bool>,
-    pub delete_story:  Box<dyn Fn() -> bool>,
-    pub update_status: Box<dyn Fn() -> Option<Status>>,
+    pub create_project: Box<dyn Fn() -> Project>,
+    pub create_epic:    Box<dyn Fn() -> Epic>,
+    pub create_story:   Box<dyn Fn() -> Story>,
+    pub edit_epic:      EditPrompt,
+    pub edit_story:     EditPrompt,
+    pub delete_epic:    Box<dyn Fn() -> bool>,
+    pub delete_story:   Box<dyn Fn() -> bool>,
+    pub move_story:     PickEpicPrompt,
+    pub assign:         PickUserPrompt,
+    pub add_label:      Box<dyn Fn() -> Option<String>>,
+    pub remove_label:   PickLabelPrompt,
+    pub schedule:       SchedulePrompt,
+    pub update_status:  Box<dyn Fn() -> Option<Status>>,
+    pub retry_change:   Box<dyn Fn() -> bool>,
+    pub purge_item:     Box<dyn Fn() -> bool>,
+    pub empty_trash:    Box<dyn Fn() -> bool>,
 }
 
 impl Prompts {
     pub fn new() -> Self {
         Self {
-            create_epic:   Box::new(create_epic_prompt),
-            create_story:  Box::new(create_story_prompt),
-            delete_epic:   Box::new(delete_epic_prompt),
-            delete_story:  Box::new(delete_story_prompt),
-            update_status: Box::new(update_status_prompt),
+            create_project: Box::new(create_project_prompt),
+            create_epic:    Box::new(create_epic_prompt),
+            create_story:   Box::new(create_story_prompt),
+            edit_epic:      Box::new(|details| edit_details_prompt("Epic", details)),
+            edit_story:     Box::new(|details| edit_details_prompt("Story", details)),
+            delete_epic:    Box::new(delete_epic_prompt),
+            delete_story:   Box::new(delete_story_prompt),
+            move_story:     Box::new(move_story_prompt),
+            assign:         Box::new(assign_prompt),
+            add_label:      Box::new(add_label_prompt),
+            remove_label:   Box::new(remove_label_prompt),
+            schedule:       Box::new(schedule_prompt),
+            update_status:  Box::new(update_status_prompt),
+            retry_change:   Box::new(retry_change_prompt),
+            purge_item:     Box::new(purge_item_prompt),
+            empty_trash:    Box::new(empty_trash_prompt),
         }
     }
 }
@@ -25,32 +74,107 @@ impl Default for Prompts {
     fn default() -> Self { Self::new() }
 }
 
+fn create_project_prompt() -> Project {
+    println!("----------------------------");
+
+    println!("Project Name: ");
+    let project_name = read_line();
+
+    println!("Project Key (e.g. PAY): ");
+    let project_key = read_line();
+
+    println!("Project Description: ");
+    let project_desc = read_line();
+
+    Project::new(
+        project_name.trim().to_string(),
+        project_key.trim().to_uppercase(),
+        project_desc.trim().to_string(),
+    )
+}
+
 fn create_epic_prompt() -> Epic {
     println!("----------------------------");
 
     println!("Epic Name: ");
     let epic_name = read_line();
 
-    println!("Epic Description: ");
-    let epic_desc = read_line();
+    println!("Epic Description (! to write it in your editor): ");
+    let epic_desc = read_description("").unwrap_or_default();
+
+    println!("Epic {PRIORITY_CHOICES} [{}]: ", Priority::default());
+    let priority = read_priority(Priority::default());
 
-    Epic::new(epic_name.trim().to_string(), epic_desc.trim().to_string())
+    Epic { priority, ..Epic::new(epic_name.trim().to_string(), epic_desc) }
 }
 fn create_story_prompt() -> Story {
     println!("Story Name: ");
     let story_name = read_line();
 
-    println!("Story Description: ");
-    let story_desc = read_line();
+    println!("Story Description (! to write it in your editor): ");
+    let story_desc = read_description("").unwrap_or_default();
+
+    println!("Story {PRIORITY_CHOICES} [{}]: ", Priority::default());
+    let priority = read_priority(Priority::default());
+
+    Story { priority, ..Story::new(story_name.trim().to_string(), story_desc) }
+}
+
+/// Asks for a new name, description and priority, keeping the current one
+/// wherever the answer is left blank.
+fn edit_details_prompt(kind: &str, current: &Details) -> Details {
+    println!("----------------------------");
+
+    println!("{kind} Name [{}]: ", current.name);
+    let name = read_line();
 
-    Story::new(story_name.trim().to_string(), story_desc.trim().to_string())
+    println!("{kind} Description (! to edit it in your editor) [{}]: ", current.description);
+    let description = read_description(&current.description);
+
+    println!("{kind} {PRIORITY_CHOICES} [{}]: ", current.priority);
+    let priority = read_priority(current.priority);
+
+    let name = match name.trim() {
+        "" => current.name.clone(),
+        name => name.to_string(),
+    };
+    Details {
+        name,
+        description: description.unwrap_or_else(|| current.description.clone()),
+        priority,
+    }
+}
+
+const PRIORITY_CHOICES: &str =
+    "Priority (1 - HIGHEST, 2 - HIGH, 3 - MEDIUM, 4 - LOW, 5 - LOWEST)";
+
+/// Reads a priority by number or name, keeping `current` when the answer is
+/// blank or not a priority.
+fn read_priority(current: Priority) -> Priority { read_line().parse().unwrap_or(current) }
+
+/// Reads a one-line description, or opens the user's editor on `current` when
+/// the answer is `!` so the description can span several lines. Returns `None`
+/// for a blank answer.
+fn read_description(current: &str) -> Option<String> {
+    let input = read_line();
+    match input.trim() {
+        "" => None,
+        "!" => match edit_in_editor(current) {
+            Ok(description) => Some(description),
+            Err(error) => {
+                println!("{error:#}\nDescription: ");
+                read_description(current)
+            },
+        },
+        input => Some(input.to_string()),
+    }
 }
 
 fn delete_epic_prompt() -> bool {
     println!("----------------------------");
     println!(
         "Are you sure you want to delete this epic? All stories in this epic will also be \
-         deleted [Y/n]: "
+         moved to the trash [Y/n]: "
     );
 
     read_line().trim().to_lowercase().eq("y")
@@ -63,9 +187,122 @@ fn delete_story_prompt() -> bool {
     read_line().trim().to_lowercase().eq("y")
 }
 
+fn move_story_prompt(project: &Project, epics: &[(u32, Epic)]) -> Option<u32> {
+    println!("----------------------------");
+    for (epic_id, epic) in epics {
+        println!("{:<11} {}", project.issue_key(*epic_id), epic.name);
+    }
+    println!("Move story to epic (leave blank to cancel): ");
+
+    let epic_id = project.parse_issue_key(read_line().trim())?;
+    epics.iter().any(|(id, _)| *id == epic_id).then_some(epic_id)
+}
+
+fn assign_prompt(users: &[(String, User)]) -> Option<Option<String>> {
+    println!("----------------------------");
+    for (username, user) in users {
+        println!("{username:<11} {}", user.name);
+    }
+    println!("Assign to (- to unassign, leave blank to cancel): ");
+
+    match read_line().trim() {
+        "" => None,
+        "-" => Some(None),
+        username => {
+            let known = users.iter().any(|(name, _)| name == username);
+            known.then(|| Some(username.to_string()))
+        },
+    }
+}
+
+fn add_label_prompt() -> Option<String> {
+    println!("----------------------------");
+    println!("Label (leave blank to cancel): ");
+
+    let label = read_line();
+    let normalized = normalize_label(&label);
+    if normalized.is_none() && !label.trim().is_empty() {
+        println!("Invalid label {:?}: use up to 32 letters, digits or dashes", label.trim());
+    }
+    normalized
+}
+
+fn remove_label_prompt(labels: &[String]) -> Option<String> {
+    println!("----------------------------");
+    println!("Labels: {}", labels.join(", "));
+    println!("Remove label (leave blank to cancel): ");
+
+    let label = normalize_label(&read_line())?;
+    labels.contains(&label).then_some(label)
+}
+
+fn schedule_prompt(current: &Schedule) -> Option<Schedule> {
+    println!("----------------------------");
+    println!("Dates are YYYY-MM-DD, today, or relative like +3d or +2w.");
+    println!("Leave blank to keep a date, or enter - to clear it.");
+
+    let start = read_date_prompt("Start", current.start)?;
+    let due = read_date_prompt("Due", current.due)?;
+    if let (Some(start), Some(due)) = (start, due)
+        && start > due
+    {
+        println!("Start date {start} is after due date {due}");
+        return None;
+    }
+    Some(Schedule { start, due })
+}
+
+/// Reads one date of a schedule, returning `None` if it isn't a date.
+fn read_date_prompt(name: &str, current: Option<Date>) -> Option<Option<Date>> {
+    let current_text = current.map_or_else(|| "-".to_string(), |date| date.to_string());
+    println!("{name} date [{current_text}]: ");
+
+    match read_line().trim() {
+        "" => Some(current),
+        "-" | "none" => Some(None),
+        input => {
+            let date = Date::parse(input, Date::today());
+            if date.is_none() {
+                println!("Invalid date {input:?}");
+            }
+            date.map(Some)
+        },
+    }
+}
+
 fn update_status_prompt() -> Option<Status> {
     println!("----------------------------");
     println!("New Status (1 - OPEN, 2 - IN-PROGRESS, 3 - RESOLVED, 4 - CLOSED): ");
 
     read_line().parse().ok()
 }
+
+fn retry_change_prompt() -> bool {
+    println!("----------------------------");
+    println!(
+        "This board was changed by another session since the page was drawn. Retry against \
+         the latest version? [Y/n]: "
+    );
+
+    // Retrying is the default: declining throws away the change just made.
+    matches!(read_line().trim().to_lowercase().as_str(), "" | "y" | "yes")
+}
+
+fn purge_item_prompt() -> bool {
+    println!("----------------------------");
+    println!(
+        "Are you sure you want to permanently delete this item? This can't be undone [Y/n]: "
+    );
+
+    read_line().trim().to_lowercase().eq("y")
+}
+
+fn empty_trash_prompt() -> bool {
+    println!("----------------------------");
+    println!(
+        "Are you sure you want to permanently delete everything in the trash? This can't be \
+         undone [Y/n]: "
+    );
+
+    read_line().trim().to_lowercase().eq("y")
+}
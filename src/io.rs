@@ -1,4 +1,9 @@
-use std::io;
+use std::path::Path;
+use std::process::{self, Command};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
+
+use anyhow::{Context, Result, bail};
 
 pub fn read_line() -> String {
     let mut buffer = String::new();
@@ -7,3 +12,64 @@ pub fn read_line() -> String {
 }
 
 pub fn pause() { io::stdin().read_line(&mut String::new()).unwrap(); }
+
+/// Opens `$VISUAL`, `$EDITOR` or else `vi` on a temporary file holding `text`
+/// and returns the file's contents once the editor exits. Only the newline
+/// editors add at the end of the file is dropped; the rest is kept verbatim.
+pub fn edit_in_editor(text: &str) -> Result<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .find_map(|key| env::var(key).ok().filter(|editor| !editor.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+
+    edit_with(&editor, text)
+}
+
+/// Runs `editor`, which may carry arguments such as `code --wait`, on a
+/// temporary file that is removed afterwards.
+fn edit_with(editor: &str, text: &str) -> Result<String> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_nanos());
+    let path = env::temp_dir().join(format!("jiraffe-{}-{nanos}.txt", process::id()));
+    fs::write(&path, text)
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+
+    let edited = run_editor(editor, &path);
+    let _ = fs::remove_file(&path);
+
+    let edited = edited?;
+    let edited = edited.strip_suffix('\n').unwrap_or(&edited);
+    Ok(edited.strip_suffix('\r').unwrap_or(edited).to_string())
+}
+
+fn run_editor(editor: &str, path: &Path) -> Result<String> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("No editor set")?;
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run editor: {editor}"))?;
+    if !status.success() {
+        bail!("Editor {editor} exited with {status}");
+    }
+
+    fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_with_should_return_edited_text_verbatim() {
+        let edited = edit_with("sed -i s/draft/final/", "draft\n\n- [ ] criteria\n").unwrap();
+        assert_eq!(edited, "final\n\n- [ ] criteria");
+    }
+
+    #[test]
+    fn edit_with_should_fail_if_editor_fails() {
+        assert!(edit_with("false", "text").is_err());
+        assert!(edit_with("jiraffe-no-such-editor", "text").is_err());
+    }
+}
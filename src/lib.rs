@@ -1,3 +1,5 @@
+pub mod cli;
+pub mod config;
 pub mod database;
 pub mod io;
 pub mod navigator;
@@ -1,12 +1,103 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::rc::Rc;
+use std::{env, fmt, io};
 
+use anyhow::Result;
+use jiraffe::cli::{self, Command, USAGE_ERROR};
+use jiraffe::config::{Config, Settings};
 use jiraffe::database::JiraDatabase;
 use jiraffe::io::{pause, read_line};
 use jiraffe::navigator::Navigator;
 
-fn main() {
-    let db = Rc::new(JiraDatabase::new("./data/db.json".to_string()));
-    let mut navigator = Navigator::new(Rc::clone(&db));
+/// Reports a malformed command line the way every usage error is reported.
+fn usage_error(error: impl fmt::Display) -> ExitCode {
+    eprintln!("{error}\nRun `jiraffe --help` for usage.");
+    ExitCode::from(USAGE_ERROR)
+}
+
+fn main() -> Result<ExitCode> {
+    let mut cli_db = None;
+    let mut args = Vec::new();
+
+    let mut raw_args = env::args().skip(1);
+    while let Some(arg) = raw_args.next() {
+        if arg == "--db" {
+            let Some(path) = raw_args.next() else {
+                return Ok(usage_error("--db needs a path"));
+            };
+            cli_db = Some(PathBuf::from(path));
+        } else if let Some(path) = arg.strip_prefix("--db=") {
+            cli_db = Some(PathBuf::from(path));
+        } else {
+            args.push(arg);
+        }
+    }
+
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(error) => return Ok(usage_error(error)),
+    };
+
+    // Completion scripts are sourced at shell startup, so printing one mustn't
+    // depend on the database being there or the config being valid.
+    if let Some(Command::Completions { shell }) = command {
+        print!("{}", shell.script());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let env = |key: &str| env::var(key).ok();
+    let config = match Config::default_path(env) {
+        Some(path) => Config::load(&path, env)?,
+        None => Config::default(),
+    };
+    let settings = Settings::resolve(cli_db, env, config)?;
+
+    if let Some(Command::Complete { words }) = &command {
+        let out = &mut io::stdout().lock();
+        cli::complete_board(settings.backend, &settings.db_path, words, out)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let db = Rc::new(JiraDatabase::open(settings.backend, settings.db_path)?);
+
+    match command {
+        None => {
+            db.purge_expired(settings.trash_retention)?;
+            warn_about_integrity_issues(&db)?;
+            run(db, settings.user)?;
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(command) => {
+            match cli::execute(&db, settings.user.as_deref(), command, &mut io::stdout().lock())
+            {
+                Ok(code) => Ok(code),
+                Err(error) => {
+                    eprintln!("Error: {error:#}");
+                    Ok(ExitCode::FAILURE)
+                },
+            }
+        },
+    }
+}
+
+fn warn_about_integrity_issues(db: &JiraDatabase) -> Result<()> {
+    let issues = db.check_integrity()?;
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    println!("Warning: found {} integrity issue(s) in the database:", issues.len());
+    for issue in &issues {
+        println!("  {issue}");
+    }
+    println!("Run `jiraffe check --repair` to fix them.\nPress any key to continue...");
+    pause();
+    Ok(())
+}
+
+fn run(db: Rc<JiraDatabase>, user: Option<String>) -> Result<()> {
+    let mut navigator = Navigator::new(db).with_user(user);
 
     loop {
         clearscreen::clear().unwrap();
@@ -23,14 +114,14 @@ fn main() {
                     pause();
                 },
                 Ok(action) => {
-                    if let Some(action) = action {
-                        if let Err(error) = navigator.handle_action(action) {
-                            println!(
-                                "Error handling processing user input: {error}\nPress any key \
-                                 to continue..."
-                            );
-                            pause();
-                        }
+                    if let Some(action) = action
+                        && let Err(error) = navigator.handle_action(action)
+                    {
+                        println!(
+                            "Error handling processing user input: {error}\nPress any key to \
+                             continue..."
+                        );
+                        pause();
                     }
                 },
             }
@@ -38,4 +129,6 @@ fn main() {
             break;
         }
     }
+
+    Ok(())
 }
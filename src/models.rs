@@ -2,22 +2,77 @@ use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 
-use serde::{Deserialize, Serialize};
+use itertools::Itertools;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, PartialEq, Eq)]
+mod date;
+pub use date::Date;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
-    NavigateToEpicDetail { epic_id: u32 },
-    NavigateToStoryDetail { epic_id: u32, story_id: u32 },
+    NavigateToProject { project_id: u32 },
+    NavigateToEpicDetail { project_id: u32, epic_id: u32 },
+    NavigateToStoryDetail { project_id: u32, epic_id: u32, story_id: u32 },
     NavigateToPreviousPage,
-    CreateEpic,
+    CreateProject,
+    CreateEpic { project_id: u32 },
     UpdateEpicStatus { epic_id: u32 },
-    DeleteEpic { epic_id: u32 },
+    EditEpic { epic_id: u32 },
+    AssignEpic { epic_id: u32 },
+    DeleteEpic { project_id: u32, epic_id: u32 },
     CreateStory { epic_id: u32 },
     UpdateStoryStatus { story_id: u32 },
+    EditStory { story_id: u32 },
+    MoveStory { project_id: u32, epic_id: u32, story_id: u32 },
+    RankStory { epic_id: u32, story_id: u32, rank: Rank },
+    AssignStory { story_id: u32 },
+    AddLabel { item_id: u32 },
+    RemoveLabel { item_id: u32 },
+    ScheduleItem { item_id: u32 },
     DeleteStory { epic_id: u32, story_id: u32 },
+    NavigateToTrash,
+    NavigateToMyWork,
+    RestoreItem { item_id: u32 },
+    PurgeItem { item_id: u32 },
+    EmptyTrash,
+    Undo,
+    Redo,
     Exit,
 }
 
+/// Where to move a story in its epic's backlog, which is ranked top first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    Up,
+    Down,
+    Top,
+}
+
+impl Rank {
+    /// The position a story at `position` moves to, among `len` stories.
+    pub fn position(self, position: usize, len: usize) -> usize {
+        match self {
+            Rank::Up => position.saturating_sub(1),
+            Rank::Down => (position + 1).min(len.saturating_sub(1)),
+            Rank::Top => 0,
+        }
+    }
+}
+
+impl FromStr for Rank {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "up" => Ok(Rank::Up),
+            "down" => Ok(Rank::Down),
+            "top" => Ok(Rank::Top),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
     Closed,
@@ -26,6 +81,21 @@ pub enum Status {
     Resolved,
 }
 
+impl Status {
+    /// The lower-case name used on the command line and in JSON/CSV output.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Status::Open => "open",
+            Status::InProgress => "in-progress",
+            Status::Resolved => "resolved",
+            Status::Closed => "closed",
+        }
+    }
+
+    /// Whether work on the item is finished, so it can no longer be overdue.
+    pub fn is_done(&self) -> bool { matches!(self, Status::Resolved | Status::Closed) }
+}
+
 impl From<&Status> for &'static str {
     fn from(status: &Status) -> Self {
         match status {
@@ -40,12 +110,14 @@ impl From<&Status> for &'static str {
 impl FromStr for Status {
     type Err = ();
 
+    /// Accepts the number shown in the status prompt or the status name, e.g.
+    /// `2`, `in-progress` or `IN PROGRESS`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.trim() {
-            "1" => Ok(Status::Open),
-            "2" => Ok(Status::InProgress),
-            "3" => Ok(Status::Resolved),
-            "4" => Ok(Status::Closed),
+        match s.trim().to_ascii_lowercase().replace(['-', '_'], " ").as_str() {
+            "1" | "open" => Ok(Status::Open),
+            "2" | "in progress" => Ok(Status::InProgress),
+            "3" | "resolved" => Ok(Status::Resolved),
+            "4" | "closed" => Ok(Status::Closed),
             _ => Err(()),
         }
     }
@@ -68,17 +140,195 @@ impl TryFrom<u8> for Status {
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.into()) }
 }
+/// How urgent an epic or story is, most urgent first so that sorting puts
+/// the top priorities at the top.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    Highest,
+    High,
+    #[default]
+    Medium,
+    Low,
+    Lowest,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] =
+        [Priority::Highest, Priority::High, Priority::Medium, Priority::Low, Priority::Lowest];
+
+    /// The lower-case name used on the command line and in JSON/CSV output.
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Priority::Highest => "highest",
+            Priority::High => "high",
+            Priority::Medium => "medium",
+            Priority::Low => "low",
+            Priority::Lowest => "lowest",
+        }
+    }
+}
+
+impl From<&Priority> for &'static str {
+    fn from(priority: &Priority) -> Self {
+        match priority {
+            Priority::Highest => "HIGHEST",
+            Priority::High => "HIGH",
+            Priority::Medium => "MEDIUM",
+            Priority::Low => "LOW",
+            Priority::Lowest => "LOWEST",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = ();
+
+    /// Accepts the number shown in the priority prompt or the priority name,
+    /// e.g. `2` or `high`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "1" | "highest" => Ok(Priority::Highest),
+            "2" | "high" => Ok(Priority::High),
+            "3" | "medium" => Ok(Priority::Medium),
+            "4" | "low" => Ok(Priority::Low),
+            "5" | "lowest" => Ok(Priority::Lowest),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.into()) }
+}
+
+/// A product or team board that groups epics. Its `key` prefixes the ids of
+/// everything in it, e.g. `PAY-12`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Project {
+    pub name:        String,
+    pub key:         String,
+    pub description: String,
+    pub epics:       Vec<u32>,
+}
+
+impl Project {
+    pub fn new(name: String, key: String, description: String) -> Self {
+        Self { name, key, description, epics: Vec::with_capacity(8) }
+    }
+
+    /// Formats the id of an epic or story in this project, e.g. `PAY-12`.
+    pub fn issue_key(&self, item_id: u32) -> String {
+        IssueKey { project: self.key.clone(), item_id }.to_string()
+    }
+
+    /// Reads an item id typed on one of this project's pages, either as a key
+    /// like `PAY-12` or as the bare `12`. Keys of other projects don't match.
+    pub fn parse_issue_key(&self, input: &str) -> Option<u32> {
+        if let Ok(item_id) = input.parse::<u32>() {
+            return Some(item_id);
+        }
+
+        let key = input.parse::<IssueKey>().ok()?;
+        (key.project == self.key).then_some(key.item_id)
+    }
+}
+
+/// The human-readable id of an epic or story: its project's key followed by
+/// the item id, e.g. `PAY-12`. Item ids are unique across projects and never
+/// reused, so a key stays valid for as long as the item exists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueKey {
+    pub project: String,
+    pub item_id: u32,
+}
+
+impl FromStr for IssueKey {
+    type Err = ();
+
+    /// Parses a key ignoring case and surrounding whitespace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (project, item_id) = s.trim().rsplit_once('-').ok_or(())?;
+        if project.is_empty() || !project.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(());
+        }
+
+        let item_id = item_id.parse::<u32>().map_err(|_| ())?;
+        Ok(Self { project: project.to_ascii_uppercase(), item_id })
+    }
+}
+
+impl fmt::Display for IssueKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.project, self.item_id)
+    }
+}
+
+/// Turns a label as typed into the form it is stored and matched in: lower
+/// case, with runs of whitespace, dashes and underscores made into a single
+/// dash, e.g. `Tech Debt` into `tech-debt`. Returns `None` for labels that
+/// are empty, longer than 32 characters or hold anything but letters, digits
+/// and dashes once normalised.
+pub fn normalize_label(label: &str) -> Option<String> {
+    let label = label
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .join("-")
+        .to_lowercase();
+
+    let valid = (1..=32).contains(&label.chars().count())
+        && label.chars().all(|c| c.is_alphanumeric() || c == '-');
+    valid.then_some(label)
+}
+
+/// Someone who can own epics and be assigned stories. Users are known by
+/// their username, e.g. `ana`, which is unique on the board and never
+/// changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Epic {
     pub name:        String,
     pub description: String,
+    /// Boards saved before priorities existed read as `Medium`.
+    #[serde(default)]
+    pub priority:    Priority,
     pub status:      Status,
     pub stories:     Vec<u32>,
+    /// Username of the user who owns the epic.
+    #[serde(default)]
+    pub owner:       Option<String>,
+    /// Normalised labels, sorted and without duplicates.
+    #[serde(default)]
+    pub labels:      Vec<String>,
+    #[serde(default)]
+    pub start_date:  Option<Date>,
+    #[serde(default)]
+    pub due_date:    Option<Date>,
 }
 
 impl Epic {
     pub fn new(name: String, description: String) -> Self {
-        Self { name, description, status: Status::Open, stories: Vec::with_capacity(8) }
+        Self {
+            name,
+            description,
+            priority: Priority::default(),
+            status: Status::Open,
+            stories: Vec::with_capacity(8),
+            owner: None,
+            labels: Vec::new(),
+            start_date: None,
+            due_date: None,
+        }
+    }
+
+    /// Whether the epic is still being worked on past its due date.
+    pub fn is_overdue(&self, today: Date) -> bool {
+        !self.status.is_done() && self.due_date.is_some_and(|due| due < today)
     }
 }
 
@@ -86,24 +336,169 @@ impl Epic {
 pub struct Story {
     pub name:        String,
     pub description: String,
+    #[serde(default)]
+    pub priority:    Priority,
     pub status:      Status,
+    /// Username of the user working on the story.
+    #[serde(default)]
+    pub assignee:    Option<String>,
+    /// Username of the user who created the story.
+    #[serde(default)]
+    pub reporter:    Option<String>,
+    /// Normalised labels, sorted and without duplicates.
+    #[serde(default)]
+    pub labels:      Vec<String>,
+    #[serde(default)]
+    pub start_date:  Option<Date>,
+    #[serde(default)]
+    pub due_date:    Option<Date>,
 }
 
 impl Story {
     pub fn new(name: String, description: String) -> Self {
-        Self { name, description, status: Status::Open }
+        Self {
+            name,
+            description,
+            priority: Priority::default(),
+            status: Status::Open,
+            assignee: None,
+            reporter: None,
+            labels: Vec::new(),
+            start_date: None,
+            due_date: None,
+        }
+    }
+
+    /// Whether the story is still being worked on past its due date.
+    pub fn is_overdue(&self, today: Date) -> bool {
+        !self.status.is_done() && self.due_date.is_some_and(|due| due < today)
+    }
+}
+
+/// The name, description and priority of an epic or story, the parts a user
+/// can edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Details {
+    pub name:        String,
+    pub description: String,
+    pub priority:    Priority,
+}
+
+impl From<&Epic> for Details {
+    fn from(epic: &Epic) -> Self {
+        Self {
+            name:        epic.name.clone(),
+            description: epic.description.clone(),
+            priority:    epic.priority,
+        }
+    }
+}
+
+impl From<&Story> for Details {
+    fn from(story: &Story) -> Self {
+        Self {
+            name:        story.name.clone(),
+            description: story.description.clone(),
+            priority:    story.priority,
+        }
+    }
+}
+
+/// When work on an epic or story is planned to start and be done by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Schedule {
+    pub start: Option<Date>,
+    pub due:   Option<Date>,
+}
+
+impl From<&Epic> for Schedule {
+    fn from(epic: &Epic) -> Self { Self { start: epic.start_date, due: epic.due_date } }
+}
+
+impl From<&Story> for Schedule {
+    fn from(story: &Story) -> Self { Self { start: story.start_date, due: story.due_date } }
+}
+
+/// A deleted epic or story, kept under its original id until it is restored
+/// or purged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashItem {
+    /// Seconds since the Unix epoch.
+    pub deleted_at: u64,
+    #[serde(flatten)]
+    pub item:       Trashed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trashed {
+    /// An epic goes to the trash together with the stories it held, and
+    /// remembers where it sat in its project.
+    Epic {
+        #[serde(default)]
+        project_id: u32,
+        #[serde(default)]
+        position:   usize,
+        epic:       Epic,
+        #[serde(deserialize_with = "deserialize_id_map")]
+        stories:    HashMap<u32, Story>,
+    },
+    /// A story remembers where it sat so it can be put back in place.
+    Story { epic_id: u32, position: usize, story: Story },
+}
+
+/// Reads a map keyed by item id. Inside a flattened enum serde hands the
+/// keys over as strings, which `HashMap<u32, _>` won't accept by itself.
+fn deserialize_id_map<'de, D, V>(deserializer: D) -> Result<HashMap<u32, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    HashMap::<String, V>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(id, value)| Ok((id.parse().map_err(D::Error::custom)?, value)))
+        .collect()
+}
+
+impl Trashed {
+    pub fn name(&self) -> &str {
+        match self {
+            Trashed::Epic { epic, .. } => &epic.name,
+            Trashed::Story { story, .. } => &story.name,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Trashed::Epic { .. } => "EPIC",
+            Trashed::Story { .. } => "STORY",
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DBState {
+    /// Bumped on every successful write so stale writers can be detected.
+    pub revision:     u64,
     pub last_item_id: u32,
+    pub projects:     HashMap<u32, Project>,
     pub epics:        HashMap<u32, Epic>,
     pub stories:      HashMap<u32, Story>,
+    pub trash:        HashMap<u32, TrashItem>,
+    /// Keyed by username.
+    pub users:        HashMap<String, User>,
 }
 impl DBState {
     pub fn new() -> Self {
-        Self { last_item_id: 0, epics: HashMap::new(), stories: HashMap::new() }
+        Self {
+            revision:     0,
+            last_item_id: 0,
+            projects:     HashMap::new(),
+            epics:        HashMap::new(),
+            stories:      HashMap::new(),
+            trash:        HashMap::new(),
+            users:        HashMap::new(),
+        }
     }
 }
 
@@ -0,0 +1,179 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A calendar day, written `YYYY-MM-DD`. It is kept as a count of days since
+/// 1970-01-01, so dates compare and add up like numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    days: i32,
+}
+
+impl Date {
+    /// Today in UTC, as the board has no time zone of its own.
+    pub fn today() -> Self {
+        let seconds =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+        Self { days: (seconds / SECONDS_PER_DAY) as i32 }
+    }
+
+    /// Returns `None` for days that don't exist, e.g. February 30th.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        let valid =
+            (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day);
+        valid.then(|| Self { days: days_from_civil(year, month, day) })
+    }
+
+    pub fn add_days(self, days: i32) -> Self { Self { days: self.days + days } }
+
+    /// Reads a date as typed: `YYYY-MM-DD`, `today`, or a number of days or
+    /// weeks before or after today, e.g. `+3d`, `-1d` or `+2w`.
+    pub fn parse(input: &str, today: Date) -> Option<Self> {
+        let input = input.trim().to_ascii_lowercase();
+        if input == "today" {
+            return Some(today);
+        }
+
+        let (sign, offset) = match input.split_at_checked(1) {
+            Some(("+", offset)) => (1, offset),
+            Some(("-", offset)) => (-1, offset),
+            _ => return input.parse().ok(),
+        };
+        let (count, unit) = match (offset.strip_suffix('d'), offset.strip_suffix('w')) {
+            (Some(count), _) => (count, 1),
+            (_, Some(count)) => (count, 7),
+            _ => return None,
+        };
+
+        if !count.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let count = count.parse::<u16>().ok()?;
+        Some(today.add_days(sign * unit * i32::from(count)))
+    }
+}
+
+impl FromStr for Date {
+    type Err = ();
+
+    /// Accepts `YYYY-MM-DD` only; see `Date::parse` for relative dates.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split('-').collect::<Vec<_>>();
+        let [year, month, day] = parts.as_slice() else { return Err(()) };
+
+        let digits = |part: &str, len: usize| {
+            part.len() == len && part.chars().all(|c| c.is_ascii_digit())
+        };
+        if !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+            return Err(());
+        }
+
+        let number = |part: &str| part.parse::<u32>().map_err(|_| ());
+        Date::from_ymd(number(year)? as i32, number(month)?, number(day)?).ok_or(())
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.days);
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let date = String::deserialize(deserializer)?;
+        date.parse().map_err(|_| D::Error::custom(format!("invalid date {date:?}")))
+    }
+}
+
+fn is_leap_year(year: i32) -> bool { year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) }
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Counts the days from 1970-01-01 to a day of the proleptic Gregorian
+/// calendar, counting years from March so leap days come last.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i32 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400) as u32;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era as i32 - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i32) -> (i32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097) as u32;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = year_of_era as i32 + era * 400 + i32::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date { s.parse().unwrap() }
+
+    #[test]
+    fn from_str_should_read_calendar_days_only() {
+        assert_eq!(date("1970-01-01"), Date { days: 0 });
+        assert_eq!(date("2024-02-29").to_string(), "2024-02-29");
+        assert_eq!(date(" 1969-12-31 ").add_days(1), date("1970-01-01"));
+        assert_eq!(date("2000-03-01").add_days(-1), date("2000-02-29"));
+
+        for invalid in ["2023-02-29", "1900-02-29", "2026-13-01", "2026-00-10", "2026-4-1", ""]
+        {
+            assert_eq!(invalid.parse::<Date>(), Err(()), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn parse_should_read_relative_dates() {
+        let today = date("2026-12-30");
+
+        assert_eq!(Date::parse("today", today), Some(today));
+        assert_eq!(Date::parse("+3d", today), Some(date("2027-01-02")));
+        assert_eq!(Date::parse("-1D", today), Some(date("2026-12-29")));
+        assert_eq!(Date::parse("+2w", today), Some(date("2027-01-13")));
+        assert_eq!(Date::parse("2027-06-01", today), Some(date("2027-06-01")));
+
+        for invalid in ["+3", "3d", "+d", "+-3d", "++3d", "+3m", "+é"] {
+            assert_eq!(Date::parse(invalid, today), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn dates_should_round_trip_through_serde() {
+        let json = serde_json::to_string(&date("2026-10-16")).unwrap();
+        assert_eq!(json, "\"2026-10-16\"");
+        assert_eq!(serde_json::from_str::<Date>(&json).unwrap(), date("2026-10-16"));
+        assert!(serde_json::from_str::<Date>("\"16/10/2026\"").is_err());
+    }
+}
@@ -1,36 +1,86 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 
 use anyhow::{Context, Result, anyhow};
 
-use crate::database::JiraDatabase;
-use crate::interface::{EpicDetail, HomePage, Page, Prompts, StoryDetail};
-use crate::models::Action;
+use crate::database::{EpicFilter, JiraDatabase, RevisionConflict};
+use crate::interface::{
+    EpicDetail,
+    HomePage,
+    MyWorkPage,
+    Page,
+    ProjectsPage,
+    Prompts,
+    StoryDetail,
+    TrashPage,
+};
+use crate::models::{Action, Details, Schedule, Story, Trashed};
+
+mod history;
+use history::{Edit, History, Item};
 
 pub struct Navigator {
     pages:    Vec<Box<dyn Page>>,
     prompts:  Prompts,
     database: Rc<JiraDatabase>,
+    history:  History,
+    /// The username of whoever runs the board, if known.
+    user:     Option<String>,
 }
 
 impl Navigator {
     pub fn new(db: Rc<JiraDatabase>) -> Self {
         Self {
-            pages:    vec![Box::new(HomePage { database: Rc::clone(&db) })],
+            pages:    vec![Box::new(ProjectsPage { database: Rc::clone(&db) })],
             prompts:  Prompts::new(),
             database: db,
+            history:  History::default(),
+            user:     None,
         }
     }
 
+    /// Reports new stories as `user` and lists their work under "my work".
+    pub fn with_user(self, user: Option<String>) -> Self { Self { user, ..self } }
+
     pub fn get_current_page(&self) -> Option<&dyn Page> { self.pages.last().map(|v| &**v) }
 
     pub fn handle_action(&mut self, action: Action) -> Result<()> {
+        match self.dispatch_action(action) {
+            Err(error) if error.is::<RevisionConflict>() => {
+                if !(self.prompts.retry_change)() {
+                    return Ok(());
+                }
+
+                // Catch up with the other session's change before asking again.
+                self.database.read().context("Failed to read from database")?;
+                self.handle_action(action)
+            },
+            result => result,
+        }
+    }
+
+    fn dispatch_action(&mut self, action: Action) -> Result<()> {
         match action {
-            Action::NavigateToEpicDetail { epic_id } => {
-                let detail = EpicDetail { epic_id, database: self.database.clone() };
+            Action::NavigateToProject { project_id } => {
+                let home = HomePage {
+                    project_id,
+                    database: self.database.clone(),
+                    view: RefCell::default(),
+                };
+                self.pages.push(Box::new(home));
+            },
+            Action::NavigateToEpicDetail { project_id, epic_id } => {
+                let detail = EpicDetail {
+                    project_id,
+                    epic_id,
+                    database: self.database.clone(),
+                    view: RefCell::default(),
+                };
                 self.pages.push(Box::new(detail));
             },
-            Action::NavigateToStoryDetail { epic_id, story_id } => {
-                let detail = StoryDetail { epic_id, story_id, database: self.database.clone() };
+            Action::NavigateToStoryDetail { project_id, epic_id, story_id } => {
+                let database = self.database.clone();
+                let detail = StoryDetail { project_id, epic_id, story_id, database };
                 self.pages.push(Box::new(detail));
             },
             Action::NavigateToPreviousPage => {
@@ -38,24 +88,68 @@ impl Navigator {
                     self.pages.pop();
                 }
             },
-            Action::CreateEpic => {
-                let epic = (self.prompts.create_epic)();
+            Action::CreateProject => {
+                let project = (self.prompts.create_project)();
                 self.database
-                    .create_epic(epic)
+                    .create_project(project)
+                    .with_context(|| anyhow!("Failed to create project"))?;
+            },
+            Action::CreateEpic { project_id } => {
+                let epic = (self.prompts.create_epic)();
+                let epic_id = self
+                    .database
+                    .create_epic(epic, project_id)
                     .with_context(|| anyhow!("Failed to create epic"))?;
+
+                self.history.record(Edit::Create(Item::Epic { project_id, epic_id }));
             },
             Action::UpdateEpicStatus { epic_id } => {
                 if let Some(status) = (self.prompts.update_status)() {
-                    self.database
+                    let from = self
+                        .database
                         .update_epic_status(epic_id, status)
                         .with_context(|| anyhow!("Failed to update epic with id {epic_id}"))?;
+
+                    self.history.record(Edit::UpdateEpicStatus { epic_id, from, to: status });
                 }
             },
-            Action::DeleteEpic { epic_id } => {
+            Action::EditEpic { epic_id } => {
+                let epic = self
+                    .database
+                    .get_epic(epic_id)
+                    .context("Failed to read from database")?
+                    .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+
+                let details = (self.prompts.edit_epic)(&Details::from(&epic));
+                let from = self
+                    .database
+                    .update_epic_details(epic_id, details.clone())
+                    .with_context(|| anyhow!("Failed to edit epic with id {epic_id}"))?;
+
+                if from != details {
+                    self.history.record(Edit::UpdateEpicDetails { epic_id, from, to: details });
+                }
+            },
+            Action::AssignEpic { epic_id } => {
+                let users =
+                    self.database.list_users().context("Failed to read from database")?;
+                if let Some(to) = (self.prompts.assign)(&users) {
+                    let from = self
+                        .database
+                        .assign_epic(epic_id, to.clone())
+                        .with_context(|| anyhow!("Failed to assign epic with id {epic_id}"))?;
+
+                    if from != to {
+                        self.history.record(Edit::AssignEpic { epic_id, from, to });
+                    }
+                }
+            },
+            Action::DeleteEpic { project_id, epic_id } => {
                 if (self.prompts.delete_epic)() {
                     self.database
-                        .delete_epic(epic_id)
+                        .delete_epic(project_id, epic_id)
                         .with_context(|| anyhow!("Failed to delete epic with id {epic_id}"))?;
+                    self.history.record(Edit::Delete(Item::Epic { project_id, epic_id }));
 
                     if !self.pages.is_empty() {
                         self.pages.pop();
@@ -63,16 +157,152 @@ impl Navigator {
                 }
             },
             Action::CreateStory { epic_id } => {
-                let story = (self.prompts.create_story)();
-                self.database
+                let reporter = self.known_user()?;
+                let story = Story { reporter, ..(self.prompts.create_story)() };
+                let story_id = self
+                    .database
                     .create_story(story, epic_id)
                     .with_context(|| anyhow!("Failed to create story"))?;
+
+                self.history.record(Edit::Create(Item::Story { epic_id, story_id }));
             },
             Action::UpdateStoryStatus { story_id } => {
                 if let Some(status) = (self.prompts.update_status)() {
-                    self.database.update_story_status(story_id, status).with_context(|| {
-                        anyhow!("Failed to update story with id {story_id}")
+                    let from =
+                        self.database.update_story_status(story_id, status).with_context(
+                            || anyhow!("Failed to update story with id {story_id}"),
+                        )?;
+
+                    self.history.record(Edit::UpdateStoryStatus { story_id, from, to: status });
+                }
+            },
+            Action::EditStory { story_id } => {
+                let story = self
+                    .database
+                    .get_story(story_id)
+                    .context("Failed to read from database")?
+                    .ok_or_else(|| anyhow!("Story with id {story_id} not found!"))?;
+
+                let details = (self.prompts.edit_story)(&Details::from(&story));
+                let from = self
+                    .database
+                    .update_story_details(story_id, details.clone())
+                    .with_context(|| anyhow!("Failed to edit story with id {story_id}"))?;
+
+                if from != details {
+                    self.history.record(Edit::UpdateStoryDetails {
+                        story_id,
+                        from,
+                        to: details,
+                    });
+                }
+            },
+            Action::MoveStory { project_id, epic_id, story_id } => {
+                let project = self
+                    .database
+                    .get_project(project_id)
+                    .context("Failed to read from database")?
+                    .ok_or_else(|| anyhow!("Project with id {project_id} not found!"))?;
+                let filter =
+                    EpicFilter { project_id: Some(project_id), ..EpicFilter::default() };
+                let mut epics = self
+                    .database
+                    .list_epics(&filter)
+                    .context("Failed to read from database")?;
+                epics.retain(|(id, _)| *id != epic_id);
+
+                if let Some(to_epic) = (self.prompts.move_story)(&project, &epics) {
+                    let to_position = epics
+                        .iter()
+                        .find(|(id, _)| *id == to_epic)
+                        .map_or(0, |(_, epic)| epic.stories.len());
+                    let position = self
+                        .database
+                        .move_story(story_id, epic_id, to_epic)
+                        .with_context(|| anyhow!("Failed to move story with id {story_id}"))?;
+
+                    self.history.record(Edit::MoveStory {
+                        story_id,
+                        from_epic: epic_id,
+                        from_position: position,
+                        to_epic,
+                        to_position,
+                    });
+                    self.follow_moved_story()?;
+                }
+            },
+            Action::RankStory { epic_id, story_id, rank } => {
+                let epic = self
+                    .database
+                    .get_epic(epic_id)
+                    .context("Failed to read from database")?
+                    .ok_or_else(|| anyhow!("Epic with id {epic_id} not found!"))?;
+                let Some(from) = epic.stories.iter().position(|&id| id == story_id) else {
+                    return Err(anyhow!("Story with id {story_id} is not in this epic!"));
+                };
+
+                let to = rank.position(from, epic.stories.len());
+                if to != from {
+                    self.database
+                        .rank_story(epic_id, story_id, to)
+                        .with_context(|| anyhow!("Failed to rank story with id {story_id}"))?;
+                    self.history.record(Edit::MoveStory {
+                        story_id,
+                        from_epic: epic_id,
+                        from_position: from,
+                        to_epic: epic_id,
+                        to_position: to,
+                    });
+                }
+            },
+            Action::AssignStory { story_id } => {
+                let users =
+                    self.database.list_users().context("Failed to read from database")?;
+                if let Some(to) = (self.prompts.assign)(&users) {
+                    let from = self.database.assign_story(story_id, to.clone()).with_context(
+                        || anyhow!("Failed to assign story with id {story_id}"),
+                    )?;
+
+                    if from != to {
+                        self.history.record(Edit::AssignStory { story_id, from, to });
+                    }
+                }
+            },
+            Action::AddLabel { item_id } => {
+                if let Some(label) = (self.prompts.add_label)() {
+                    let added = self
+                        .database
+                        .add_label(item_id, &label)
+                        .with_context(|| anyhow!("Failed to label item with id {item_id}"))?;
+
+                    if let Some(label) = added {
+                        self.history.record(Edit::AddLabel { item_id, label });
+                    }
+                }
+            },
+            Action::RemoveLabel { item_id } => {
+                let labels = self.item_labels(item_id)?;
+                if let Some(label) = (self.prompts.remove_label)(&labels) {
+                    let removed = self
+                        .database
+                        .remove_label(item_id, &label)
+                        .with_context(|| anyhow!("Failed to unlabel item with id {item_id}"))?;
+
+                    if removed {
+                        self.history.record(Edit::RemoveLabel { item_id, label });
+                    }
+                }
+            },
+            Action::ScheduleItem { item_id } => {
+                let current = self.item_schedule(item_id)?;
+                if let Some(to) = (self.prompts.schedule)(&current) {
+                    let from = self.database.schedule(item_id, to).with_context(|| {
+                        anyhow!("Failed to schedule item with id {item_id}")
                     })?;
+
+                    if from != to {
+                        self.history.record(Edit::Schedule { item_id, from, to });
+                    }
                 }
             },
             Action::DeleteStory { epic_id, story_id } => {
@@ -80,18 +310,137 @@ impl Navigator {
                     self.database.delete_story(epic_id, story_id).with_context(|| {
                         anyhow!("failed to delete story with id {story_id}")
                     })?;
+                    self.history.record(Edit::Delete(Item::Story { epic_id, story_id }));
 
                     if !self.pages.is_empty() {
                         self.pages.pop();
                     }
                 }
             },
+            Action::NavigateToTrash => {
+                self.pages.push(Box::new(TrashPage { database: self.database.clone() }));
+            },
+            Action::NavigateToMyWork => {
+                let database = self.database.clone();
+                self.pages.push(Box::new(MyWorkPage { user: self.user.clone(), database }));
+            },
+            Action::RestoreItem { item_id } => {
+                let restored = self
+                    .database
+                    .restore(item_id)
+                    .with_context(|| anyhow!("Failed to restore item with id {item_id}"))?;
+
+                let item = match restored {
+                    Trashed::Epic { project_id, .. } => {
+                        Item::Epic { project_id, epic_id: item_id }
+                    },
+                    Trashed::Story { epic_id, .. } => {
+                        Item::Story { epic_id, story_id: item_id }
+                    },
+                };
+                self.history.record(Edit::Restore(item));
+            },
+            Action::PurgeItem { item_id } => {
+                if (self.prompts.purge_item)() {
+                    self.database
+                        .purge(item_id)
+                        .with_context(|| anyhow!("Failed to purge item with id {item_id}"))?;
+                }
+            },
+            Action::EmptyTrash => {
+                if (self.prompts.empty_trash)() {
+                    self.database
+                        .empty_trash()
+                        .with_context(|| anyhow!("Failed to empty trash"))?;
+                }
+            },
+            Action::Undo => {
+                self.history.undo(&self.database)?;
+                self.close_missing_pages()?;
+                self.follow_moved_story()?;
+            },
+            Action::Redo => {
+                self.history.redo(&self.database)?;
+                self.close_missing_pages()?;
+                self.follow_moved_story()?;
+            },
             Action::Exit => self.pages.clear(),
         }
 
         Ok(())
     }
 
+    /// The current user, if they are in the board's user directory.
+    fn known_user(&self) -> Result<Option<String>> {
+        let Some(username) = &self.user else { return Ok(None) };
+        let user = self.database.get_user(username).context("Failed to read from database")?;
+        Ok(user.map(|_| username.clone()))
+    }
+
+    /// The labels of an epic or story, or none if it no longer exists.
+    fn item_labels(&self, item_id: u32) -> Result<Vec<String>> {
+        if let Some(epic) =
+            self.database.get_epic(item_id).context("Failed to read from database")?
+        {
+            return Ok(epic.labels);
+        }
+
+        let story = self.database.get_story(item_id).context("Failed to read from database")?;
+        Ok(story.map(|story| story.labels).unwrap_or_default())
+    }
+
+    /// The dates of an epic or story, or none if it no longer exists.
+    fn item_schedule(&self, item_id: u32) -> Result<Schedule> {
+        if let Some(epic) =
+            self.database.get_epic(item_id).context("Failed to read from database")?
+        {
+            return Ok(Schedule::from(&epic));
+        }
+
+        let story = self.database.get_story(item_id).context("Failed to read from database")?;
+        Ok(story.map(|story| Schedule::from(&story)).unwrap_or_default())
+    }
+
+    /// Backs out of detail pages whose epic or story was just undone away.
+    fn close_missing_pages(&mut self) -> Result<()> {
+        while let Some(page) = self.pages.last() {
+            let page = page.as_any();
+
+            let missing = if let Some(detail) = page.downcast_ref::<EpicDetail>() {
+                self.database.get_epic(detail.epic_id)?.is_none()
+            } else if let Some(detail) = page.downcast_ref::<StoryDetail>() {
+                self.database.get_story(detail.story_id)?.is_none()
+            } else {
+                false
+            };
+
+            if !missing {
+                break;
+            }
+            self.pages.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Points an open story page at the epic the story is in now, after a
+    /// move or its undo took it out of the one the page was opened from.
+    fn follow_moved_story(&mut self) -> Result<()> {
+        let Some(page) = self.pages.last() else { return Ok(()) };
+        let Some(detail) = page.as_any().downcast_ref::<StoryDetail>() else { return Ok(()) };
+
+        let (project_id, story_id) = (detail.project_id, detail.story_id);
+        let Some(epic_id) = self.database.find_story_epic(story_id)? else { return Ok(()) };
+        if epic_id == detail.epic_id {
+            return Ok(());
+        }
+
+        let database = self.database.clone();
+        self.pages.pop();
+        self.pages.push(Box::new(StoryDetail { project_id, epic_id, story_id, database }));
+        Ok(())
+    }
+
     // Private functions used for testing
     #[cfg(test)]
     fn get_page_count(&self) -> usize { self.pages.len() }
@@ -103,56 +452,73 @@ impl Navigator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::database::test_utils::MockDB;
-    use crate::models::{Epic, Status, Story};
+    use crate::database::DocumentStore;
+    use crate::database::test_utils::{MockDB, test_project};
+    use crate::models::{Epic, Priority, Project, Rank, Status, Story, User};
 
     #[test]
-    fn should_start_on_home_page() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+    fn should_start_on_projects_page() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
         let nav = Navigator::new(db);
 
         assert_eq!(nav.get_page_count(), 1);
 
         let current_page = nav.get_current_page().unwrap();
-        let home_page = current_page.as_any().downcast_ref::<HomePage>();
+        let projects_page = current_page.as_any().downcast_ref::<ProjectsPage>();
 
-        assert!(home_page.is_some());
+        assert!(projects_page.is_some());
     }
 
     #[test]
     fn handle_action_should_navigate_pages() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
 
         let mut nav = Navigator::new(db);
 
-        nav.handle_action(Action::NavigateToEpicDetail { epic_id: 1 }).unwrap();
+        nav.handle_action(Action::NavigateToProject { project_id: 1 }).unwrap();
         assert_eq!(nav.get_page_count(), 2);
 
+        let current_page = nav.get_current_page().unwrap();
+        let home_page = current_page.as_any().downcast_ref::<HomePage>();
+        assert!(home_page.is_some());
+
+        nav.handle_action(Action::NavigateToEpicDetail { project_id: 1, epic_id: 2 }).unwrap();
+        assert_eq!(nav.get_page_count(), 3);
+
         let current_page = nav.get_current_page().unwrap();
         let epic_detail_page = current_page.as_any().downcast_ref::<EpicDetail>();
         assert!(epic_detail_page.is_some());
 
-        nav.handle_action(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 }).unwrap();
-        assert_eq!(nav.get_page_count(), 3);
+        let action =
+            Action::NavigateToStoryDetail { project_id: 1, epic_id: 2, story_id: 3 };
+        nav.handle_action(action).unwrap();
+        assert_eq!(nav.get_page_count(), 4);
 
         let current_page = nav.get_current_page().unwrap();
         let story_detail_page = current_page.as_any().downcast_ref::<StoryDetail>();
         assert!(story_detail_page.is_some());
 
         nav.handle_action(Action::NavigateToPreviousPage).unwrap();
-        assert_eq!(nav.get_page_count(), 2);
+        assert_eq!(nav.get_page_count(), 3);
 
         let current_page = nav.get_current_page().unwrap();
         let epic_detail_page = current_page.as_any().downcast_ref::<EpicDetail>();
         assert!(epic_detail_page.is_some());
 
         nav.handle_action(Action::NavigateToPreviousPage).unwrap();
-        assert_eq!(nav.get_page_count(), 1);
+        assert_eq!(nav.get_page_count(), 2);
 
         let current_page = nav.get_current_page().unwrap();
         let home_page = current_page.as_any().downcast_ref::<HomePage>();
         assert!(home_page.is_some());
 
+        nav.handle_action(Action::NavigateToPreviousPage).unwrap();
+        assert_eq!(nav.get_page_count(), 1);
+
+        let current_page = nav.get_current_page().unwrap();
+        let projects_page = current_page.as_any().downcast_ref::<ProjectsPage>();
+        assert!(projects_page.is_some());
+
         nav.handle_action(Action::NavigateToPreviousPage).unwrap();
         assert_eq!(nav.get_page_count(), 0);
 
@@ -162,12 +528,12 @@ mod tests {
 
     #[test]
     fn handle_action_should_clear_pages_on_exit() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
 
         let mut nav = Navigator::new(db);
 
-        nav.handle_action(Action::NavigateToEpicDetail { epic_id: 1 }).unwrap();
-        nav.handle_action(Action::NavigateToStoryDetail { epic_id: 1, story_id: 2 }).unwrap();
+        nav.handle_action(Action::NavigateToProject { project_id: 1 }).unwrap();
+        nav.handle_action(Action::NavigateToEpicDetail { project_id: 1, epic_id: 2 }).unwrap();
         nav.handle_action(Action::Exit).unwrap();
 
         assert_eq!(nav.get_page_count(), 0);
@@ -175,7 +541,8 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_create_epic() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
 
         let mut nav = Navigator::new(Rc::clone(&db));
 
@@ -185,7 +552,7 @@ mod tests {
 
         nav.set_prompts(prompts);
 
-        nav.handle_action(Action::CreateEpic).unwrap();
+        nav.handle_action(Action::CreateEpic { project_id }).unwrap();
 
         let db_state = db.read().unwrap();
         assert_eq!(db_state.epics.len(), 1);
@@ -195,10 +562,33 @@ mod tests {
         assert_eq!(epic.description, "description".to_string());
     }
 
+    #[test]
+    fn handle_action_should_handle_create_project() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.create_project = Box::new(|| {
+            Project::new("Payments".to_string(), "pay".to_string(), "".to_string())
+        });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateProject).unwrap();
+        assert!(nav.handle_action(Action::CreateProject).is_err());
+
+        let projects = db.list_projects().unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].1.key, "PAY");
+    }
+
     #[test]
     fn handle_action_should_handle_update_epic() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-        let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
         let mut nav = Navigator::new(Rc::clone(&db));
 
@@ -215,8 +605,10 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_delete_epic() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-        let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
         let mut nav = Navigator::new(Rc::clone(&db));
 
@@ -225,7 +617,7 @@ mod tests {
 
         nav.set_prompts(prompts);
 
-        nav.handle_action(Action::DeleteEpic { epic_id }).unwrap();
+        nav.handle_action(Action::DeleteEpic { project_id, epic_id }).unwrap();
 
         let db_state = db.read().unwrap();
         assert_eq!(db_state.epics.len(), 0);
@@ -233,8 +625,10 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_create_story() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-        let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
 
         let mut nav = Navigator::new(Rc::clone(&db));
 
@@ -256,8 +650,10 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_update_story() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-        let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
         let story_id =
             db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
@@ -274,10 +670,281 @@ mod tests {
         assert_eq!(db_state.stories.get(&story_id).unwrap().status, Status::InProgress);
     }
 
+    #[test]
+    fn handle_action_should_edit_and_undo_details() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id = db
+            .create_epic(Epic::new("Chekout".to_string(), "".to_string()), project_id)
+            .unwrap();
+        let story_id =
+            db.create_story(Story::new("Cart".to_string(), "".to_string()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.edit_epic = Box::new(|current| Details {
+            name:        "Checkout".to_string(),
+            description: current.description.clone(),
+            priority:    Priority::High,
+        });
+        prompts.edit_story = Box::new(|current| Details {
+            name:        current.name.clone(),
+            description: "Line items and totals".to_string(),
+            priority:    current.priority,
+        });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::EditEpic { epic_id }).unwrap();
+        nav.handle_action(Action::EditStory { story_id }).unwrap();
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().name, "Checkout");
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().priority, Priority::High);
+        assert_eq!(
+            db.get_story(story_id).unwrap().unwrap().description,
+            "Line items and totals"
+        );
+
+        nav.handle_action(Action::Undo).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().name, "Chekout");
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().priority, Priority::Medium);
+        assert_eq!(db.get_story(story_id).unwrap().unwrap().description, "");
+
+        nav.handle_action(Action::Redo).unwrap();
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().name, "Checkout");
+    }
+
+    #[test]
+    fn handle_action_should_move_story_and_undo_in_place() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let from =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let to = db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let first = db.create_story(Story::new("".to_string(), "".to_string()), from).unwrap();
+        let second = db.create_story(Story::new("".to_string(), "".to_string()), from).unwrap();
+        let other = db.create_story(Story::new("".to_string(), "".to_string()), to).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.move_story = Box::new(move |_, epics| {
+            assert_eq!(epics.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![to]);
+            Some(to)
+        });
+
+        nav.set_prompts(prompts);
+
+        let story_id = first;
+        nav.handle_action(Action::NavigateToStoryDetail {
+            project_id,
+            epic_id: from,
+            story_id,
+        })
+        .unwrap();
+        nav.handle_action(Action::MoveStory { project_id, epic_id: from, story_id }).unwrap();
+
+        assert_eq!(db.get_epic(from).unwrap().unwrap().stories, vec![second]);
+        assert_eq!(db.get_epic(to).unwrap().unwrap().stories, vec![other, first]);
+        let page = nav.get_current_page().unwrap();
+        assert_eq!(page.as_any().downcast_ref::<StoryDetail>().unwrap().epic_id, to);
+
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(db.get_epic(from).unwrap().unwrap().stories, vec![first, second]);
+        assert_eq!(db.get_epic(to).unwrap().unwrap().stories, vec![other]);
+        let page = nav.get_current_page().unwrap();
+        assert_eq!(page.as_any().downcast_ref::<StoryDetail>().unwrap().epic_id, from);
+    }
+
+    #[test]
+    fn handle_action_should_rank_stories_and_undo() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story = || Story::new("".to_string(), "".to_string());
+        let a = db.create_story(story(), epic_id).unwrap();
+        let b = db.create_story(story(), epic_id).unwrap();
+        let c = db.create_story(story(), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+        let stories = || db.get_epic(epic_id).unwrap().unwrap().stories;
+
+        nav.handle_action(Action::RankStory { epic_id, story_id: c, rank: Rank::Top }).unwrap();
+        assert_eq!(stories(), vec![c, a, b]);
+        nav.handle_action(Action::RankStory { epic_id, story_id: c, rank: Rank::Down })
+            .unwrap();
+        assert_eq!(stories(), vec![a, c, b]);
+        nav.handle_action(Action::RankStory { epic_id, story_id: a, rank: Rank::Up }).unwrap();
+        assert_eq!(stories(), vec![a, c, b]);
+
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(stories(), vec![c, a, b]);
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(stories(), vec![a, b, c]);
+        nav.handle_action(Action::Redo).unwrap();
+        nav.handle_action(Action::Redo).unwrap();
+        assert_eq!(stories(), vec![a, c, b]);
+    }
+
+    #[test]
+    fn handle_action_should_assign_and_undo() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.create_user("ana", User { name: "Ana".to_string() }).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db)).with_user(Some("ana".to_string()));
+
+        let mut prompts = Prompts::new();
+        prompts.create_story = Box::new(|| Story::new("".to_string(), "".to_string()));
+        prompts.assign = Box::new(|users| Some(Some(users[0].0.clone())));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateStory { epic_id }).unwrap();
+        let story_id = db.get_epic(epic_id).unwrap().unwrap().stories[0];
+        let story = || db.get_story(story_id).unwrap().unwrap();
+        assert_eq!(story().reporter.as_deref(), Some("ana"));
+
+        nav.handle_action(Action::AssignStory { story_id }).unwrap();
+        nav.handle_action(Action::AssignEpic { epic_id }).unwrap();
+        assert_eq!(story().assignee.as_deref(), Some("ana"));
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().owner.as_deref(), Some("ana"));
+
+        nav.handle_action(Action::NavigateToMyWork).unwrap();
+        let page = nav.get_current_page().unwrap().as_any().downcast_ref::<MyWorkPage>();
+        assert_eq!(page.unwrap().user.as_deref(), Some("ana"));
+
+        nav.handle_action(Action::Undo).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(story().assignee, None);
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().owner, None);
+        nav.handle_action(Action::Redo).unwrap();
+        assert_eq!(story().assignee.as_deref(), Some("ana"));
+    }
+
+    #[test]
+    fn handle_action_should_label_and_undo() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let labels = || db.get_epic(epic_id).unwrap().unwrap().labels;
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.add_label = Box::new(|| Some("ui".to_string()));
+        prompts.remove_label = Box::new(|labels| labels.first().cloned());
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::AddLabel { item_id: epic_id }).unwrap();
+        nav.handle_action(Action::AddLabel { item_id: epic_id }).unwrap();
+        assert_eq!(labels(), vec!["ui"]);
+
+        nav.handle_action(Action::RemoveLabel { item_id: epic_id }).unwrap();
+        assert!(labels().is_empty());
+
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(labels(), vec!["ui"]);
+        // Labelling twice was recorded once, so a second undo removes the
+        // label.
+        nav.handle_action(Action::Undo).unwrap();
+        assert!(labels().is_empty());
+    }
+
+    #[test]
+    fn handle_action_should_schedule_and_undo() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let story_id =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let schedule = || Schedule::from(&db.get_story(story_id).unwrap().unwrap());
+        let due = "2026-03-01".parse().ok();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.schedule = Box::new(move |current| Some(Schedule { due, ..*current }));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::ScheduleItem { item_id: story_id }).unwrap();
+        nav.handle_action(Action::ScheduleItem { item_id: story_id }).unwrap();
+        assert_eq!(schedule(), Schedule { start: None, due });
+
+        // Scheduling twice was recorded once, so one undo clears the date.
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(schedule(), Schedule::default());
+        nav.handle_action(Action::Redo).unwrap();
+        assert_eq!(schedule(), Schedule { start: None, due });
+    }
+
+    #[test]
+    fn handle_action_should_not_apply_stale_change_if_retry_declined() {
+        let store = MockDB::new();
+        let db = Rc::new(JiraDatabase::from_store(store.clone()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|| Some(Status::InProgress));
+        prompts.retry_change = Box::new(|| false);
+
+        nav.set_prompts(prompts);
+
+        let mut db_state = db.read().unwrap();
+        db_state.revision += 1;
+        store.write(&db_state).unwrap();
+
+        nav.handle_action(Action::UpdateEpicStatus { epic_id }).unwrap();
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::Open);
+    }
+
+    #[test]
+    fn handle_action_should_reapply_stale_change_if_retry_accepted() {
+        let store = MockDB::new();
+        let db = Rc::new(JiraDatabase::from_store(store.clone()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|| Some(Status::InProgress));
+        prompts.retry_change = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        let mut db_state = db.read().unwrap();
+        db_state.revision += 1;
+        store.write(&db_state).unwrap();
+
+        nav.handle_action(Action::UpdateEpicStatus { epic_id }).unwrap();
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::InProgress);
+        assert_eq!(db_state.revision, 4);
+    }
+
     #[test]
     fn handle_action_should_handle_delete_story() {
-        let db = Rc::new(JiraDatabase { database: Box::new(MockDB::new()) });
-        let epic_id = db.create_epic(Epic::new("".to_string(), "".to_string())).unwrap();
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
         let story_id =
             db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
 
@@ -293,4 +960,177 @@ mod tests {
         let db_state = db.read().unwrap();
         assert_eq!(db_state.stories.len(), 0);
     }
+
+    #[test]
+    fn undo_should_restore_deleted_epic_with_its_stories() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.create_story(Story::new("a".to_string(), "".to_string()), epic_id).unwrap();
+        db.create_story(Story::new("b".to_string(), "".to_string()), epic_id).unwrap();
+        let before = db.read().unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.delete_epic = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::NavigateToEpicDetail { project_id, epic_id }).unwrap();
+        nav.handle_action(Action::DeleteEpic { project_id, epic_id }).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+
+        let db_state = db.read().unwrap();
+        assert_eq!(db_state.epics, before.epics);
+        assert_eq!(db_state.stories, before.stories);
+
+        nav.handle_action(Action::Redo).unwrap();
+        assert!(db.read().unwrap().epics.is_empty());
+        assert!(db.read().unwrap().stories.is_empty());
+    }
+
+    #[test]
+    fn undo_should_restore_deleted_story_in_place() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let epic_id =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let first =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+        let second =
+            db.create_story(Story::new("".to_string(), "".to_string()), epic_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.delete_story = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::DeleteStory { epic_id, story_id: first }).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().stories, vec![first, second]);
+        assert!(db.get_story(first).unwrap().is_some());
+    }
+
+    #[test]
+    fn undo_and_redo_should_cover_create_and_status_updates() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.create_epic = Box::new(|| Epic::new("".to_string(), "".to_string()));
+        prompts.create_story = Box::new(|| Story::new("".to_string(), "".to_string()));
+        prompts.update_status = Box::new(|| Some(Status::Closed));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateEpic { project_id }).unwrap();
+        let epic_id = *db.read().unwrap().epics.keys().next().unwrap();
+        nav.handle_action(Action::CreateStory { epic_id }).unwrap();
+        let story_id = *db.read().unwrap().stories.keys().next().unwrap();
+        nav.handle_action(Action::UpdateStoryStatus { story_id }).unwrap();
+        nav.handle_action(Action::UpdateEpicStatus { epic_id }).unwrap();
+        let done = db.read().unwrap();
+
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(db.get_epic(epic_id).unwrap().unwrap().status, Status::Open);
+        nav.handle_action(Action::Undo).unwrap();
+        assert_eq!(db.get_story(story_id).unwrap().unwrap().status, Status::Open);
+        nav.handle_action(Action::Undo).unwrap();
+        assert!(db.get_story(story_id).unwrap().is_none());
+        nav.handle_action(Action::Undo).unwrap();
+        assert!(db.read().unwrap().epics.is_empty());
+
+        for _ in 0..4 {
+            nav.handle_action(Action::Redo).unwrap();
+        }
+        let redone = db.read().unwrap();
+        assert_eq!(redone.epics, done.epics);
+        assert_eq!(redone.stories, done.stories);
+    }
+
+    #[test]
+    fn new_change_should_clear_redo_stack() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.create_epic = Box::new(|| Epic::new("".to_string(), "".to_string()));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateEpic { project_id }).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+        nav.handle_action(Action::CreateEpic { project_id }).unwrap();
+        nav.handle_action(Action::Redo).unwrap();
+
+        assert_eq!(db.read().unwrap().epics.len(), 1);
+    }
+
+    #[test]
+    fn undo_should_close_pages_of_removed_items() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.create_epic = Box::new(|| Epic::new("".to_string(), "".to_string()));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateEpic { project_id }).unwrap();
+        let epic_id = *db.read().unwrap().epics.keys().next().unwrap();
+        nav.handle_action(Action::NavigateToEpicDetail { project_id, epic_id }).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+
+        assert_eq!(nav.get_page_count(), 1);
+    }
+
+    #[test]
+    fn handle_action_should_restore_and_purge_trash() {
+        let db = Rc::new(JiraDatabase::from_store(MockDB::new()));
+        let project_id = test_project(&db);
+        let first =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let second =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        let third =
+            db.create_epic(Epic::new("".to_string(), "".to_string()), project_id).unwrap();
+        db.delete_epic(project_id, first).unwrap();
+        db.delete_epic(project_id, second).unwrap();
+        db.delete_epic(project_id, third).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.purge_item = Box::new(|| true);
+        prompts.empty_trash = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::NavigateToTrash).unwrap();
+        let current_page = nav.get_current_page().unwrap();
+        assert!(current_page.as_any().downcast_ref::<TrashPage>().is_some());
+
+        nav.handle_action(Action::RestoreItem { item_id: first }).unwrap();
+        assert!(db.get_epic(first).unwrap().is_some());
+
+        nav.handle_action(Action::Undo).unwrap();
+        assert!(db.get_epic(first).unwrap().is_none());
+
+        nav.handle_action(Action::PurgeItem { item_id: second }).unwrap();
+        assert_eq!(db.list_trash().unwrap().len(), 2);
+
+        nav.handle_action(Action::EmptyTrash).unwrap();
+        assert!(db.list_trash().unwrap().is_empty());
+    }
 }
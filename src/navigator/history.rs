@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+
+use crate::database::JiraDatabase;
+use crate::models::{Details, Schedule, Status};
+
+/// How many edits can be undone before the oldest ones are forgotten.
+const UNDO_LIMIT: usize = 100;
+
+/// An epic or story that can be moved to the trash and back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Epic { project_id: u32, epic_id: u32 },
+    Story { epic_id: u32, story_id: u32 },
+}
+
+impl Item {
+    fn trash(self, database: &JiraDatabase) -> Result<()> {
+        match self {
+            Item::Epic { project_id, epic_id } => database.delete_epic(project_id, epic_id),
+            Item::Story { epic_id, story_id } => database.delete_story(epic_id, story_id),
+        }
+    }
+
+    fn restore(self, database: &JiraDatabase) -> Result<()> {
+        let item_id = match self {
+            Item::Epic { epic_id, .. } => epic_id,
+            Item::Story { story_id, .. } => story_id,
+        };
+
+        database.restore(item_id).map(drop)
+    }
+}
+
+/// A change made through the navigator, recorded so it can be undone.
+/// Undoing a creation moves the item to the trash rather than dropping it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Create(Item),
+    Delete(Item),
+    Restore(Item),
+    UpdateEpicStatus {
+        epic_id: u32,
+        from:    Status,
+        to:      Status,
+    },
+    UpdateStoryStatus {
+        story_id: u32,
+        from:     Status,
+        to:       Status,
+    },
+    UpdateEpicDetails {
+        epic_id: u32,
+        from:    Details,
+        to:      Details,
+    },
+    UpdateStoryDetails {
+        story_id: u32,
+        from:     Details,
+        to:       Details,
+    },
+    /// Giving an epic an owner or taking it away.
+    AssignEpic {
+        epic_id: u32,
+        from:    Option<String>,
+        to:      Option<String>,
+    },
+    AssignStory {
+        story_id: u32,
+        from:     Option<String>,
+        to:       Option<String>,
+    },
+    /// Labelling an epic or story, which removing the label undoes.
+    AddLabel {
+        item_id: u32,
+        label:   String,
+    },
+    RemoveLabel {
+        item_id: u32,
+        label:   String,
+    },
+    /// Setting or clearing an epic's or story's start and due dates.
+    Schedule {
+        item_id: u32,
+        from:    Schedule,
+        to:      Schedule,
+    },
+    /// Moving a story to another epic, or to another rank in the same one.
+    MoveStory {
+        story_id:      u32,
+        from_epic:     u32,
+        from_position: usize,
+        to_epic:       u32,
+        to_position:   usize,
+    },
+}
+
+impl Edit {
+    fn apply(&self, database: &JiraDatabase) -> Result<()> {
+        match self {
+            Edit::Create(item) | Edit::Restore(item) => item.restore(database),
+            Edit::Delete(item) => item.trash(database),
+            Edit::UpdateEpicStatus { epic_id, to, .. } => {
+                database.update_epic_status(*epic_id, *to).map(drop)
+            },
+            Edit::UpdateStoryStatus { story_id, to, .. } => {
+                database.update_story_status(*story_id, *to).map(drop)
+            },
+            Edit::UpdateEpicDetails { epic_id, to, .. } => {
+                database.update_epic_details(*epic_id, to.clone()).map(drop)
+            },
+            Edit::UpdateStoryDetails { story_id, to, .. } => {
+                database.update_story_details(*story_id, to.clone()).map(drop)
+            },
+            Edit::AssignEpic { epic_id, to, .. } => {
+                database.assign_epic(*epic_id, to.clone()).map(drop)
+            },
+            Edit::AssignStory { story_id, to, .. } => {
+                database.assign_story(*story_id, to.clone()).map(drop)
+            },
+            Edit::AddLabel { item_id, label } => database.add_label(*item_id, label).map(drop),
+            Edit::RemoveLabel { item_id, label } => {
+                database.remove_label(*item_id, label).map(drop)
+            },
+            Edit::Schedule { item_id, to, .. } => database.schedule(*item_id, *to).map(drop),
+            Edit::MoveStory { story_id, from_epic, to_epic, to_position, .. } => {
+                database.move_story_to(*story_id, *from_epic, *to_epic, *to_position).map(drop)
+            },
+        }
+    }
+
+    fn revert(&self, database: &JiraDatabase) -> Result<()> {
+        match self {
+            Edit::Create(item) | Edit::Restore(item) => item.trash(database),
+            Edit::Delete(item) => item.restore(database),
+            Edit::UpdateEpicStatus { epic_id, from, .. } => {
+                database.update_epic_status(*epic_id, *from).map(drop)
+            },
+            Edit::UpdateStoryStatus { story_id, from, .. } => {
+                database.update_story_status(*story_id, *from).map(drop)
+            },
+            Edit::UpdateEpicDetails { epic_id, from, .. } => {
+                database.update_epic_details(*epic_id, from.clone()).map(drop)
+            },
+            Edit::UpdateStoryDetails { story_id, from, .. } => {
+                database.update_story_details(*story_id, from.clone()).map(drop)
+            },
+            Edit::AssignEpic { epic_id, from, .. } => {
+                database.assign_epic(*epic_id, from.clone()).map(drop)
+            },
+            Edit::AssignStory { story_id, from, .. } => {
+                database.assign_story(*story_id, from.clone()).map(drop)
+            },
+            Edit::AddLabel { item_id, label } => {
+                database.remove_label(*item_id, label).map(drop)
+            },
+            Edit::RemoveLabel { item_id, label } => {
+                database.add_label(*item_id, label).map(drop)
+            },
+            Edit::Schedule { item_id, from, .. } => {
+                database.schedule(*item_id, *from).map(drop)
+            },
+            Edit::MoveStory { story_id, from_epic, from_position, to_epic, .. } => database
+                .move_story_to(*story_id, *to_epic, *from_epic, *from_position)
+                .map(drop),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl History {
+    /// Records a fresh edit, which makes anything undone so far unredoable.
+    pub fn record(&mut self, edit: Edit) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    /// Reverts the latest edit. An edit that can't be reverted, e.g. because
+    /// another session already changed the item, stays on the stack.
+    pub fn undo(&mut self, database: &JiraDatabase) -> Result<()> {
+        let Some(edit) = self.undo.pop() else { return Ok(()) };
+
+        if let Err(error) = edit.revert(database) {
+            self.undo.push(edit);
+            return Err(error).context("Failed to undo last change");
+        }
+
+        self.redo.push(edit);
+        Ok(())
+    }
+
+    pub fn redo(&mut self, database: &JiraDatabase) -> Result<()> {
+        let Some(edit) = self.redo.pop() else { return Ok(()) };
+
+        if let Err(error) = edit.apply(database) {
+            self.redo.push(edit);
+            return Err(error).context("Failed to redo last change");
+        }
+
+        self.undo.push(edit);
+        Ok(())
+    }
+}